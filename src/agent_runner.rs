@@ -3,6 +3,17 @@ use serial_test::serial;
 
 use crate::config::{anthropic_api_key, anthropic_base_url, claude_model};
 use crate::error::{NuClawError, Result};
+use crate::history::{build_turns, history_budget};
+use crate::failover::{global_breakers, FailoverChain};
+use crate::providers::{
+    provider_registry, ChatMessage, ChatOptions, ProviderRegistry, Usage, ANTHROPIC_CONTEXT_WINDOW,
+    ANTHROPIC_MAX_OUTPUT_TOKENS,
+};
+use crate::retry::{self, RetryPolicy};
+use crate::container_runner::ProgressSender;
+use crate::runtime::default_runtime;
+use crate::secrets::{group_secrets_for_run, with_tool_secrets, GroupSecrets};
+use crate::workflow::{load_workflow_config, WorkflowConfig};
 use crate::types::{ContainerInput, ContainerOutput};
 use crate::workspace_manager::WorkspaceManager;
 use async_trait::async_trait;
@@ -16,22 +27,84 @@ use rig::completion::Prompt;
 use rig::providers::anthropic;
 
 // Tool integration
-use crate::skill_to_rig::all_skills_to_tools;
-use crate::tool_registry::{ToolRegistry, InMemoryToolRegistry};
+use crate::skill_to_rig::{all_skills_to_tools, tools_to_rig, ToolCallLog};
+use crate::tool_registry::{Tool, ToolDefinition, ToolRegistry, InMemoryToolRegistry};
+use std::sync::Arc;
 use crate::skills::{builtin_skills, SkillRegistry};
 
+/// Build a tool registry populated with the builtin skills
+fn builtin_tool_registry() -> InMemoryToolRegistry {
+    let mut tool_registry = InMemoryToolRegistry::new();
+    let registry: &dyn SkillRegistry = &builtin_skills();
+    let skills = registry.list();
+    let tools = all_skills_to_tools(skills);
+    for tool in tools {
+        let name = tool.definition().name.clone();
+        if let Err(e) = tool_registry.register(tool) {
+            tracing::warn!("Failed to register tool {}: {}", name, e);
+        }
+    }
+    tool_registry
+}
+
+/// Execute the tool calls requested by the model and build `tool_result` blocks
+async fn execute_tool_calls(
+    registry: &dyn ToolRegistry,
+    calls: Vec<(String, String, serde_json::Value)>,
+) -> Vec<RequestBlock> {
+    let mut results = Vec::with_capacity(calls.len());
+
+    for (id, name, args) in calls {
+        tracing::info!("Executing tool call: {}", name);
+
+        let (content, is_error) = match registry.get(&name) {
+            Some(tool) => match tool.execute(args).await {
+                Ok(result) if result.success => (result.result.unwrap_or_default(), false),
+                Ok(result) => (
+                    result.error.unwrap_or_else(|| "Tool execution failed".to_string()),
+                    true,
+                ),
+                Err(e) => (e.to_string(), true),
+            },
+            None => (format!("Tool not found: {}", name), true),
+        };
+
+        results.push(RequestBlock::ToolResult {
+            tool_use_id: id,
+            content,
+            is_error,
+        });
+    }
+
+    results
+}
+
+/// Hide the group's secrets from tool results before the model sees them
+fn redact_tool_results(secrets: &GroupSecrets, results: &mut [RequestBlock]) {
+    if secrets.is_empty() {
+        return;
+    }
+    for block in results {
+        if let RequestBlock::ToolResult { content, .. } = block {
+            *content = secrets.redact(content);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AgentRunnerMode {
     #[default]
     Container,
     Api,
     Rig,
+    Provider,
 }
 
 pub fn agent_runner_mode() -> AgentRunnerMode {
     match std::env::var("AGENT_RUNNER_MODE").as_deref() {
         Ok("api") => AgentRunnerMode::Api,
         Ok("rig") => AgentRunnerMode::Rig,
+        Ok("provider") => AgentRunnerMode::Provider,
         _ => AgentRunnerMode::Container,
     }
 }
@@ -39,12 +112,97 @@ pub fn agent_runner_mode() -> AgentRunnerMode {
 #[async_trait]
 pub trait AgentRunner: Send + Sync {
     async fn run(&self, input: ContainerInput) -> Result<ContainerOutput>;
+
+    /// Run and forward intermediate progress as it arrives
+    ///
+    /// Runners that cannot report progress just run.
+    async fn run_with_progress(
+        &self,
+        input: ContainerInput,
+        progress: ProgressSender,
+    ) -> Result<ContainerOutput> {
+        drop(progress);
+        self.run(input).await
+    }
+}
+
+/// Default cap on model round-trips in a single tool-use loop
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 10;
+
+/// Get the maximum number of tool-use iterations per run
+pub fn max_tool_iterations() -> usize {
+    std::env::var("AGENT_MAX_TOOL_ITERATIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_MAX_TOOL_ITERATIONS)
 }
 
 #[derive(Debug, Serialize, Clone)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: MessageContent,
+}
+
+impl AnthropicMessage {
+    fn text(role: &str, text: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: MessageContent::Text(text.into()),
+        }
+    }
+
+    fn blocks(role: &str, blocks: Vec<RequestBlock>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: MessageContent::Blocks(blocks),
+        }
+    }
+}
+
+/// Message content: plain text or structured content blocks
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Blocks(Vec<RequestBlock>),
+}
+
+/// Content block sent to the API (assistant tool calls and their results)
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RequestBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        is_error: bool,
+    },
+}
+
+/// Tool advertised to the model
+#[derive(Debug, Serialize, Clone)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+impl From<&ToolDefinition> for AnthropicTool {
+    fn from(def: &ToolDefinition) -> Self {
+        Self {
+            name: def.name.clone(),
+            description: def.description.clone(),
+            input_schema: def.input_schema(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -53,6 +211,8 @@ struct AnthropicRequest {
     messages: Vec<AnthropicMessage>,
     max_tokens: u32,
     system: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -61,6 +221,8 @@ struct AnthropicStreamingRequest {
     messages: Vec<AnthropicMessage>,
     max_tokens: u32,
     system: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
     #[serde(rename = "stream")]
     is_streaming: bool,
 }
@@ -72,6 +234,7 @@ impl From<&AnthropicRequest> for AnthropicStreamingRequest {
             messages: req.messages.clone(),
             max_tokens: req.max_tokens,
             system: req.system.clone(),
+            tools: req.tools.clone(),
             is_streaming: true,
         }
     }
@@ -80,12 +243,17 @@ impl From<&AnthropicRequest> for AnthropicStreamingRequest {
 #[derive(Debug, Deserialize)]
 struct AnthropicResponse {
     content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<Usage>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 #[allow(dead_code)]
 enum ContentBlock {
+    ToolUse { id: String, name: String, input: serde_json::Value },
     Text { text: String },
     Thinking { thinking: String, text: Option<String>, #[serde(rename = "type")] block_type: Option<String> },
     Error { error: ApiError },
@@ -107,12 +275,27 @@ struct ApiErrorResponse {
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 enum SSEEvent {
+    #[serde(rename = "message_start")]
+    MessageStart { message: SSEMessage },
     #[serde(rename = "content_block_start")]
-    ContentBlockStart,
+    ContentBlockStart {
+        #[serde(default)]
+        index: usize,
+        #[serde(default)]
+        content_block: Option<SSEContentBlock>,
+    },
     #[serde(rename = "content_block_delta")]
-    ContentBlockDelta { delta: SSEDelta },
+    ContentBlockDelta {
+        #[serde(default)]
+        index: usize,
+        delta: SSEDelta,
+    },
     #[serde(rename = "message_delta")]
-    MessageDelta { delta: SSEDelta },
+    MessageDelta {
+        delta: SSEDelta,
+        #[serde(default)]
+        usage: Option<Usage>,
+    },
     #[serde(rename = "message_stop")]
     MessageStop,
 }
@@ -120,18 +303,161 @@ enum SSEEvent {
 #[derive(Debug, Deserialize)]
 struct SSEDelta {
     text: Option<String>,
+    /// Piece of a tool call's JSON input (`input_json_delta`)
+    #[serde(default)]
+    partial_json: Option<String>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+}
+
+/// Block opened by `content_block_start`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SSEContentBlock {
+    Text {
+        #[serde(default)]
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct SSEMessage {
+    #[serde(default)]
+    usage: Option<Usage>,
 }
 
 impl SSEEvent {
     fn delta_text(&self) -> Option<String> {
         match self {
-            SSEEvent::ContentBlockDelta { delta } => delta.text.clone(),
-            SSEEvent::MessageDelta { delta } => delta.text.clone(),
+            SSEEvent::ContentBlockDelta { delta, .. } => delta.text.clone(),
+            SSEEvent::MessageDelta { delta, .. } => delta.text.clone(),
+            _ => None,
+        }
+    }
+
+    /// Usage carried by the event: input tokens on `message_start`,
+    /// cumulative output tokens on `message_delta`
+    fn usage(&self) -> Option<Usage> {
+        match self {
+            SSEEvent::MessageStart { message } => message.usage,
+            SSEEvent::MessageDelta { usage, .. } => *usage,
             _ => None,
         }
     }
 }
 
+/// A block of a streamed response being assembled
+#[derive(Debug)]
+enum StreamedBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input_json: String,
+    },
+}
+
+/// A streamed response assembled from SSE events, tool calls included
+#[derive(Debug, Default)]
+struct StreamedResponse {
+    blocks: Vec<(usize, StreamedBlock)>,
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl StreamedResponse {
+    /// Apply one event; returns the text it adds, to forward to the caller
+    fn apply(&mut self, event: SSEEvent) -> Option<String> {
+        if let Some(event_usage) = event.usage() {
+            merge_stream_usage(&mut self.usage, event_usage);
+        }
+        let text = event.delta_text();
+        match event {
+            SSEEvent::ContentBlockStart {
+                index,
+                content_block: Some(SSEContentBlock::ToolUse { id, name }),
+            } => {
+                let block = StreamedBlock::ToolUse {
+                    id,
+                    name,
+                    input_json: String::new(),
+                };
+                self.blocks.push((index, block));
+                None
+            }
+            SSEEvent::ContentBlockStart {
+                index,
+                content_block: Some(SSEContentBlock::Text { text }),
+            } => {
+                self.blocks.push((index, StreamedBlock::Text(text.clone())));
+                Some(text).filter(|t| !t.is_empty())
+            }
+            SSEEvent::ContentBlockDelta { index, delta } => {
+                if let Some(json) = delta.partial_json {
+                    if let Some((_, StreamedBlock::ToolUse { input_json, .. })) =
+                        self.blocks.iter_mut().rev().find(|(i, _)| *i == index)
+                    {
+                        input_json.push_str(&json);
+                    }
+                }
+                let text = text?;
+                self.push_text(Some(index), &text);
+                Some(text)
+            }
+            SSEEvent::MessageDelta { delta, .. } => {
+                if delta.stop_reason.is_some() {
+                    self.stop_reason = delta.stop_reason;
+                }
+                let text = text?;
+                self.push_text(None, &text);
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Append text to the block at `index`, else the last text block
+    fn push_text(&mut self, index: Option<usize>, text: &str) {
+        let block = self
+            .blocks
+            .iter_mut()
+            .rev()
+            .find(|(i, block)| matches!(block, StreamedBlock::Text(_)) && index.is_none_or(|index| *i == index));
+        match block {
+            Some((_, StreamedBlock::Text(existing))) => existing.push_str(text),
+            _ => self
+                .blocks
+                .push((index.unwrap_or(usize::MAX), StreamedBlock::Text(text.to_string()))),
+        }
+    }
+
+    fn into_response(self) -> AnthropicResponse {
+        let content = self
+            .blocks
+            .into_iter()
+            .map(|(_, block)| match block {
+                StreamedBlock::Text(text) => ContentBlock::Text { text },
+                StreamedBlock::ToolUse { id, name, input_json } => ContentBlock::ToolUse {
+                    id,
+                    name,
+                    input: serde_json::from_str(&input_json).unwrap_or_else(|_| serde_json::json!({})),
+                },
+            })
+            .collect();
+        AnthropicResponse {
+            content,
+            stop_reason: self.stop_reason,
+            usage: self.usage,
+        }
+    }
+}
+
 fn extract_urls(text: &str) -> Vec<String> {
     let url_regex = Regex::new(r"https?://[^\s\)]+").unwrap();
     url_regex
@@ -191,6 +517,14 @@ pub struct ApiRunner {
     base_url: String,
     model: String,
     session_history: std::sync::Mutex<std::collections::HashMap<String, Vec<AnthropicMessage>>>,
+    tool_registry: InMemoryToolRegistry,
+    failover: FailoverChain,
+    retry: RetryPolicy,
+}
+
+/// Whether an API status should trigger provider failover (rate limit or server error)
+fn is_failover_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
 }
 
 impl ApiRunner {
@@ -204,6 +538,7 @@ impl ApiRunner {
         let model = claude_model().unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
 
         let client = Client::new();
+        let workflow = load_workflow_config();
 
         Ok(Self {
             client,
@@ -211,14 +546,17 @@ impl ApiRunner {
             base_url,
             model,
             session_history: std::sync::Mutex::new(std::collections::HashMap::new()),
+            tool_registry: builtin_tool_registry(),
+            retry: RetryPolicy::from_agent_settings(&workflow.agent),
+            failover: FailoverChain::new(workflow.agent.failover, Arc::new(provider_registry())),
         })
     }
 
     fn add_to_history(&self, session_id: &str, user_msg: String, assistant_msg: String) {
         let mut history = self.session_history.lock().unwrap();
         let messages = history.entry(session_id.to_string()).or_insert_with(Vec::new);
-        messages.push(AnthropicMessage { role: "user".to_string(), content: user_msg });
-        messages.push(AnthropicMessage { role: "assistant".to_string(), content: assistant_msg });
+        messages.push(AnthropicMessage::text("user", user_msg));
+        messages.push(AnthropicMessage::text("assistant", assistant_msg));
         if messages.len() > 20 {
             messages.drain(0..4);
         }
@@ -229,6 +567,84 @@ impl ApiRunner {
         history.get(session_id).cloned().unwrap_or_default()
     }
 
+    /// Turns for this run: stored chat history when provided, else in-process session history
+    fn conversation_turns(
+        &self,
+        input: &ContainerInput,
+        session_id: &str,
+        system: &str,
+        prompt: String,
+    ) -> Vec<AnthropicMessage> {
+        if input.history.is_empty() {
+            let mut messages = self.get_history(session_id);
+            messages.push(AnthropicMessage::text("user", prompt));
+            return messages;
+        }
+
+        // Failover may answer with the same turns, so they must fit its providers too
+        let (window, max_output) = match self.failover.context_limits() {
+            Some((window, max_output)) => (
+                window.min(ANTHROPIC_CONTEXT_WINDOW),
+                max_output.max(ANTHROPIC_MAX_OUTPUT_TOKENS),
+            ),
+            None => (ANTHROPIC_CONTEXT_WINDOW, ANTHROPIC_MAX_OUTPUT_TOKENS),
+        };
+        let budget = history_budget(window, max_output, system, &prompt);
+        build_turns(&input.history, &prompt, budget)
+            .into_iter()
+            .map(|turn| AnthropicMessage::text(&turn.role, turn.content))
+            .collect()
+    }
+
+    /// Answer through the failover chain after the primary API failed
+    ///
+    /// The chain gets the conversation so far, including tool calls made
+    /// before the failure.
+    async fn fail_over(
+        &self,
+        input: &ContainerInput,
+        system: &str,
+        messages: &[AnthropicMessage],
+        prompt: &str,
+        reason: String,
+    ) -> ContainerOutput {
+        tracing::warn!("Anthropic API unavailable ({}), trying failover chain", reason);
+
+        let options = ChatOptions::default().with_temperature(PROVIDER_TEMPERATURE);
+        match self
+            .failover
+            .chat_messages(&failover_messages(system, messages), &options)
+            .await
+        {
+            Ok(response) => {
+                let session_id = history_key(input);
+                self.add_to_history(session_id, prompt.to_string(), response.text.clone());
+                ContainerOutput {
+                    status: "success".to_string(),
+                    result: Some(response.text),
+                    new_session_id: input.session_id.clone(),
+                    error: None,
+                    tools_used: Vec::new(),
+                    provider: Some(response.provider),
+                    model: Some(response.model),
+                    usage: response.usage,
+                    artifacts: Vec::new(),
+                }
+            }
+            Err(e) => ContainerOutput {
+                status: "error".to_string(),
+                result: None,
+                new_session_id: input.session_id.clone(),
+                error: Some(format!("{}; {}", reason, e)),
+                tools_used: Vec::new(),
+                provider: None,
+                model: None,
+                usage: None,
+                artifacts: Vec::new(),
+            },
+        }
+    }
+
     fn clear_history(&self, session_id: &str) {
         let mut history = self.session_history.lock().unwrap();
         history.remove(session_id);
@@ -238,74 +654,201 @@ impl ApiRunner {
 #[async_trait]
 impl AgentRunner for ApiRunner {
     async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
+        self.run_loop(input, None).await
+    }
+}
+
+impl ApiRunner {
+    /// Answer through the tool-use loop, streaming each turn's text to
+    /// `on_text` when given
+    ///
+    /// Streamed turns still carry the tools, so tool calls, the circuit
+    /// breaker and failover behave as in a plain run. The group's secrets
+    /// are redacted from tool results and from the output, as in the sandbox.
+    async fn run_loop(
+        &self,
+        input: ContainerInput,
+        on_text: Option<&mut (dyn FnMut(String) + Send)>,
+    ) -> Result<ContainerOutput> {
+        let secrets = group_secrets_for_run(&input.group_folder);
+        let mut output = self.run_turns(input, &secrets, on_text).await?;
+        secrets.redact_output(&mut output);
+        Ok(output)
+    }
+
+    async fn run_turns(
+        &self,
+        input: ContainerInput,
+        secrets: &GroupSecrets,
+        mut on_text: Option<&mut (dyn FnMut(String) + Send)>,
+    ) -> Result<ContainerOutput> {
         let system = build_system_prompt(&input);
-        let session_id = input.session_id.as_deref().unwrap_or("default");
+        let session_id = history_key(&input);
 
         let processed_content = preprocess_prompt(&input.prompt).await;
         let user_message = processed_content.clone();
 
-        let mut messages = self.get_history(session_id);
-        messages.push(AnthropicMessage {
-            role: "user".to_string(),
-            content: processed_content,
-        });
+        let mut messages = self.conversation_turns(&input, session_id, &system, processed_content);
+
+        let tools: Vec<AnthropicTool> = self
+            .tool_registry
+            .definitions()
+            .iter()
+            .map(AnthropicTool::from)
+            .collect();
+        let max_iterations = max_tool_iterations();
+        let mut tools_used = Vec::new();
+        let mut usage: Option<Usage> = None;
+
+        let breaker = global_breakers().get("anthropic");
+        if !breaker.allow_request() && !self.failover.is_empty() {
+            tracing::warn!("Anthropic circuit open, failing over");
+            return Ok(self
+                .fail_over(&input, &system, &messages, &user_message, "circuit open".to_string())
+                .await);
+        }
 
-        let request = AnthropicRequest {
-            model: self.model.clone(),
-            messages: messages.clone(),
-            max_tokens: 4096,
-            system: Some(system),
-        };
+        for iteration in 0..max_iterations {
+            let request = AnthropicRequest {
+                model: self.model.clone(),
+                messages: messages.clone(),
+                max_tokens: 4096,
+                system: Some(system.clone()),
+                tools: tools.clone(),
+            };
 
-        let _url = format!("{}/v1/messages", self.base_url);
-        let response = self
-            .send_with_retry(&request)
-            .await?;
+            let sent = match on_text {
+                Some(_) => {
+                    self.send_streaming_with_retry(&AnthropicStreamingRequest::from(&request))
+                        .await
+                }
+                None => self.send_with_retry(&request).await,
+            };
+            let response = match sent {
+                Ok(response) => response,
+                Err(e) => {
+                    breaker.record_failure();
+                    if self.failover.is_empty() {
+                        return Err(e);
+                    }
+                    return Ok(self
+                        .fail_over(&input, &system, &messages, &user_message, e.to_string())
+                        .await);
+                }
+            };
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            let error_msg = Self::parse_api_error(&body).unwrap_or_else(|| {
-                format!("API request failed with status {}", status)
-            });
-            return Ok(ContainerOutput {
-                status: "error".to_string(),
-                result: None,
-                new_session_id: input.session_id,
-                error: Some(error_msg),
-            });
-        }
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                let detail = Self::parse_api_error(&body).unwrap_or(body);
+                let error_msg = retry::classify_status(status, &detail).to_string();
+                if is_failover_status(status) {
+                    breaker.record_failure();
+                    if !self.failover.is_empty() {
+                        return Ok(self
+                            .fail_over(&input, &system, &messages, &user_message, error_msg)
+                            .await);
+                    }
+                } else {
+                    // Anthropic answered; the request was at fault, not the provider
+                    breaker.record_success();
+                }
+                return Ok(ContainerOutput {
+                    status: "error".to_string(),
+                    result: None,
+                    new_session_id: input.session_id,
+                    error: Some(error_msg),
+                    tools_used,
+                    provider: None,
+                    model: None,
+                    usage: None,
+                    artifacts: Vec::new(),
+                });
+            }
 
-        let anthropic_response: AnthropicResponse =
-            response.json().await.map_err(|e| NuClawError::Api {
-                message: format!("Failed to parse response: {}", e),
-            })?;
+            // Settles a half-open trial before anything else can return
+            breaker.record_success();
 
-        tracing::debug!("API response content: {:?}", anthropic_response.content);
+            let anthropic_response: AnthropicResponse = match on_text.as_deref_mut() {
+                Some(on_text) => Self::read_sse_response(response, on_text).await?,
+                None => response.json().await.map_err(|e| NuClawError::Api {
+                    message: format!("Failed to parse response: {}", e),
+                })?,
+            };
 
-        let content = Self::extract_response_content(anthropic_response);
+            tracing::debug!("API response content: {:?}", anthropic_response.content);
+            if let Some(turn_usage) = anthropic_response.usage {
+                *usage.get_or_insert_with(Usage::default) += turn_usage;
+            }
+
+            let tool_calls = Self::extract_tool_calls(&anthropic_response);
+            if anthropic_response.stop_reason.as_deref() == Some("tool_use") && !tool_calls.is_empty() {
+                tracing::debug!(
+                    "Tool-use iteration {}/{}: {} call(s)",
+                    iteration + 1,
+                    max_iterations,
+                    tool_calls.len()
+                );
+                messages.push(AnthropicMessage::blocks(
+                    "assistant",
+                    Self::assistant_blocks(&anthropic_response),
+                ));
+                tools_used.extend(tool_calls.iter().map(|(_, name, _)| name.clone()));
+                // Tools only see the secrets of the group they run for
+                let mut results =
+                    with_tool_secrets(secrets.clone(), execute_tool_calls(&self.tool_registry, tool_calls)).await;
+                redact_tool_results(secrets, &mut results);
+                messages.push(AnthropicMessage::blocks("user", results));
+                continue;
+            }
+
+            let content = Self::extract_response_content(anthropic_response);
+
+            if content.trim().is_empty() {
+                return Ok(ContainerOutput {
+                    status: "error".to_string(),
+                    result: None,
+                    new_session_id: input.session_id,
+                    error: Some("Empty response from API".to_string()),
+                    tools_used,
+                    provider: None,
+                    model: None,
+                    usage,
+                    artifacts: Vec::new(),
+                });
+            }
+
+            self.add_to_history(session_id, user_message, content.clone());
 
-        if content.trim().is_empty() {
             return Ok(ContainerOutput {
-                status: "error".to_string(),
-                result: None,
+                status: "success".to_string(),
+                result: Some(content),
                 new_session_id: input.session_id,
-                error: Some("Empty response from API".to_string()),
+                error: None,
+                tools_used,
+                provider: Some("anthropic".to_string()),
+                model: Some(self.model.clone()),
+                usage,
+                artifacts: Vec::new(),
             });
         }
 
-        self.add_to_history(session_id, user_message, content.clone());
-
         Ok(ContainerOutput {
-            status: "success".to_string(),
-            result: Some(content),
+            status: "error".to_string(),
+            result: None,
             new_session_id: input.session_id,
-            error: None,
+            error: Some(format!(
+                "Tool-use loop exceeded {} iterations",
+                max_iterations
+            )),
+            tools_used,
+            provider: None,
+            model: None,
+            usage,
+            artifacts: Vec::new(),
         })
     }
-}
 
-impl ApiRunner {
     fn parse_api_error(body: &str) -> Option<String> {
         let err: ApiErrorResponse = serde_json::from_str(body).ok()?;
         Some(err.error.message)
@@ -321,11 +864,42 @@ impl ApiRunner {
                 ContentBlock::Error { error } => {
                     Some(format!("[API Error: {}]", error.message))
                 }
+                ContentBlock::ToolUse { .. } => None,
             })
             .collect::<Vec<_>>()
             .join("\n")
     }
 
+    fn extract_tool_calls(response: &AnthropicResponse) -> Vec<(String, String, serde_json::Value)> {
+        response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { id, name, input } => {
+                    Some((id.clone(), name.clone(), input.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Echo the assistant turn (text and tool calls) back into the conversation
+    fn assistant_blocks(response: &AnthropicResponse) -> Vec<RequestBlock> {
+        response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(RequestBlock::Text { text: text.clone() }),
+                ContentBlock::ToolUse { id, name, input } => Some(RequestBlock::ToolUse {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
     async fn send_with_retry(&self, request: &AnthropicRequest) -> Result<reqwest::Response> {
         let url = format!("{}/v1/messages", self.base_url);
         self.do_send_with_retry(&url, request).await
@@ -337,117 +911,35 @@ impl ApiRunner {
     }
 
     async fn do_send_with_retry<T: Serialize>(&self, url: &str, request: &T) -> Result<reqwest::Response> {
-        const MAX_ATTEMPTS: u32 = 3;
-        let mut attempts = 0;
-
-        loop {
-            attempts += 1;
-
-            let resp = self
-                .client
+        retry::send_with_retry(&self.retry, "Anthropic API", || {
+            self.client
                 .post(url)
                 .header("x-api-key", &self.api_key)
                 .header("anthropic-version", "2023-06-01")
                 .json(request)
                 .send()
-                .await;
-
-            match resp {
-                Ok(r) => {
-                    let status = r.status();
-                    if status.is_success() {
-                        return Ok(r);
-                    }
-                    if status.as_u16() >= 500 && attempts < MAX_ATTEMPTS {
-                        tracing::warn!(
-                            "API returned {}, retrying ({}/{})",
-                            status,
-                            attempts,
-                            MAX_ATTEMPTS
-                        );
-                        tokio::time::sleep(tokio::time::Duration::from_secs(attempts as u64)).await;
-                        continue;
-                    }
-                    return Ok(r);
-                }
-                Err(e) => {
-                    if attempts >= MAX_ATTEMPTS {
-                        return Err(NuClawError::Api {
-                            message: format!("Request failed after {} attempts: {}", MAX_ATTEMPTS, e),
-                        });
-                    }
-                    tracing::warn!(
-                        "API request failed (attempt {}/{}): {}",
-                        attempts,
-                        MAX_ATTEMPTS,
-                        e
-                    );
-                    tokio::time::sleep(tokio::time::Duration::from_secs(attempts as u64)).await;
-                }
-            }
-        }
+        })
+        .await
     }
 
+    /// Run like `run`, passing text deltas to `callback` as they arrive
     pub async fn run_streaming<F>(&self, input: &ContainerInput, mut callback: F) -> Result<ContainerOutput>
     where
         F: FnMut(String) + Send,
     {
-        let system = build_system_prompt(input);
-        let session_id = input.session_id.as_deref().unwrap_or("default");
-
-        let processed_content = preprocess_prompt(&input.prompt).await;
-
-        let mut messages = self.get_history(session_id);
-        messages.push(AnthropicMessage {
-            role: "user".to_string(),
-            content: processed_content.clone(),
-        });
-
-        let request = AnthropicRequest {
-            model: self.model.clone(),
-            messages: messages.clone(),
-            max_tokens: 4096,
-            system: Some(system),
-        };
-
-        let streaming_request = AnthropicStreamingRequest::from(&request);
-
-        let resp = self.send_streaming_with_retry(&streaming_request).await?;
-
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            let error_msg = Self::parse_api_error(&body).unwrap_or_else(|| {
-                format!("Streaming API failed with status {}", status)
-            });
-            return Ok(ContainerOutput {
-                status: "error".to_string(),
-                result: None,
-                new_session_id: input.session_id.clone(),
-                error: Some(error_msg),
-            });
-        }
-
-        let full_content = self.parse_sse_stream(resp, &mut callback).await?;
-        self.add_to_history(session_id, processed_content, full_content.clone());
-
-        Ok(ContainerOutput {
-            status: "success".to_string(),
-            result: Some(full_content),
-            new_session_id: input.session_id.clone(),
-            error: None,
-        })
+        self.run_loop(input.clone(), Some(&mut callback)).await
     }
 
-    async fn parse_sse_stream<F>(&self, resp: reqwest::Response, callback: &mut F) -> Result<String>
-    where
-        F: FnMut(String) + Send,
-    {
+    /// Read an SSE response, forwarding text deltas and assembling the blocks
+    async fn read_sse_response(
+        resp: reqwest::Response,
+        on_text: &mut (dyn FnMut(String) + Send),
+    ) -> Result<AnthropicResponse> {
         use futures::StreamExt;
-        
+
         let mut stream = resp.bytes_stream();
         let mut buffer = String::new();
-        let mut full_content = String::new();
+        let mut streamed = StreamedResponse::default();
 
         while let Some(chunk_result) = stream.next().await {
             let chunk = chunk_result.map_err(|e| NuClawError::Api {
@@ -460,25 +952,69 @@ impl ApiRunner {
                 let line = buffer[..line_end].trim().to_string();
                 buffer = buffer[line_end + 1..].to_string();
 
-                if line.starts_with("data: ") {
-                    let data = &line[6..];
+                if let Some(data) = line.strip_prefix("data: ") {
                     if data == "[DONE]" {
-                        return Ok(full_content);
+                        return Ok(streamed.into_response());
                     }
                     if let Ok(event) = serde_json::from_str::<SSEEvent>(data) {
-                        if let Some(text) = event.delta_text() {
-                            full_content.push_str(&text);
-                            callback(text);
+                        if let Some(text) = streamed.apply(event) {
+                            on_text(text);
                         }
                     }
                 }
             }
         }
 
-        Ok(full_content)
+        Ok(streamed.into_response())
     }
 }
 
+/// Conversation for the failover chain, with tool calls and results as text
+///
+/// Failover providers answer without tools, so tool blocks are rendered in
+/// their turn instead of being sent as structured calls.
+fn failover_messages(system: &str, messages: &[AnthropicMessage]) -> Vec<ChatMessage> {
+    let mut turns = vec![ChatMessage::system(system)];
+    for message in messages {
+        let content = match &message.content {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Blocks(blocks) => blocks
+                .iter()
+                .map(|block| match block {
+                    RequestBlock::Text { text } => text.clone(),
+                    RequestBlock::ToolUse { name, input, .. } => {
+                        format!("[Called tool {} with {}]", name, input)
+                    }
+                    RequestBlock::ToolResult {
+                        content, is_error, ..
+                    } => {
+                        let label = if *is_error { "Tool error" } else { "Tool result" };
+                        format!("[{}]\n{}", label, content)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        turns.push(match message.role.as_str() {
+            "assistant" => ChatMessage::assistant(content),
+            _ => ChatMessage::user(content),
+        });
+    }
+    turns
+}
+
+/// Merge streamed usage; counts in later events are cumulative
+fn merge_stream_usage(total: &mut Option<Usage>, event: Usage) {
+    let total = total.get_or_insert_with(Usage::default);
+    total.input_tokens = total.input_tokens.max(event.input_tokens);
+    total.output_tokens = total.output_tokens.max(event.output_tokens);
+}
+
+/// Key for in-process history: the agent session, else the chat
+fn history_key(input: &ContainerInput) -> &str {
+    input.session_id.as_deref().unwrap_or(&input.chat_jid)
+}
+
 fn build_system_prompt(input: &ContainerInput) -> String {
     let mut prompt = String::new();
 
@@ -509,6 +1045,7 @@ pub struct RigRunner {
     client: anthropic::Client,
     model: String,
     tool_registry: InMemoryToolRegistry,
+    retry: RetryPolicy,
 }
 
 impl RigRunner {
@@ -536,18 +1073,14 @@ impl RigRunner {
                 })?
         };
 
-        let mut tool_registry = InMemoryToolRegistry::new();
-        let registry: &dyn SkillRegistry = &builtin_skills();
-        let skills = registry.list();
-        let tools = all_skills_to_tools(skills);
-        for tool in tools {
-            let name = tool.definition().name.clone();
-            if let Err(e) = tool_registry.register(tool) {
-                tracing::warn!("Failed to register tool {}: {}", name, e);
-            }
-        }
+        let tool_registry = builtin_tool_registry();
 
-        Ok(Self { client, model, tool_registry })
+        Ok(Self {
+            client,
+            model,
+            tool_registry,
+            retry: RetryPolicy::from_workflow(),
+        })
     }
 }
 
@@ -556,24 +1089,196 @@ impl AgentRunner for RigRunner {
     async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
         let system = build_system_prompt(&input);
 
+        let call_log = ToolCallLog::default();
+        let tools: Vec<Arc<dyn Tool>> = self
+            .tool_registry
+            .list()
+            .iter()
+            .filter_map(|name| self.tool_registry.get(name))
+            .collect();
+
         let agent = self
             .client
             .agent(&self.model)
             .preamble(&system)
+            .tools(tools_to_rig(tools, &call_log))
+            .default_max_turns(max_tool_iterations())
             .build();
 
-        match agent.prompt(&input.prompt).await {
+        // Rig mode always talks to Anthropic
+        let budget = history_budget(ANTHROPIC_CONTEXT_WINDOW, ANTHROPIC_MAX_OUTPUT_TOKENS, &system, &input.prompt);
+        let mut turns = build_turns(&input.history, &input.prompt, budget);
+        let prompt = turns.pop().map(|turn| turn.content).unwrap_or_default();
+        let history: Vec<rig::completion::Message> = turns
+            .into_iter()
+            .map(|turn| match turn.role.as_str() {
+                "assistant" => rig::completion::Message::assistant(turn.content),
+                _ => rig::completion::Message::user(turn.content),
+            })
+            .collect();
+
+        let mut attempt = 0;
+        let result = loop {
+            let mut attempt_history = history.clone();
+            let result = agent
+                .prompt(prompt.as_str())
+                .with_history(&mut attempt_history)
+                .extended_details()
+                .await;
+            match result {
+                Err(e) if attempt < self.retry.max_retries && retry::is_transient_message(&e.to_string()) => {
+                    let delay = self.retry.backoff(attempt);
+                    tracing::warn!(
+                        "Rig request failed ({}), retrying in {:?} ({}/{})",
+                        e,
+                        delay,
+                        attempt + 1,
+                        self.retry.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+        let tools_used = call_log.lock().map(|log| log.clone()).unwrap_or_default();
+
+        match result {
             Ok(response) => Ok(ContainerOutput {
                 status: "success".to_string(),
-                result: Some(response),
+                result: Some(response.output),
                 new_session_id: input.session_id,
                 error: None,
+                tools_used,
+                provider: Some("anthropic".to_string()),
+                model: Some(self.model.clone()),
+                usage: Some(Usage {
+                    input_tokens: response.usage.input_tokens,
+                    output_tokens: response.usage.output_tokens,
+                }),
+                artifacts: Vec::new(),
             }),
             Err(e) => Ok(ContainerOutput {
                 status: "error".to_string(),
                 result: None,
                 new_session_id: input.session_id,
                 error: Some(format!("Rig error: {}", e)),
+                tools_used,
+                provider: None,
+                model: None,
+                usage: None,
+                artifacts: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// Sampling temperature used in provider mode
+const PROVIDER_TEMPERATURE: f64 = 0.7;
+
+/// Runner that goes through the `Provider` trait, for any provider in `PROVIDERS`
+pub struct ProviderRunner {
+    registry: Arc<ProviderRegistry>,
+    workflow: WorkflowConfig,
+}
+
+impl ProviderRunner {
+    pub fn new() -> Result<Self> {
+        let runner = Self::with_config(provider_registry(), load_workflow_config());
+
+        if runner.workflow.agent.provider.is_none()
+            && runner.workflow.agent.failover.is_empty()
+            && runner.registry.detect_provider().is_none()
+        {
+            return Err(NuClawError::Config {
+                message: "No LLM provider configured for provider mode".to_string(),
+            });
+        }
+
+        Ok(runner)
+    }
+
+    pub fn with_config(registry: ProviderRegistry, workflow: WorkflowConfig) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            workflow,
+        }
+    }
+
+    /// Provider name for a group: workflow override, then the detected provider
+    fn provider_name(&self, group_folder: &str) -> Option<String> {
+        self.workflow
+            .provider_for_group(group_folder)
+            .or_else(|| self.registry.detect_provider())
+    }
+
+    /// Failover chain for a group, starting with its primary provider
+    fn chain_for_group(&self, group_folder: &str) -> FailoverChain {
+        let targets = self
+            .workflow
+            .failover_chain(group_folder, self.provider_name(group_folder));
+        FailoverChain::new(targets, self.registry.clone())
+    }
+}
+
+#[async_trait]
+impl AgentRunner for ProviderRunner {
+    async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
+        let system = build_system_prompt(&input);
+
+        let chain = self.chain_for_group(&input.group_folder);
+        if chain.is_empty() {
+            return Ok(ContainerOutput {
+                status: "error".to_string(),
+                result: None,
+                new_session_id: input.session_id,
+                error: Some("No LLM provider configured for provider mode".to_string()),
+                tools_used: Vec::new(),
+                provider: None,
+                model: None,
+                usage: None,
+                artifacts: Vec::new(),
+            });
+        }
+
+        let (window, max_output) = chain
+            .context_limits()
+            .unwrap_or((ANTHROPIC_CONTEXT_WINDOW, ANTHROPIC_MAX_OUTPUT_TOKENS));
+        let budget = history_budget(window, max_output, &system, &input.prompt);
+        let mut messages = vec![ChatMessage::system(system)];
+        messages.extend(build_turns(&input.history, &input.prompt, budget));
+        let options = ChatOptions::default().with_temperature(PROVIDER_TEMPERATURE);
+
+        match chain.chat_messages(&messages, &options).await {
+            Ok(response) => {
+                tracing::debug!(
+                    "Provider mode: group={}, answered by {}/{}",
+                    input.group_folder,
+                    response.provider,
+                    response.model
+                );
+                Ok(ContainerOutput {
+                    status: "success".to_string(),
+                    result: Some(response.text),
+                    new_session_id: input.session_id,
+                    error: None,
+                    tools_used: Vec::new(),
+                    provider: Some(response.provider),
+                    model: Some(response.model),
+                    usage: response.usage,
+                    artifacts: Vec::new(),
+                })
+            }
+            Err(e) => Ok(ContainerOutput {
+                status: "error".to_string(),
+                result: None,
+                new_session_id: input.session_id,
+                error: Some(e.to_string()),
+                tools_used: Vec::new(),
+                provider: None,
+                model: None,
+                usage: None,
+                artifacts: Vec::new(),
             }),
         }
     }
@@ -589,6 +1294,10 @@ pub fn create_runner() -> Result<Box<dyn AgentRunner>> {
             let runner = RigRunner::new()?;
             Ok(Box::new(runner))
         }
+        AgentRunnerMode::Provider => {
+            let runner = ProviderRunner::new()?;
+            Ok(Box::new(runner))
+        }
         AgentRunnerMode::Container => Ok(Box::new(ContainerRunnerAdapter::new())),
     }
 }
@@ -611,9 +1320,12 @@ impl Default for ContainerRunnerAdapter {
     }
 }
 
-#[async_trait]
-impl AgentRunner for ContainerRunnerAdapter {
-    async fn run(&self, mut input: ContainerInput) -> Result<ContainerOutput> {
+impl ContainerRunnerAdapter {
+    async fn run_in_workspace(
+        &self,
+        mut input: ContainerInput,
+        progress: Option<ProgressSender>,
+    ) -> Result<ContainerOutput> {
         // Resolve workspace for this session
         let workspace_path = self
             .workspace_manager
@@ -631,8 +1343,12 @@ impl AgentRunner for ContainerRunnerAdapter {
             let _ = self.workspace_manager.activate_workspace(session_id).await;
         }
 
-        // Run container with workspace (clone input to avoid move)
-        let result = crate::container_runner::run_container(input.clone()).await;
+        // Run in the configured container runtime (clone input to avoid move)
+        let runtime = default_runtime();
+        let result = match progress {
+            Some(progress) => runtime.run_with_progress(input.clone(), progress).await,
+            None => runtime.run(input.clone()).await,
+        };
 
         // Deactivate workspace after execution
         if let Some(ref session_id) = session_id_clone {
@@ -643,6 +1359,21 @@ impl AgentRunner for ContainerRunnerAdapter {
     }
 }
 
+#[async_trait]
+impl AgentRunner for ContainerRunnerAdapter {
+    async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
+        self.run_in_workspace(input, None).await
+    }
+
+    async fn run_with_progress(
+        &self,
+        input: ContainerInput,
+        progress: ProgressSender,
+    ) -> Result<ContainerOutput> {
+        self.run_in_workspace(input, Some(progress)).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -686,6 +1417,14 @@ mod tests {
         assert_eq!(agent_runner_mode(), AgentRunnerMode::Api);
     }
 
+    #[test]
+    #[serial]
+    fn test_agent_runner_mode_provider() {
+        std::env::remove_var("AGENT_RUNNER_MODE");
+        let _guard = with_env_var("AGENT_RUNNER_MODE", "provider");
+        assert_eq!(agent_runner_mode(), AgentRunnerMode::Provider);
+    }
+
     #[test]
     #[serial]
     fn test_agent_runner_mode_invalid() {
@@ -704,6 +1443,8 @@ mod tests {
             is_main: true,
             is_scheduled_task: false,
             session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
         };
         let prompt = build_system_prompt(&input);
         assert!(prompt.contains("main context"));
@@ -720,6 +1461,8 @@ mod tests {
             is_main: false,
             is_scheduled_task: true,
             session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
         };
         let prompt = build_system_prompt(&input);
         assert!(prompt.contains("scheduled task"));
@@ -736,6 +1479,8 @@ mod tests {
             is_main: false,
             is_scheduled_task: false,
             session_workspace_id: Some("ws_456".to_string()),
+            history: Vec::new(),
+            run_id: None,
         };
         let prompt = build_system_prompt(&input);
         assert!(prompt.contains("isolated context"));
@@ -745,12 +1490,10 @@ mod tests {
     fn test_anthropic_request_serialization() {
         let request = AnthropicRequest {
             model: "test-model".to_string(),
-            messages: vec![AnthropicMessage {
-                role: "user".to_string(),
-                content: "Hello".to_string(),
-            }],
+            messages: vec![AnthropicMessage::text("user", "Hello")],
             max_tokens: 1024,
             system: Some("You are helpful.".to_string()),
+            tools: Vec::new(),
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("test-model"));
@@ -788,6 +1531,107 @@ mod tests {
         std::env::remove_var("ANTHROPIC_API_KEY");
     }
 
+    #[test]
+    #[serial]
+    fn test_provider_runner_chain_for_group() {
+        use crate::workflow::{FailoverTarget, GroupSettings};
+
+        let mut workflow = WorkflowConfig::default();
+        workflow.agent.provider = Some("anthropic".to_string());
+        workflow.agent.failover = vec![FailoverTarget {
+            provider: "openai".to_string(),
+            model: Some("gpt-4o-mini".to_string()),
+        }];
+        workflow.groups.insert(
+            "research".to_string(),
+            GroupSettings {
+                provider: Some("openrouter".to_string()),
+                model: Some("group-model".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let runner = ProviderRunner::with_config(ProviderRegistry::new(), workflow);
+
+        let chain = runner.chain_for_group("research");
+        let targets = chain.targets();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].provider, "openrouter");
+        assert_eq!(targets[0].model, Some("group-model".to_string()));
+        assert_eq!(targets[1].provider, "openai");
+
+        let chain = runner.chain_for_group("main");
+        assert_eq!(chain.targets()[0].provider, "anthropic");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_provider_runner_unconfigured_provider() {
+        std::env::remove_var("OPENAI_API_KEY");
+
+        let mut workflow = WorkflowConfig::default();
+        workflow.agent.provider = Some("openai".to_string());
+
+        let runner = ProviderRunner::with_config(ProviderRegistry::new(), workflow);
+        let output = runner
+            .run(ContainerInput {
+                prompt: "Hello".to_string(),
+                session_id: None,
+                group_folder: "main".to_string(),
+                chat_jid: "test@chat".to_string(),
+                is_main: true,
+                is_scheduled_task: false,
+                session_workspace_id: None,
+                history: Vec::new(),
+                run_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(output.status, "error");
+        assert!(output.error.unwrap().contains("openai: not configured"));
+    }
+
+    #[test]
+    fn test_is_failover_status() {
+        assert!(is_failover_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_failover_status(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(!is_failover_status(reqwest::StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_failover_messages_keep_history_and_tools() {
+        let messages = vec![
+            AnthropicMessage::text("user", "What is the weather?"),
+            AnthropicMessage::text("assistant", "Where are you?"),
+            AnthropicMessage::text("user", "Berlin"),
+            AnthropicMessage::blocks(
+                "assistant",
+                vec![RequestBlock::ToolUse {
+                    id: "t1".to_string(),
+                    name: "weather".to_string(),
+                    input: serde_json::json!({"city": "Berlin"}),
+                }],
+            ),
+            AnthropicMessage::blocks(
+                "user",
+                vec![RequestBlock::ToolResult {
+                    tool_use_id: "t1".to_string(),
+                    content: "12°C, rain".to_string(),
+                    is_error: false,
+                }],
+            ),
+        ];
+
+        let turns = failover_messages("system prompt", &messages);
+        let roles: Vec<&str> = turns.iter().map(|t| t.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user", "assistant", "user"]);
+        assert_eq!(turns[0].content, "system prompt");
+        assert_eq!(turns[2].content, "Where are you?");
+        assert!(turns[4].content.contains("weather"));
+        assert!(turns[4].content.contains("Berlin"));
+        assert_eq!(turns[5].content, "[Tool result]\n12°C, rain");
+    }
+
     #[test]
     fn test_sse_event_parsing_content_block_delta() {
         let json = r#"{"type":"content_block_delta","delta":{"text":"Hello"}}"#;
@@ -802,6 +1646,32 @@ mod tests {
         assert_eq!(event.delta_text(), Some("World".to_string()));
     }
 
+    #[test]
+    fn test_sse_event_usage() {
+        let start = r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":25,"output_tokens":1}}}"#;
+        let delta = r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":15}}"#;
+
+        let mut usage = None;
+        for json in [start, delta] {
+            let event: SSEEvent = serde_json::from_str(json).unwrap();
+            merge_stream_usage(&mut usage, event.usage().unwrap());
+        }
+        assert_eq!(
+            usage,
+            Some(Usage {
+                input_tokens: 25,
+                output_tokens: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_anthropic_response_usage() {
+        let json = r#"{"content":[{"type":"text","text":"hi"}],"usage":{"input_tokens":3,"output_tokens":2}}"#;
+        let response: AnthropicResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.usage.unwrap().total_tokens(), 5);
+    }
+
     #[test]
     fn test_sse_event_parsing_content_block_start() {
         let json = r#"{"type":"content_block_start"}"#;
@@ -820,12 +1690,10 @@ mod tests {
     fn test_streaming_request_serialization() {
         let request = AnthropicRequest {
             model: "test-model".to_string(),
-            messages: vec![AnthropicMessage {
-                role: "user".to_string(),
-                content: "Hello".to_string(),
-            }],
+            messages: vec![AnthropicMessage::text("user", "Hello")],
             max_tokens: 1024,
             system: Some("You are helpful.".to_string()),
+            tools: Vec::new(),
         };
         let streaming_request = AnthropicStreamingRequest::from(&request);
         let json = serde_json::to_string(&streaming_request).unwrap();
@@ -834,6 +1702,245 @@ mod tests {
         assert!(json.contains("You are helpful"));
     }
 
+    #[test]
+    fn test_redact_tool_results() {
+        let secrets = GroupSecrets::new(vec![("API_TOKEN".to_string(), "tok-12345".to_string())]);
+        let mut results = vec![
+            RequestBlock::ToolResult {
+                tool_use_id: "call_1".to_string(),
+                content: "TOKEN=tok-12345".to_string(),
+                is_error: false,
+            },
+            RequestBlock::Text {
+                text: "tok-12345".to_string(),
+            },
+        ];
+        redact_tool_results(&secrets, &mut results);
+
+        match &results[0] {
+            RequestBlock::ToolResult { content, .. } => {
+                assert_eq!(content, &format!("TOKEN={}", crate::secrets::REDACTED))
+            }
+            other => panic!("unexpected block: {:?}", other),
+        }
+        // Only tool output is rewritten
+        assert!(matches!(&results[1], RequestBlock::Text { text } if text == "tok-12345"));
+    }
+
+    #[test]
+    fn test_streamed_response_assembles_tool_calls() {
+        let events = [
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Checking"}}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"tu_1","name":"lookup","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"city\": "}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"Berlin\"}"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":20}}"#,
+        ];
+        let mut streamed = StreamedResponse::default();
+        let mut forwarded = Vec::new();
+        for json in events {
+            forwarded.extend(streamed.apply(serde_json::from_str(json).unwrap()));
+        }
+        assert_eq!(forwarded, vec!["Checking".to_string()]);
+
+        let response = streamed.into_response();
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage.unwrap().total_tokens(), 30);
+        assert_eq!(
+            ApiRunner::extract_tool_calls(&response),
+            vec![(
+                "tu_1".to_string(),
+                "lookup".to_string(),
+                serde_json::json!({"city": "Berlin"})
+            )]
+        );
+        assert_eq!(ApiRunner::extract_response_content(response), "Checking");
+    }
+
+    #[tokio::test]
+    async fn test_run_streaming_sends_tools_and_streams_text() {
+        use axum::{routing::post, Json, Router};
+        use std::sync::Mutex;
+
+        let seen: Arc<Mutex<Option<serde_json::Value>>> = Arc::default();
+        let recorded = seen.clone();
+        let app = Router::new().route(
+            "/v1/messages",
+            post(move |Json(body): Json<serde_json::Value>| async move {
+                *recorded.lock().unwrap() = Some(body);
+                let events = [
+                    r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+                    r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}"#,
+                    r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"lo"}}"#,
+                    r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":2}}"#,
+                    r#"{"type":"message_stop"}"#,
+                ];
+                let body: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+                ([("content-type", "text/event-stream")], body)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let runner = ApiRunner {
+            client: Client::new(),
+            api_key: "test-key".to_string(),
+            base_url: format!("http://{}", addr),
+            model: "test-model".to_string(),
+            session_history: std::sync::Mutex::new(std::collections::HashMap::new()),
+            tool_registry: builtin_tool_registry(),
+            failover: FailoverChain::new(Vec::new(), Arc::new(provider_registry())),
+            retry: RetryPolicy::default(),
+        };
+        assert!(!runner.tool_registry.definitions().is_empty());
+
+        let input = ContainerInput {
+            prompt: "Say hello".to_string(),
+            session_id: None,
+            group_folder: "main".to_string(),
+            chat_jid: "telegram:stream".to_string(),
+            is_main: true,
+            is_scheduled_task: false,
+            session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
+        };
+        let mut deltas = Vec::new();
+        let output = runner
+            .run_streaming(&input, |delta| deltas.push(delta))
+            .await
+            .unwrap();
+
+        assert_eq!(deltas, vec!["Hel".to_string(), "lo".to_string()]);
+        assert_eq!(output.status, "success");
+        assert_eq!(output.result.as_deref(), Some("Hello"));
+        let request = seen.lock().unwrap().clone().unwrap();
+        assert_eq!(request["stream"], true);
+        assert!(!request["tools"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_anthropic_request_serialization_with_tools() {
+        let def = ToolDefinition {
+            name: "lookup".to_string(),
+            description: "Look something up".to_string(),
+            params: vec![],
+        };
+        let request = AnthropicRequest {
+            model: "test-model".to_string(),
+            messages: vec![AnthropicMessage::blocks(
+                "user",
+                vec![RequestBlock::ToolResult {
+                    tool_use_id: "toolu_1".to_string(),
+                    content: "42".to_string(),
+                    is_error: false,
+                }],
+            )],
+            max_tokens: 1024,
+            system: None,
+            tools: vec![AnthropicTool::from(&def)],
+        };
+        let json: serde_json::Value = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["tools"][0]["name"], "lookup");
+        assert_eq!(json["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(json["messages"][0]["content"][0]["type"], "tool_result");
+        assert_eq!(json["messages"][0]["content"][0]["tool_use_id"], "toolu_1");
+    }
+
+    #[test]
+    fn test_anthropic_request_omits_empty_tools() {
+        let request = AnthropicRequest {
+            model: "test-model".to_string(),
+            messages: vec![AnthropicMessage::text("user", "Hello")],
+            max_tokens: 1024,
+            system: None,
+            tools: Vec::new(),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert!(!json.contains("tools"));
+        assert!(json.contains("\"content\":\"Hello\""));
+    }
+
+    #[test]
+    fn test_anthropic_response_tool_use_deserialization() {
+        let response_json = r#"{
+            "content": [
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {"q": "x"}}
+            ],
+            "stop_reason": "tool_use"
+        }"#;
+        let response: AnthropicResponse = serde_json::from_str(response_json).unwrap();
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+
+        let calls = ApiRunner::extract_tool_calls(&response);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, "toolu_1");
+        assert_eq!(calls[0].1, "lookup");
+        assert_eq!(calls[0].2["q"], "x");
+
+        let blocks = ApiRunner::assistant_blocks(&response);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(ApiRunner::extract_response_content(response), "Let me check.");
+    }
+
+    #[tokio::test]
+    async fn test_execute_tool_calls() {
+        use crate::skill_to_rig::SkillAsTool;
+        use crate::skills::Skill;
+        use std::sync::Arc;
+
+        let mut registry = InMemoryToolRegistry::new();
+        registry
+            .register(Arc::new(SkillAsTool::new(Arc::new(Skill::new(
+                "notes",
+                "Notes skill",
+                "Skill body",
+            )))))
+            .unwrap();
+
+        let results = execute_tool_calls(
+            &registry,
+            vec![
+                ("a".to_string(), "notes".to_string(), serde_json::json!({})),
+                ("b".to_string(), "missing".to_string(), serde_json::json!({})),
+            ],
+        )
+        .await;
+
+        assert_eq!(results.len(), 2);
+        match &results[0] {
+            RequestBlock::ToolResult { tool_use_id, is_error, .. } => {
+                assert_eq!(tool_use_id, "a");
+                assert!(!is_error);
+            }
+            other => panic!("unexpected block: {:?}", other),
+        }
+        match &results[1] {
+            RequestBlock::ToolResult { content, is_error, .. } => {
+                assert!(is_error);
+                assert!(content.contains("missing"));
+            }
+            other => panic!("unexpected block: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_max_tool_iterations() {
+        std::env::remove_var("AGENT_MAX_TOOL_ITERATIONS");
+        assert_eq!(max_tool_iterations(), DEFAULT_MAX_TOOL_ITERATIONS);
+
+        let _guard = with_env_var("AGENT_MAX_TOOL_ITERATIONS", "3");
+        assert_eq!(max_tool_iterations(), 3);
+
+        std::env::set_var("AGENT_MAX_TOOL_ITERATIONS", "0");
+        assert_eq!(max_tool_iterations(), DEFAULT_MAX_TOOL_ITERATIONS);
+    }
+
     #[test]
     fn test_sse_event_parsing_invalid_json() {
         let json = r#"{"type":"unknown_type","delta":{"text":"test"}}"#;
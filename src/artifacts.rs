@@ -0,0 +1,404 @@
+//! Agent artifacts - Files an agent run produces for the chat
+//!
+//! The agent drops files it wants delivered (charts, reports, CSVs) into its
+//! run's outbox, `outbox/<run_id>/` in the group workspace
+//! (`/workspace/group/outbox/<run_id>` inside the container, with `run_id`
+//! from the input). Concurrent runs of one group each get their own outbox.
+//! After the run the host moves the files to
+//! `data/artifacts/<group>/<run_id>/` and attaches them to
+//! `ContainerOutput::artifacts`, and channel clients upload them as images or
+//! documents where the platform supports it.
+//!
+//! Only regular files directly inside the outbox are collected; dot-files are
+//! treated as still being written. Files over `ARTIFACT_MAX_BYTES` or beyond
+//! `ARTIFACT_MAX_FILES` are discarded with a warning.
+
+use crate::config::{data_dir, groups_dir};
+use crate::container_runner::validate_group_folder;
+use crate::error::{NuClawError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Folder inside the group workspace the agent writes deliverables to
+pub const OUTBOX_DIR: &str = "outbox";
+
+/// Default largest file delivered: 20 MB
+pub const DEFAULT_ARTIFACT_MAX_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Default number of files delivered per run
+pub const DEFAULT_ARTIFACT_MAX_FILES: usize = 10;
+
+/// Get the largest deliverable file size from environment or default
+pub fn artifact_max_bytes() -> u64 {
+    std::env::var("ARTIFACT_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_ARTIFACT_MAX_BYTES)
+}
+
+/// Get the number of files delivered per run from environment or default
+pub fn artifact_max_files() -> usize {
+    std::env::var("ARTIFACT_MAX_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_ARTIFACT_MAX_FILES)
+}
+
+/// Host directory collected artifacts are kept in
+pub fn artifacts_dir() -> PathBuf {
+    data_dir().join("artifacts")
+}
+
+/// How a channel should present an artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Image,
+    Document,
+}
+
+impl ArtifactKind {
+    /// Classify a file by its extension
+    pub fn from_name(name: &str) -> Self {
+        let ext = Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        match ext.as_deref() {
+            Some("png" | "jpg" | "jpeg" | "gif" | "webp") => Self::Image,
+            _ => Self::Document,
+        }
+    }
+}
+
+/// A file produced by an agent run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// File name as the agent wrote it
+    pub name: String,
+    /// Where the file is kept on the host
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    pub kind: ArtifactKind,
+}
+
+/// Outbox folder of a group workspace
+pub fn outbox_dir(group_dir: &Path) -> PathBuf {
+    group_dir.join(OUTBOX_DIR)
+}
+
+/// New run id: a timestamp plus a random suffix
+pub fn new_run_id() -> String {
+    format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S"),
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    )
+}
+
+/// Outbox of one run in a group workspace, or `None` for an unsafe run id
+pub fn run_outbox_dir(group_dir: &Path, run_id: &str) -> Option<PathBuf> {
+    let safe = !run_id.is_empty()
+        && run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    safe.then(|| outbox_dir(group_dir).join(run_id))
+}
+
+/// A run's outbox, checked to be a real directory inside `group_dir`
+///
+/// The agent can rewrite its workspace, so `outbox/` or `outbox/<run_id>`
+/// may have been swapped for a symlink to a host directory; those are
+/// refused. Missing directories are created when `create` is set, otherwise
+/// `Ok(None)` means the run left no outbox.
+pub fn checked_run_outbox(group_dir: &Path, run_id: &str, create: bool) -> Result<Option<PathBuf>> {
+    let outbox = run_outbox_dir(group_dir, run_id).ok_or_else(|| NuClawError::Security {
+        message: format!("Invalid run id: {}", run_id),
+    })?;
+    if create {
+        fs::create_dir_all(group_dir).map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to create group directory: {}", e),
+        })?;
+    }
+    for dir in [outbox_dir(group_dir), outbox.clone()] {
+        match fs::symlink_metadata(&dir) {
+            Ok(meta) if meta.file_type().is_dir() => {}
+            Ok(_) => {
+                return Err(NuClawError::Security {
+                    message: format!("Outbox {} is not a directory", dir.display()),
+                })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+                // `create_dir` fails on anything already there, symlinks included
+                fs::create_dir(&dir).map_err(|e| NuClawError::FileSystem {
+                    message: format!("Failed to create outbox {}: {}", dir.display(), e),
+                })?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(NuClawError::FileSystem {
+                    message: format!("Failed to read outbox {}: {}", dir.display(), e),
+                })
+            }
+        }
+    }
+    let canonical = |path: &Path| {
+        path.canonicalize().map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to resolve {}: {}", path.display(), e),
+        })
+    };
+    let outbox = canonical(&outbox)?;
+    if !outbox.starts_with(canonical(group_dir)?) {
+        return Err(NuClawError::Security {
+            message: format!("Outbox {} is outside the group folder", outbox.display()),
+        });
+    }
+    Ok(Some(outbox))
+}
+
+/// Create a run's outbox so the agent finds it, logging failures
+pub fn prepare_outbox(group_folder: &str, run_id: &str) {
+    if validate_group_folder(group_folder).is_err() {
+        return;
+    }
+    if let Err(e) = checked_run_outbox(&groups_dir().join(group_folder), run_id, true) {
+        warn!("Failed to prepare outbox for {}: {}", group_folder, e);
+    }
+}
+
+/// Move the files in `outbox` to `dest` and describe them
+///
+/// `dest` is only created when there is something to move.
+pub fn collect_artifacts(
+    outbox: &Path,
+    dest: &Path,
+    max_bytes: u64,
+    max_files: usize,
+) -> Result<Vec<Artifact>> {
+    let entries = match fs::read_dir(outbox) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(NuClawError::FileSystem {
+                message: format!("Failed to read outbox {}: {}", outbox.display(), e),
+            })
+        }
+    };
+
+    let mut files: Vec<(String, PathBuf, u64)> = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        // Symlinks could point anywhere on the host, so only plain files count
+        match entry.path().symlink_metadata() {
+            Ok(meta) if meta.file_type().is_file() => files.push((name, entry.path(), meta.len())),
+            _ => continue,
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut artifacts = Vec::new();
+    for (name, path, size) in files {
+        if size > max_bytes || artifacts.len() >= max_files {
+            warn!("Discarding outbox file {} ({} bytes)", name, size);
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        fs::create_dir_all(dest).map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to create artifact directory: {}", e),
+        })?;
+        let target = dest.join(&name);
+        move_file(&path, &target)?;
+        artifacts.push(Artifact {
+            kind: ArtifactKind::from_name(&name),
+            name,
+            path: target,
+            size,
+        });
+    }
+    Ok(artifacts)
+}
+
+/// Rename a file, copying when source and target are on different filesystems
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .and_then(|_| fs::remove_file(from))
+        .map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to move {}: {}", from.display(), e),
+        })
+}
+
+/// Collect the artifacts a run left in its outbox, logging failures
+///
+/// The run's outbox is removed afterwards; anything left in it is discarded.
+pub fn collect_run_artifacts(group_folder: &str, run_id: &str) -> Vec<Artifact> {
+    if validate_group_folder(group_folder).is_err() {
+        return Vec::new();
+    }
+    let outbox = match checked_run_outbox(&groups_dir().join(group_folder), run_id, false) {
+        Ok(Some(outbox)) => outbox,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            warn!("Refusing outbox of group {}: {}", group_folder, e);
+            return Vec::new();
+        }
+    };
+    let dest = artifacts_dir().join(group_folder).join(run_id);
+    let artifacts = collect_artifacts(&outbox, &dest, artifact_max_bytes(), artifact_max_files())
+        .unwrap_or_else(|e| {
+            warn!("Failed to collect artifacts for {}: {}", group_folder, e);
+            Vec::new()
+        });
+    let _ = fs::remove_dir_all(&outbox);
+    artifacts
+}
+
+/// Text listing the artifacts, for channels that cannot upload files
+pub fn artifact_summary(artifacts: &[Artifact]) -> Option<String> {
+    if artifacts.is_empty() {
+        return None;
+    }
+    let names: Vec<&str> = artifacts.iter().map(|a| a.name.as_str()).collect();
+    Some(format!("📎 Files produced: {}", names.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_artifact_kind_from_name() {
+        assert_eq!(ArtifactKind::from_name("chart.PNG"), ArtifactKind::Image);
+        assert_eq!(ArtifactKind::from_name("photo.jpeg"), ArtifactKind::Image);
+        assert_eq!(ArtifactKind::from_name("report.csv"), ArtifactKind::Document);
+        assert_eq!(ArtifactKind::from_name("README"), ArtifactKind::Document);
+    }
+
+    #[test]
+    fn test_collect_artifacts_moves_outbox_files() {
+        let tmp = TempDir::new().unwrap();
+        let group_dir = tmp.path().join("group");
+        let outbox = outbox_dir(&group_dir);
+        fs::create_dir_all(outbox.join("nested")).unwrap();
+        fs::write(outbox.join("chart.png"), b"png").unwrap();
+        fs::write(outbox.join("report.csv"), b"a,b\n1,2\n").unwrap();
+        fs::write(outbox.join(".partial.csv"), b"x").unwrap();
+        fs::write(group_dir.join("notes.md"), b"not in outbox").unwrap();
+
+        let dest = tmp.path().join("artifacts");
+        let artifacts = collect_artifacts(&outbox, &dest, 1024, 10).unwrap();
+
+        let names: Vec<&str> = artifacts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["chart.png", "report.csv"]);
+        assert_eq!(artifacts[0].kind, ArtifactKind::Image);
+        assert_eq!(artifacts[1].size, 8);
+        assert_eq!(fs::read(dest.join("report.csv")).unwrap(), b"a,b\n1,2\n");
+        assert!(!outbox.join("chart.png").exists());
+        assert!(outbox.join(".partial.csv").exists());
+    }
+
+    #[test]
+    fn test_collect_artifacts_enforces_limits() {
+        let tmp = TempDir::new().unwrap();
+        let group_dir = tmp.path().join("group");
+        let outbox = outbox_dir(&group_dir);
+        fs::create_dir_all(&outbox).unwrap();
+        fs::write(outbox.join("a.txt"), b"a").unwrap();
+        fs::write(outbox.join("b.txt"), b"b").unwrap();
+        fs::write(outbox.join("big.bin"), vec![0u8; 64]).unwrap();
+
+        let dest = tmp.path().join("artifacts");
+        let artifacts = collect_artifacts(&outbox, &dest, 16, 1).unwrap();
+
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].name, "a.txt");
+        assert_eq!(fs::read_dir(&outbox).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_artifacts_skips_symlinks() {
+        let tmp = TempDir::new().unwrap();
+        let group_dir = tmp.path().join("group");
+        let outbox = outbox_dir(&group_dir);
+        fs::create_dir_all(&outbox).unwrap();
+        let secret = tmp.path().join("secret.txt");
+        fs::write(&secret, b"host secret").unwrap();
+        std::os::unix::fs::symlink(&secret, outbox.join("leak.txt")).unwrap();
+
+        let artifacts = collect_artifacts(&outbox, &tmp.path().join("artifacts"), 1024, 10).unwrap();
+        assert!(artifacts.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_checked_run_outbox_refuses_symlinks() {
+        let tmp = TempDir::new().unwrap();
+        let group_dir = tmp.path().join("group");
+        let host_dir = tmp.path().join("ssh");
+        fs::create_dir_all(&host_dir).unwrap();
+        fs::write(host_dir.join("id_rsa"), b"host key").unwrap();
+
+        // A symlinked run outbox is neither read nor written through
+        fs::create_dir_all(outbox_dir(&group_dir)).unwrap();
+        std::os::unix::fs::symlink(&host_dir, outbox_dir(&group_dir).join("run-1")).unwrap();
+        assert!(checked_run_outbox(&group_dir, "run-1", false).is_err());
+        assert!(checked_run_outbox(&group_dir, "run-1", true).is_err());
+
+        // So is a symlinked outbox folder
+        let other_group = tmp.path().join("other");
+        fs::create_dir_all(&other_group).unwrap();
+        std::os::unix::fs::symlink(&host_dir, outbox_dir(&other_group)).unwrap();
+        assert!(checked_run_outbox(&other_group, "run-2", true).is_err());
+        assert!(!host_dir.join("run-2").exists());
+        assert!(host_dir.join("id_rsa").exists());
+
+        let outbox = checked_run_outbox(&group_dir, "run-3", true).unwrap().unwrap();
+        assert!(outbox.ends_with("outbox/run-3"));
+        assert!(outbox.is_dir());
+        assert_eq!(checked_run_outbox(&group_dir, "run-4", false).unwrap(), None);
+    }
+
+    #[test]
+    fn test_collect_artifacts_without_outbox() {
+        let tmp = TempDir::new().unwrap();
+        let dest = tmp.path().join("artifacts");
+        assert!(collect_artifacts(&outbox_dir(tmp.path()), &dest, 1024, 10).unwrap().is_empty());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_run_outbox_dir() {
+        let group_dir = Path::new("/groups/team");
+        let run_id = new_run_id();
+        assert_eq!(
+            run_outbox_dir(group_dir, &run_id),
+            Some(group_dir.join(OUTBOX_DIR).join(&run_id))
+        );
+        assert_ne!(new_run_id(), run_id);
+        assert_eq!(run_outbox_dir(group_dir, ""), None);
+        assert_eq!(run_outbox_dir(group_dir, "../other"), None);
+    }
+
+    #[test]
+    fn test_artifact_summary() {
+        assert_eq!(artifact_summary(&[]), None);
+        let artifact = Artifact {
+            name: "report.csv".to_string(),
+            path: PathBuf::from("/tmp/report.csv"),
+            size: 3,
+            kind: ArtifactKind::Document,
+        };
+        assert_eq!(
+            artifact_summary(&[artifact]).as_deref(),
+            Some("📎 Files produced: report.csv")
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Token and spend budgets for NuClaw
+//!
+//! Checks a group's (and a user's) recorded usage against the daily/monthly
+//! limits from WORKFLOW.md before a message is handed to the agent.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::db::Database;
+use crate::error::Result;
+use crate::usage::{usage_since, UsagePeriod};
+use crate::workflow::{workflow_config, BudgetSettings};
+
+/// Default fraction of a budget at which a warning is sent
+pub const DEFAULT_WARN_RATIO: f64 = 0.8;
+
+/// Outcome of a budget check
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetStatus {
+    /// Within all budgets
+    Ok,
+    /// A budget crossed the warning threshold; the message should be sent once
+    Warning(BudgetWarning),
+    /// A budget is used up; the request must not run
+    Exhausted(String),
+}
+
+/// A warning for one limit in its current period
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetWarning {
+    /// Text to send to the chat
+    pub message: String,
+    limit: String,
+    period_key: String,
+}
+
+impl BudgetWarning {
+    /// Record the warning as delivered so it is not repeated this period
+    ///
+    /// Call this only after the message was sent; an undelivered warning is
+    /// reported again on the next check.
+    pub fn mark_sent(&self) {
+        let daily = UsagePeriod::Daily.current_key();
+        let monthly = UsagePeriod::Monthly.current_key();
+        let mut sent = sent_warnings().lock().unwrap();
+        sent.retain(|_, period_key| *period_key == daily || *period_key == monthly);
+        sent.insert(self.limit.clone(), self.period_key.clone());
+    }
+
+    fn is_sent(&self, sent: &HashMap<String, String>) -> bool {
+        sent.get(&self.limit) == Some(&self.period_key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    Group,
+    User,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Measure {
+    Tokens,
+    Spend,
+}
+
+/// One configured limit and how much of it is used
+#[derive(Debug, Clone)]
+struct LimitUsage {
+    scope: Scope,
+    period: UsagePeriod,
+    measure: Measure,
+    used: f64,
+    limit: f64,
+}
+
+impl LimitUsage {
+    fn ratio(&self) -> f64 {
+        if self.limit <= 0.0 {
+            f64::INFINITY
+        } else {
+            self.used / self.limit
+        }
+    }
+
+    fn description(&self) -> String {
+        let owner = match self.scope {
+            Scope::Group => "this group's",
+            Scope::User => "your",
+        };
+        let period = match self.period {
+            UsagePeriod::Daily => "daily",
+            UsagePeriod::Monthly => "monthly",
+        };
+        let measure = match self.measure {
+            Measure::Tokens => "token",
+            Measure::Spend => "spend",
+        };
+        format!("{} {} {} budget", owner, period, measure)
+    }
+
+    fn amounts(&self) -> String {
+        match self.measure {
+            Measure::Tokens => format!("{}/{} tokens", self.used as u64, self.limit as u64),
+            Measure::Spend => format!("${:.2}/${:.2}", self.used, self.limit),
+        }
+    }
+
+    fn resets(&self) -> &'static str {
+        match self.period {
+            UsagePeriod::Daily => "at 00:00 UTC",
+            UsagePeriod::Monthly => "on the 1st of next month (UTC)",
+        }
+    }
+
+    fn exhausted_message(&self) -> String {
+        format!(
+            "⛔ Sorry, {} is used up ({}). It resets {}.",
+            self.description(),
+            self.amounts(),
+            self.resets()
+        )
+    }
+
+    fn warning_message(&self) -> String {
+        format!(
+            "⚠️ {:.0}% of {} has been used ({}).",
+            self.ratio() * 100.0,
+            self.description(),
+            self.amounts()
+        )
+    }
+
+    /// Build the warning for this limit in the current period
+    fn warning(&self, group_folder: &str, user: &str) -> BudgetWarning {
+        let who = match self.scope {
+            Scope::Group => "",
+            Scope::User => user,
+        };
+        BudgetWarning {
+            message: self.warning_message(),
+            limit: format!("{}|{}|{:?}|{:?}", group_folder, who, self.measure, self.period),
+            period_key: self.period.current_key(),
+        }
+    }
+}
+
+/// Collect usage for every limit configured in `budget`
+fn limit_usages(db: &Database, budget: &BudgetSettings, group_folder: &str, user: &str) -> Result<Vec<LimitUsage>> {
+    let mut limits = Vec::new();
+
+    for period in [UsagePeriod::Daily, UsagePeriod::Monthly] {
+        let (tokens, spend, user_tokens) = match period {
+            UsagePeriod::Daily => (budget.daily_tokens, budget.daily_spend_usd, budget.user_daily_tokens),
+            UsagePeriod::Monthly => (budget.monthly_tokens, budget.monthly_spend_usd, budget.user_monthly_tokens),
+        };
+        let since = period.current_key();
+
+        if tokens.is_some() || spend.is_some() {
+            let totals = usage_since(db, group_folder, None, &since)?;
+            if let Some(limit) = tokens {
+                limits.push(LimitUsage {
+                    scope: Scope::Group,
+                    period,
+                    measure: Measure::Tokens,
+                    used: totals.tokens as f64,
+                    limit: limit as f64,
+                });
+            }
+            if let Some(limit) = spend {
+                limits.push(LimitUsage {
+                    scope: Scope::Group,
+                    period,
+                    measure: Measure::Spend,
+                    used: totals.cost_usd,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(limit) = user_tokens {
+            let totals = usage_since(db, group_folder, Some(user), &since)?;
+            limits.push(LimitUsage {
+                scope: Scope::User,
+                period,
+                measure: Measure::Tokens,
+                used: totals.tokens as f64,
+                limit: limit as f64,
+            });
+        }
+    }
+
+    Ok(limits)
+}
+
+/// Check a group and user against `budget`
+///
+/// Every call past the warning threshold reports `Warning`; use
+/// [`check_budget_for`] to skip warnings that were already delivered.
+pub fn check_budget(db: &Database, budget: &BudgetSettings, group_folder: &str, user: &str) -> Result<BudgetStatus> {
+    Ok(match evaluate(db, budget, group_folder, user)? {
+        Err(exhausted) => exhausted,
+        Ok(warnings) => warnings
+            .into_iter()
+            .next()
+            .map(BudgetStatus::Warning)
+            .unwrap_or(BudgetStatus::Ok),
+    })
+}
+
+/// Evaluate budgets, returning `Err(Exhausted)` or the warnings due, highest first
+fn evaluate(
+    db: &Database,
+    budget: &BudgetSettings,
+    group_folder: &str,
+    user: &str,
+) -> Result<std::result::Result<Vec<BudgetWarning>, BudgetStatus>> {
+    if !budget.is_limited() {
+        return Ok(Ok(Vec::new()));
+    }
+
+    let mut limits = limit_usages(db, budget, group_folder, user)?;
+    if let Some(exhausted) = limits.iter().find(|l| l.ratio() >= 1.0) {
+        return Ok(Err(BudgetStatus::Exhausted(exhausted.exhausted_message())));
+    }
+
+    let warn_ratio = budget.warn_ratio.unwrap_or(DEFAULT_WARN_RATIO);
+    limits.retain(|l| l.ratio() >= warn_ratio);
+    limits.sort_by(|a, b| b.ratio().total_cmp(&a.ratio()));
+    Ok(Ok(limits.iter().map(|l| l.warning(group_folder, user)).collect()))
+}
+
+/// Delivered warnings: limit identity (group, user, measure, period) to period key
+fn sent_warnings() -> &'static Mutex<HashMap<String, String>> {
+    static SENT: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    SENT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Check the WORKFLOW.md budget of a group before handling a message
+///
+/// Warnings already marked sent for the current period are skipped; callers
+/// call [`BudgetWarning::mark_sent`] once the warning is delivered. Errors
+/// reading usage are logged and treated as within budget so accounting
+/// problems never block chats.
+pub fn check_budget_for(db: &Database, group_folder: &str, user: &str) -> BudgetStatus {
+    let budget = workflow_config().budget_for_group(group_folder);
+    match evaluate(db, &budget, group_folder, user) {
+        Ok(Err(exhausted)) => {
+            tracing::info!("Budget exhausted for group {}, user {}", group_folder, user);
+            exhausted
+        }
+        Ok(Ok(warnings)) => {
+            let sent = sent_warnings().lock().unwrap();
+            warnings
+                .into_iter()
+                .find(|w| !w.is_sent(&sent))
+                .map(BudgetStatus::Warning)
+                .unwrap_or(BudgetStatus::Ok)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to check budget for {}: {}", group_folder, e);
+            BudgetStatus::Ok
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{remove_test_db, test_db};
+    use crate::providers::Usage;
+    use crate::usage::{record_usage, UsageRecord};
+
+    fn spend(db: &Database, user: &str, tokens: u64, cost_usd: f64) {
+        record_usage(
+            db,
+            &UsageRecord {
+                group_folder: "team".to_string(),
+                chat_jid: "telegram:1".to_string(),
+                user: user.to_string(),
+                provider: None,
+                model: "gpt-4o".to_string(),
+                usage: Usage {
+                    input_tokens: tokens,
+                    output_tokens: 0,
+                },
+                cost_usd,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_unlimited_budget_is_ok() {
+        let (db, path) = test_db("test_budget_unlimited.db");
+        spend(&db, "alice", 1_000_000, 100.0);
+        let status = check_budget(&db, &BudgetSettings::default(), "team", "alice").unwrap();
+        assert_eq!(status, BudgetStatus::Ok);
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_group_token_budget_warning_and_exhaustion() {
+        let (db, path) = test_db("test_budget_group.db");
+        let budget = BudgetSettings {
+            daily_tokens: Some(1000),
+            ..Default::default()
+        };
+
+        spend(&db, "alice", 500, 0.0);
+        assert_eq!(check_budget(&db, &budget, "team", "alice").unwrap(), BudgetStatus::Ok);
+
+        spend(&db, "bob", 350, 0.0);
+        match check_budget(&db, &budget, "team", "alice").unwrap() {
+            BudgetStatus::Warning(warning) => {
+                assert!(warning.message.contains("85%"));
+                assert!(warning.message.contains("this group's daily token budget"));
+            }
+            other => panic!("expected warning, got {:?}", other),
+        }
+
+        spend(&db, "bob", 150, 0.0);
+        match check_budget(&db, &budget, "team", "alice").unwrap() {
+            BudgetStatus::Exhausted(message) => assert!(message.contains("1000/1000 tokens")),
+            other => panic!("expected exhausted, got {:?}", other),
+        }
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_user_and_spend_budgets() {
+        let (db, path) = test_db("test_budget_user.db");
+        spend(&db, "alice", 300, 4.0);
+
+        let user_budget = BudgetSettings {
+            user_monthly_tokens: Some(300),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_budget(&db, &user_budget, "team", "alice").unwrap(),
+            BudgetStatus::Exhausted(m) if m.contains("your monthly token budget")
+        ));
+        assert_eq!(check_budget(&db, &user_budget, "team", "bob").unwrap(), BudgetStatus::Ok);
+
+        let spend_budget = BudgetSettings {
+            monthly_spend_usd: Some(5.0),
+            warn_ratio: Some(0.5),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_budget(&db, &spend_budget, "team", "bob").unwrap(),
+            BudgetStatus::Warning(w) if w.message.contains("$4.00/$5.00")
+        ));
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_warning_is_repeated_until_marked_sent() {
+        let (db, path) = test_db("test_budget_mark_sent.db");
+        let budget = BudgetSettings {
+            daily_tokens: Some(100),
+            ..Default::default()
+        };
+        spend(&db, "carol", 90, 0.0);
+
+        let warning = match check_budget(&db, &budget, "team", "carol").unwrap() {
+            BudgetStatus::Warning(warning) => warning,
+            other => panic!("expected warning, got {:?}", other),
+        };
+        assert!(!warning.is_sent(&sent_warnings().lock().unwrap()));
+
+        warning.mark_sent();
+        assert!(warning.is_sent(&sent_warnings().lock().unwrap()));
+
+        let stale = BudgetWarning {
+            period_key: "1999-01-01".to_string(),
+            ..warning.clone()
+        };
+        assert!(!stale.is_sent(&sent_warnings().lock().unwrap()));
+
+        remove_test_db(&path);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::error::Result;
+use crate::db::Database;
+use crate::error::{NuClawError, Result};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::RwLock;
@@ -83,6 +84,52 @@ pub fn channel_registry() -> ChannelRegistry {
     ChannelRegistry::new()
 }
 
+/// Channel that owns a chat jid, judged by its prefix
+pub fn channel_for_jid(jid: &str) -> Option<&'static str> {
+    if jid.starts_with("telegram:") {
+        Some("telegram")
+    } else if jid.starts_with("feishu:") {
+        Some("feishu")
+    } else if jid.starts_with("wechat:") {
+        Some("wechat")
+    } else if jid.ends_with("@s.whatsapp.net") || jid.ends_with("@g.us") {
+        Some("whatsapp")
+    } else {
+        None
+    }
+}
+
+/// Send a text message to any chat jid through the channel that owns it
+pub async fn send_to_jid(db: &Database, jid: &str, text: &str) -> Result<()> {
+    match channel_for_jid(jid) {
+        Some("telegram") => {
+            let chat_id = crate::telegram::utils::extract_chat_id_pure(jid).unwrap_or_default();
+            crate::telegram::client::TelegramClient::new(db.clone())?
+                .send_message(&chat_id, text)
+                .await
+        }
+        Some("feishu") => {
+            let receive_id = jid
+                .strip_prefix("feishu:chat:")
+                .or_else(|| jid.strip_prefix("feishu:user:"))
+                .unwrap_or(jid);
+            crate::feishu::FeishuClient::new(db.clone())?
+                .send_message(receive_id, text)
+                .await
+        }
+        Some("wechat") => {
+            let to_wxid = jid.strip_prefix("wechat:").unwrap_or(jid);
+            crate::wechat::WeChatClient::new(db.clone())?
+                .send_message(to_wxid, text)
+                .await
+        }
+        Some(_) => crate::whatsapp::send_whatsapp_message(jid, text).await,
+        None => Err(NuClawError::Config {
+            message: format!("No channel handles chat {}", jid),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,4 +244,14 @@ mod tests {
         let result = registry.register(MockChannel::new("test", true));
         assert!(!result.list().is_empty());
     }
+
+    #[test]
+    fn test_channel_for_jid() {
+        assert_eq!(channel_for_jid("telegram:group:42"), Some("telegram"));
+        assert_eq!(channel_for_jid("feishu:chat:oc_1"), Some("feishu"));
+        assert_eq!(channel_for_jid("wechat:wxid_1"), Some("wechat"));
+        assert_eq!(channel_for_jid("123@s.whatsapp.net"), Some("whatsapp"));
+        assert_eq!(channel_for_jid("123-456@g.us"), Some("whatsapp"));
+        assert_eq!(channel_for_jid("unknown"), None);
+    }
 }
@@ -48,6 +48,13 @@ pub fn config_path() -> PathBuf {
     nuclaw_home().join("config.json")
 }
 
+/// WORKFLOW.md path (overridable with WORKFLOW_PATH)
+pub fn workflow_path() -> PathBuf {
+    env::var("WORKFLOW_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| nuclaw_home().join("WORKFLOW.md"))
+}
+
 /// Load .env file into environment variables (if exists)
 /// Only sets env vars that are not already set
 pub fn load_env_file() {
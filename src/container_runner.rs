@@ -2,7 +2,7 @@
 //!
 //! Supports:
 //! - macOS: Apple Container via `container` CLI
-//! - Linux: Docker via `docker` CLI
+//! - Linux: Docker via `docker` CLI (see `sandbox` for the daemonless runtime)
 //!
 //! Features:
 //! - Filesystem isolation per group
@@ -10,17 +10,23 @@
 //! - Configurable timeout
 //! - Output parsing with sentinel markers
 
+use crate::artifacts::{collect_run_artifacts, new_run_id, prepare_outbox};
 use crate::config::{
     anthropic_api_key, anthropic_base_url, assistant_name, claude_model, data_dir, groups_dir,
     logs_dir,
 };
 use crate::error::{NuClawError, Result};
-use crate::types::{ContainerInput, ContainerOutput};
+use crate::ipc::{ipc_mount, register_group_chat};
+use crate::mounts::{group_mounts, mount_args};
+use crate::runtime::{podman_is_rootless, runtime_kind, select_runtime, RuntimeKind};
+use crate::secrets::{group_secrets_for_run, GroupSecrets};
+use crate::types::{ContainerInput, ContainerOutput, ContainerProgress};
+use crate::workflow::{load_workflow_config, ContainerLimits};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{ChildStdout, Command as AsyncCommand};
+use tokio::process::{ChildStderr, ChildStdout, Command as AsyncCommand};
 use tokio::time::{timeout, Duration, Instant};
 use tracing::{info, warn};
 
@@ -31,6 +37,18 @@ const DEFAULT_MAX_OUTPUT: usize = 10 * 1024 * 1024;
 /// Sentinel markers for output parsing
 const OUTPUT_START_MARKER: &str = "--NUCLAW_OUTPUT_START--";
 const OUTPUT_END_MARKER: &str = "--NUCLAW_OUTPUT_END--";
+/// Prefix of intermediate progress lines (`--NUCLAW_PROGRESS--{json}`)
+pub const PROGRESS_MARKER: &str = "--NUCLAW_PROGRESS--";
+/// Output status when the container was OOM-killed
+pub const STATUS_OOM_KILLED: &str = "oom_killed";
+/// Output status when the container hit its disk or process limit
+pub const STATUS_LIMIT_EXCEEDED: &str = "limit_exceeded";
+
+/// Receives progress lines while a container runs
+///
+/// Only the Telegram client consumes progress (by editing a placeholder
+/// message); other channels run without one and send the final output.
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<ContainerProgress>;
 
 /// Get container timeout from environment or default
 pub fn container_timeout() -> Duration {
@@ -49,15 +67,33 @@ pub fn max_output_size() -> usize {
         .unwrap_or(DEFAULT_MAX_OUTPUT)
 }
 
-/// Get the container command based on platform
+/// Get the container command for the selected runtime
 fn get_container_command() -> &'static str {
-    if cfg!(target_os = "macos") {
+    if runtime_kind() == RuntimeKind::Podman {
+        "podman"
+    } else if cfg!(target_os = "macos") {
         "container"
     } else {
         "docker"
     }
 }
 
+/// Whether agents run through Apple `container` rather than a Docker-style CLI
+fn uses_apple_container() -> bool {
+    cfg!(target_os = "macos") && runtime_kind() != RuntimeKind::Podman
+}
+
+/// `run` flags the container engine needs besides resource limits
+///
+/// Rootless Podman maps the host user onto the container user, so files the
+/// agent writes to the group folder stay owned by the host user.
+pub fn engine_run_args(kind: RuntimeKind) -> Vec<String> {
+    match kind {
+        RuntimeKind::Podman => vec!["--userns=keep-id".to_string()],
+        _ => Vec::new(),
+    }
+}
+
 /// Create IPC directory for a group
 pub fn create_group_ipc_directory(group_folder: &str) -> Result<PathBuf> {
     let ipc_dir = data_dir().join("ipc").join(group_folder);
@@ -68,8 +104,9 @@ pub fn create_group_ipc_directory(group_folder: &str) -> Result<PathBuf> {
 }
 
 /// Write IPC files for container context
-fn write_ipc_files(group_folder: &str, input: &ContainerInput) -> Result<()> {
+pub(crate) fn write_ipc_files(group_folder: &str, input: &ContainerInput) -> Result<()> {
     let ipc_dir = create_group_ipc_directory(group_folder)?;
+    register_group_chat(group_folder, &input.chat_jid, input.is_main, input.run_id.as_deref());
 
     // Write current_tasks.json
     let tasks_path = ipc_dir.join("current_tasks.json");
@@ -110,7 +147,7 @@ fn write_ipc_files(group_folder: &str, input: &ContainerInput) -> Result<()> {
 }
 
 /// Validate group_folder to prevent path traversal attacks
-fn validate_group_folder(group_folder: &str) -> Result<()> {
+pub(crate) fn validate_group_folder(group_folder: &str) -> Result<()> {
     // Check for null bytes
     if group_folder.contains('\0') {
         return Err(NuClawError::Security {
@@ -151,7 +188,7 @@ fn validate_group_folder(group_folder: &str) -> Result<()> {
 }
 
 /// Prepare group context directory
-fn prepare_group_context(group_folder: &str) -> Result<PathBuf> {
+pub(crate) fn prepare_group_context(group_folder: &str) -> Result<PathBuf> {
     // Validate input first
     validate_group_folder(group_folder)?;
 
@@ -165,32 +202,117 @@ fn prepare_group_context(group_folder: &str) -> Result<PathBuf> {
 }
 
 /// Run a container with the given input
+///
+/// Uses a warm container from the pool when pooling is enabled, falling back
+/// to a one-shot `docker run` otherwise.
 pub async fn run_container(input: ContainerInput) -> Result<ContainerOutput> {
+    run_container_with_progress(input, None).await
+}
+
+/// Run a container, forwarding its progress lines to `progress` as they arrive
+///
+/// The final marked output still forms the returned `ContainerOutput`.
+pub async fn run_container_with_progress(
+    mut input: ContainerInput,
+    progress: Option<ProgressSender>,
+) -> Result<ContainerOutput> {
+    // Files the agent leaves in its run's outbox are delivered with the output
+    let run_id = input.run_id.get_or_insert_with(new_run_id).clone();
+    prepare_outbox(&input.group_folder, &run_id);
+    let group_folder = input.group_folder.clone();
+    let result = run_agent_container(input, progress).await;
+    let artifacts = collect_run_artifacts(&group_folder, &run_id);
+    let mut output = result?;
+    group_secrets_for_run(&group_folder).redact_output(&mut output);
+    output.artifacts = artifacts;
+    Ok(output)
+}
+
+/// Run the agent in a pooled container or a one-shot `docker run`
+async fn run_agent_container(
+    input: ContainerInput,
+    progress: Option<ProgressSender>,
+) -> Result<ContainerOutput> {
+    let pool = global_pool();
+    if pool.is_enabled() && !uses_apple_container() {
+        validate_group_folder(&input.group_folder)?;
+        if let Some(container) = pool.acquire_for(&input.group_folder, input.is_main).await {
+            return container.run(input, progress.as_ref()).await;
+        }
+    }
+
     let group_folder = &input.group_folder;
     let group_dir = prepare_group_context(group_folder)?;
     write_ipc_files(group_folder, &input)?;
-    let (mut cmd, input_path) = build_container_command(&input, &group_dir).await?;
+    let limits = load_workflow_config().container_limits_for_group(group_folder);
+    let (mut cmd, input_path, container_name) =
+        build_container_command(&input, &group_dir, &limits).await?;
     let timeout_duration = container_timeout();
-    let output = run_container_with_output(&mut cmd, timeout_duration).await?;
+    let output = run_container_with_output(
+        &mut cmd,
+        container_name.as_deref(),
+        &limits,
+        timeout_duration,
+        progress.as_ref(),
+    )
+    .await?;
     let _ = fs::remove_file(&input_path);
     Ok(output)
 }
 
+/// Pass a group's secrets by name; the values travel in the CLI's environment,
+/// not its arguments
+fn add_secret_env(cmd: &mut AsyncCommand, secrets: &GroupSecrets) {
+    for (name, value) in secrets.iter() {
+        cmd.arg("-e").arg(name).env(name, value);
+    }
+}
+
+/// `docker run` flags for resource limits and network policy
+pub fn container_limit_args(limits: &ContainerLimits) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(cpus) = limits.cpus {
+        args.push(format!("--cpus={}", cpus));
+    }
+    if let Some(memory) = &limits.memory {
+        // Equal memory and swap limits disable swap, so the limit is a hard one
+        args.push(format!("--memory={}", memory));
+        args.push(format!("--memory-swap={}", memory));
+    }
+    if let Some(pids) = limits.pids_limit {
+        args.push(format!("--pids-limit={}", pids));
+    }
+    if let Some(disk) = &limits.disk {
+        args.push("--storage-opt".to_string());
+        args.push(format!("size={}", disk));
+    }
+    if let Some(network) = &limits.network {
+        args.push(format!("--network={}", network));
+    }
+    args
+}
+
+/// Unique name for a one-shot agent container
+fn one_shot_container_name(group_folder: &str) -> String {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let group: String = group_folder
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' })
+        .collect();
+    format!("nuclaw-{}-{}", group, &suffix[..12])
+}
+
 async fn build_container_command(
     input: &ContainerInput,
     group_dir: &Path,
-) -> Result<(AsyncCommand, PathBuf)> {
+    limits: &ContainerLimits,
+) -> Result<(AsyncCommand, PathBuf, Option<String>)> {
     let temp_dir = data_dir().join("temp");
     fs::create_dir_all(&temp_dir).map_err(|e| NuClawError::FileSystem {
         message: format!("Failed to create temp directory: {}", e),
     })?;
-    let input_path = temp_dir.join(format!(
-        "input_{}.json",
-        input
-            .session_id
-            .clone()
-            .unwrap_or_else(|| "default".to_string())
-    ));
+    // Unique per run: runs that resume the same session may overlap
+    let input_path = temp_dir.join(format!("input_{}.json", uuid::Uuid::new_v4()));
     let input_json = serde_json::to_string(input).map_err(|e| NuClawError::Container {
         message: format!("Failed to serialize input: {}", e),
     })?;
@@ -198,7 +320,8 @@ async fn build_container_command(
         message: format!("Failed to write input file: {}", e),
     })?;
     let mut cmd = AsyncCommand::new(get_container_command());
-    if cfg!(target_os = "macos") {
+    let mut container_name = None;
+    if uses_apple_container() {
         cmd.arg("exec")
             .arg("--workspace")
             .arg(group_dir)
@@ -209,10 +332,18 @@ async fn build_container_command(
     } else {
         let image = std::env::var("CONTAINER_IMAGE")
             .unwrap_or_else(|_| "anthropic/claude-code:latest".to_string());
+        // Named and kept after exit so the OOM flag can be inspected; removed afterwards
+        let name = one_shot_container_name(&input.group_folder);
+        let mut extra_mounts = group_mounts(&input.group_folder, input.is_main)?;
+        extra_mounts.push(ipc_mount(&input.group_folder)?);
         cmd.arg("run")
-            .arg("--rm")
+            .arg("--name")
+            .arg(&name)
+            .args(container_limit_args(limits))
+            .args(engine_run_args(runtime_kind()))
             .arg("-v")
             .arg(format!("{}:/workspace/group", group_dir.display()))
+            .args(mount_args(&extra_mounts))
             .arg("-e")
             .arg("CLAUDE_CODE_OAUTH_TOKEN");
 
@@ -228,24 +359,31 @@ async fn build_container_command(
             cmd.arg("-e").arg("CLAUDE_MODEL");
         }
 
+        add_secret_env(&mut cmd, &group_secrets_for_run(&input.group_folder));
+
         cmd.arg("--entrypoint")
             .arg("/usr/local/bin/claude")
             .arg(image)
             .arg("/workspace/input.json");
+        container_name = Some(name);
     }
     cmd.stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped());
-    Ok((cmd, input_path))
+    Ok((cmd, input_path, container_name))
 }
 
 async fn run_container_with_output(
     cmd: &mut AsyncCommand,
+    container_name: Option<&str>,
+    limits: &ContainerLimits,
     timeout_duration: Duration,
+    progress: Option<&ProgressSender>,
 ) -> Result<ContainerOutput> {
     let mut child = cmd.spawn().map_err(|e| NuClawError::Container {
         message: format!("Failed to spawn container: {}", e),
     })?;
+    let mut cleanup = ContainerCleanup(container_name);
     let start_time = Instant::now();
     if let Some(mut stdin) = child.stdin.take() {
         let input_path = data_dir().join("temp/input.json");
@@ -266,29 +404,172 @@ async fn run_container_with_output(
         })?;
     }
     let stdout = child.stdout.take().unwrap();
-    let output_result = timeout(timeout_duration, capture_output(stdout)).await;
+    let stderr_task = child.stderr.take().map(|stderr| tokio::spawn(capture_stderr(stderr)));
+    let output_result = timeout(timeout_duration, capture_output(stdout, progress)).await;
+    if output_result.is_err() {
+        let _ = child.kill().await;
+        cleanup.remove_now();
+    }
     let exit_status = child.wait().await.map_err(|e| NuClawError::Container {
         message: format!("Failed to wait for container: {}", e),
     })?;
     let duration_ms = start_time.elapsed().as_millis() as i64;
+    let stderr = match stderr_task {
+        Some(task) => task.await.unwrap_or_default(),
+        None => String::new(),
+    };
     match output_result {
         Ok(output) => {
-            let output = output?;
-            parse_container_output(&output, exit_status.success(), duration_ms)
+            let output = parse_container_output(&output?, exit_status.success(), duration_ms)?;
+            let exit = container_name.and_then(inspect_container_exit).unwrap_or_default();
+            Ok(apply_limit_status(output, &exit, &stderr, limits))
         }
-        Err(_) => {
-            let _ = child.kill().await;
-            parse_container_output("", false, duration_ms)
+        Err(_) => parse_container_output("", false, duration_ms),
+    }
+}
+
+/// Keep the tail of stderr for diagnosing limit hits
+async fn capture_stderr(stderr: ChildStderr) -> String {
+    const MAX_STDERR: usize = 64 * 1024;
+    let mut lines = BufReader::new(stderr).lines();
+    let mut output = String::new();
+    while let Some(line) = lines.next_line().await.ok().flatten() {
+        output.push_str(&line);
+        output.push('\n');
+        if output.len() > MAX_STDERR {
+            let cut = output.len() - MAX_STDERR;
+            let cut = (cut..output.len()).find(|&i| output.is_char_boundary(i)).unwrap_or(0);
+            output.drain(..cut);
         }
     }
+    output
+}
+
+/// How a container exited, as reported by `docker inspect`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ContainerExit {
+    pub oom_killed: bool,
+    pub exit_code: Option<i32>,
 }
 
-async fn capture_output(stdout: ChildStdout) -> Result<String> {
+impl ContainerExit {
+    /// Parse `{{.State.OOMKilled}} {{.State.ExitCode}}`
+    fn parse(state: &str) -> Option<Self> {
+        let mut parts = state.split_whitespace();
+        let oom_killed = parts.next()?.parse().ok()?;
+        let exit_code = parts.next().and_then(|c| c.parse().ok());
+        Some(Self {
+            oom_killed,
+            exit_code,
+        })
+    }
+}
+
+fn inspect_container_exit(name: &str) -> Option<ContainerExit> {
+    let output = Command::new(get_container_command())
+        .args(["inspect", "--format", "{{.State.OOMKilled}} {{.State.ExitCode}}", name])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    ContainerExit::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Removes a one-shot container when dropped, so early returns cannot leak it
+struct ContainerCleanup<'a>(Option<&'a str>);
+
+impl ContainerCleanup<'_> {
+    fn remove_now(&mut self) {
+        if let Some(name) = self.0.take() {
+            remove_container(name);
+        }
+    }
+}
+
+impl Drop for ContainerCleanup<'_> {
+    fn drop(&mut self) {
+        self.remove_now();
+    }
+}
+
+fn remove_container(name: &str) {
+    if let Err(e) = Command::new(get_container_command())
+        .args(["rm", "-f", name])
+        .output()
+    {
+        warn!("Failed to remove container {}: {}", name, e);
+    }
+}
+
+/// Report resource limit hits with their own status instead of a generic error
+pub(crate) fn apply_limit_status(
+    mut output: ContainerOutput,
+    exit: &ContainerExit,
+    stderr: &str,
+    limits: &ContainerLimits,
+) -> ContainerOutput {
+    let (status, error) = if exit.oom_killed {
+        (
+            STATUS_OOM_KILLED,
+            format!(
+                "Agent container was killed after exceeding its memory limit ({})",
+                limits.memory.as_deref().unwrap_or("unset")
+            ),
+        )
+    } else if output.status == "success" {
+        return output;
+    } else if limits.disk.is_some() && stderr.contains("No space left on device") {
+        (
+            STATUS_LIMIT_EXCEEDED,
+            format!(
+                "Agent container ran out of disk space (limit {})",
+                limits.disk.as_deref().unwrap_or_default()
+            ),
+        )
+    } else if limits.pids_limit.is_some() && stderr.contains("Resource temporarily unavailable") {
+        (
+            STATUS_LIMIT_EXCEEDED,
+            format!(
+                "Agent container hit its process limit ({})",
+                limits.pids_limit.unwrap_or_default()
+            ),
+        )
+    } else {
+        return output;
+    };
+    warn!("{}", error);
+    output.status = status.to_string();
+    output.error = Some(error);
+    output
+}
+
+/// Parse a `--NUCLAW_PROGRESS--{json}` line
+pub fn parse_progress_line(line: &str) -> Option<ContainerProgress> {
+    let json = line.trim().strip_prefix(PROGRESS_MARKER)?;
+    serde_json::from_str(json).ok()
+}
+
+/// Read stdout until the process exits
+///
+/// Progress lines are forwarded to `progress` and left out of the returned
+/// output, so only the final output is parsed.
+pub(crate) async fn capture_output(
+    stdout: ChildStdout,
+    progress: Option<&ProgressSender>,
+) -> Result<String> {
     let reader = BufReader::new(stdout);
     let mut lines = reader.lines();
     let mut output = String::new();
     let max_size = max_output_size();
     while let Some(line) = lines.next_line().await.ok().flatten() {
+        if line.trim_start().starts_with(PROGRESS_MARKER) {
+            if let (Some(sender), Some(update)) = (progress, parse_progress_line(&line)) {
+                // The receiver may have gone away; the run still completes
+                let _ = sender.send(update);
+            }
+            continue;
+        }
         if output.len() + line.len() > max_size {
             output.push_str("\n[OUTPUT TRUNCATED - exceeded max size]");
             break;
@@ -299,7 +580,7 @@ async fn capture_output(stdout: ChildStdout) -> Result<String> {
     Ok(output)
 }
 
-fn parse_container_output(
+pub(crate) fn parse_container_output(
     output: &str,
     success: bool,
     _duration_ms: i64,
@@ -329,6 +610,11 @@ fn parse_container_output(
         } else {
             Some("Container execution failed".to_string())
         },
+        tools_used: Vec::new(),
+        provider: None,
+        model: None,
+        usage: None,
+        artifacts: Vec::new(),
     })
 }
 
@@ -346,6 +632,9 @@ fn parse_marked_content(content: &str, success: bool) -> Result<ContainerOutput>
     if let Ok(parsed) = serde_json::from_str::<ContainerOutput>(content) {
         return Ok(parsed);
     }
+    if let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(content) {
+        return Ok(output_from_fields(&fields, success));
+    }
     Ok(ContainerOutput {
         status: if success {
             "success".to_string()
@@ -359,29 +648,62 @@ fn parse_marked_content(content: &str, success: bool) -> Result<ContainerOutput>
         } else {
             Some("Container execution failed".to_string())
         },
+        tools_used: Vec::new(),
+        provider: None,
+        model: None,
+        usage: None,
+        artifacts: Vec::new(),
     })
 }
 
-pub fn ensure_container_system_running() -> Result<()> {
-    let output = Command::new(get_container_command())
-        .args(["system", "status"])
-        .output();
-    match output {
-        Ok(_) => Ok(()),
-        Err(_) => {
-            let output = Command::new(get_container_command())
-                .args(["system", "start"])
-                .output();
-            match output {
-                Ok(_) => Ok(()),
-                Err(e) => Err(NuClawError::Container {
-                    message: format!("Failed to start container system: {}", e),
-                }),
-            }
-        }
+/// Output from agent JSON in another shape, e.g. an SDK result message
+/// (`result`, `session_id`, `is_error`, `usage`)
+fn output_from_fields(
+    fields: &serde_json::Map<String, serde_json::Value>,
+    success: bool,
+) -> ContainerOutput {
+    let text = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| fields.get(*key).and_then(|v| v.as_str()))
+            .map(str::to_string)
+    };
+    let failed = !success
+        || fields.get("is_error").and_then(|v| v.as_bool()) == Some(true)
+        || text(&["status"]).is_some_and(|status| status != "success");
+    let result = text(&["result"]);
+    ContainerOutput {
+        status: if failed { "error" } else { "success" }.to_string(),
+        error: if failed {
+            text(&["error"])
+                .or_else(|| result.clone())
+                .or_else(|| Some("Container execution failed".to_string()))
+        } else {
+            None
+        },
+        result,
+        new_session_id: text(&["new_session_id", "newSessionId", "session_id"]),
+        tools_used: Vec::new(),
+        provider: None,
+        model: text(&["model"]),
+        usage: fields
+            .get("usage")
+            .and_then(|usage| serde_json::from_value(usage.clone()).ok()),
+        artifacts: Vec::new(),
     }
 }
 
+/// Select the runtime agents run in and make sure it is usable
+///
+/// Uses CONTAINER_RUNTIME when set, otherwise the first of docker, podman
+/// and the namespace sandbox that works on this host.
+pub fn ensure_container_system_running() -> Result<RuntimeKind> {
+    let kind = select_runtime()?;
+    if kind == RuntimeKind::Podman && !podman_is_rootless() {
+        warn!("Podman is running as root; files in group folders will be owned by root");
+    }
+    Ok(kind)
+}
+
 pub fn log_container_output(
     group_folder: &str,
     session_id: &str,
@@ -393,13 +715,16 @@ pub fn log_container_output(
     })?;
     let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
     let log_path = log_dir.join(format!("container_{}_{}.log", session_id, timestamp));
+    // Outputs from runners that did not redact them must not leak secrets here
+    let secrets = group_secrets_for_run(group_folder);
+    let redact = |text: &Option<String>| text.as_deref().map(|t| secrets.redact(t));
     let log_data = serde_json::json!({
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "group_folder": group_folder,
         "session_id": session_id,
         "status": output.status,
-        "result": output.result,
-        "error": output.error,
+        "result": redact(&output.result),
+        "error": redact(&output.error),
         "new_session_id": output.new_session_id,
     });
     let json_content =
@@ -535,6 +860,32 @@ mod tests {
         assert_eq!(output.new_session_id, Some("sess_123".to_string()));
     }
 
+    #[test]
+    fn test_parse_container_output_usage() {
+        let output = "--NUCLAW_OUTPUT_START--\n{\"status\": \"success\", \"result\": \"hi\", \"usage\": {\"input_tokens\": 12, \"output_tokens\": 3}}\n--NUCLAW_OUTPUT_END--";
+        let parsed = parse_container_output(output, true, 100).unwrap();
+        assert_eq!(
+            parsed.usage,
+            Some(crate::providers::Usage {
+                input_tokens: 12,
+                output_tokens: 3
+            })
+        );
+
+        // SDK result messages carry no status and may use other key names
+        let output = "--NUCLAW_OUTPUT_START--\n{\"type\": \"result\", \"is_error\": false, \"result\": \"done\", \"session_id\": \"s1\", \"usage\": {\"inputTokens\": 40, \"outputTokens\": 8, \"cache_read_input_tokens\": 5}}\n--NUCLAW_OUTPUT_END--";
+        let parsed = parse_container_output(output, true, 100).unwrap();
+        assert_eq!(parsed.status, "success");
+        assert_eq!(parsed.result.as_deref(), Some("done"));
+        assert_eq!(parsed.new_session_id.as_deref(), Some("s1"));
+        assert_eq!(parsed.usage.map(|u| u.total_tokens()), Some(48));
+
+        let output = "--NUCLAW_OUTPUT_START--\n{\"is_error\": true, \"result\": \"API error\"}\n--NUCLAW_OUTPUT_END--";
+        let parsed = parse_container_output(output, true, 100).unwrap();
+        assert_eq!(parsed.status, "error");
+        assert_eq!(parsed.error.as_deref(), Some("API error"));
+    }
+
     #[test]
     fn test_parse_container_output_error() {
         let output = "some error output";
@@ -555,6 +906,48 @@ mod tests {
         assert_eq!(parsed.result, Some("marked".to_string()));
     }
 
+    #[test]
+    fn test_parse_progress_line() {
+        let progress = parse_progress_line(r#"--NUCLAW_PROGRESS--{"tool": "Bash"}"#).unwrap();
+        assert_eq!(progress.tool.as_deref(), Some("Bash"));
+        assert_eq!(progress.text, None);
+
+        assert!(parse_progress_line("--NUCLAW_PROGRESS--not json").is_none());
+        assert!(parse_progress_line(r#"{"tool": "Bash"}"#).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_capture_output_forwards_progress() {
+        let script = r#"echo '--NUCLAW_PROGRESS--{"status":"thinking"}'
+echo '--NUCLAW_PROGRESS--{"text":"Hel"}'
+echo '--NUCLAW_OUTPUT_START--'
+echo '{"status":"success","result":"Hello"}'
+echo '--NUCLAW_OUTPUT_END--'"#;
+        let mut child = AsyncCommand::new("sh")
+            .args(["-c", script])
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let output = capture_output(stdout, Some(&tx)).await.unwrap();
+        let _ = child.wait().await;
+        drop(tx);
+
+        assert!(!output.contains(PROGRESS_MARKER));
+        let parsed = parse_container_output(&output, true, 0).unwrap();
+        assert_eq!(parsed.result.as_deref(), Some("Hello"));
+
+        let mut updates = Vec::new();
+        while let Some(update) = rx.recv().await {
+            updates.push(update);
+        }
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].status.as_deref(), Some("thinking"));
+        assert_eq!(updates[1].text.as_deref(), Some("Hel"));
+    }
+
     #[test]
     fn test_parse_container_output_empty() {
         let output = "";
@@ -585,6 +978,85 @@ mod tests {
         assert_eq!(parsed.result, Some("not valid json".to_string()));
     }
 
+    #[test]
+    fn test_container_limit_args() {
+        assert!(container_limit_args(&ContainerLimits::default()).is_empty());
+
+        let limits = ContainerLimits {
+            cpus: Some(1.5),
+            memory: Some("512m".to_string()),
+            pids_limit: Some(128),
+            disk: Some("5g".to_string()),
+            network: Some("none".to_string()),
+        };
+        assert_eq!(
+            container_limit_args(&limits),
+            vec![
+                "--cpus=1.5",
+                "--memory=512m",
+                "--memory-swap=512m",
+                "--pids-limit=128",
+                "--storage-opt",
+                "size=5g",
+                "--network=none",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_one_shot_container_name() {
+        let name = one_shot_container_name("team chat/ops");
+        assert!(name.starts_with("nuclaw-team-chat-ops-"));
+        assert_ne!(name, one_shot_container_name("team chat/ops"));
+    }
+
+    #[test]
+    fn test_container_exit_parse() {
+        assert_eq!(
+            ContainerExit::parse("true 137\n"),
+            Some(ContainerExit {
+                oom_killed: true,
+                exit_code: Some(137)
+            })
+        );
+        assert_eq!(ContainerExit::parse("false 0").map(|e| e.oom_killed), Some(false));
+        assert_eq!(ContainerExit::parse(""), None);
+    }
+
+    #[test]
+    fn test_apply_limit_status() {
+        let limits = ContainerLimits {
+            memory: Some("512m".to_string()),
+            pids_limit: Some(64),
+            ..Default::default()
+        };
+        let failed = parse_container_output("", false, 0).unwrap();
+
+        let oom = ContainerExit {
+            oom_killed: true,
+            exit_code: Some(137),
+        };
+        let output = apply_limit_status(failed.clone(), &oom, "", &limits);
+        assert_eq!(output.status, STATUS_OOM_KILLED);
+        assert!(output.error.unwrap().contains("512m"));
+
+        let output = apply_limit_status(
+            failed.clone(),
+            &ContainerExit::default(),
+            "bash: fork: Resource temporarily unavailable",
+            &limits,
+        );
+        assert_eq!(output.status, STATUS_LIMIT_EXCEEDED);
+
+        // Disk errors only count as a limit hit when a disk limit is set
+        let output = apply_limit_status(failed, &ContainerExit::default(), "No space left on device", &limits);
+        assert_eq!(output.status, "error");
+
+        let success = parse_container_output("done", true, 0).unwrap();
+        let output = apply_limit_status(success, &ContainerExit::default(), "", &limits);
+        assert_eq!(output.status, "success");
+    }
+
     #[test]
     fn test_get_container_command() {
         // This test just verifies the function doesn't panic
@@ -594,6 +1066,12 @@ mod tests {
         assert!(cmd == "docker" || cmd == "container");
     }
 
+    #[test]
+    fn test_engine_run_args() {
+        assert_eq!(engine_run_args(RuntimeKind::Podman), vec!["--userns=keep-id".to_string()]);
+        assert!(engine_run_args(RuntimeKind::Docker).is_empty());
+    }
+
     #[test]
     fn test_create_group_ipc_directory() {
         let result = create_group_ipc_directory("test_group_123");
@@ -638,6 +1116,8 @@ mod tests {
             is_main: true,
             is_scheduled_task: false,
             session_workspace_id: Some("test_workspace".to_string()),
+            history: Vec::new(),
+            run_id: None,
         };
 
         let result = write_ipc_files("test_ipc_group", &input);
@@ -660,6 +1140,11 @@ mod tests {
             result: Some("test result".to_string()),
             new_session_id: Some("sess_123".to_string()),
             error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         };
 
         let result = log_container_output("test_log_group", "test_session", &output);
@@ -680,6 +1165,11 @@ mod tests {
             result: None,
             new_session_id: None,
             error: Some("test error".to_string()),
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         };
 
         let result = log_container_output("test_log_error_group", "test_session", &output);
@@ -691,90 +1181,238 @@ mod tests {
     }
 }
 
+use async_trait::async_trait;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use tokio::sync::Mutex;
 
 const DEFAULT_POOL_MIN_SIZE: usize = 2;
 const DEFAULT_POOL_MAX_SIZE: usize = 5;
+/// Default idle time before a warm container is reaped: 10 minutes
+const DEFAULT_POOL_IDLE_TTL_SECS: u64 = 600;
+/// Default number of runs before a container is recycled
+const DEFAULT_POOL_MAX_RUNS: u32 = 50;
+/// Default interval between liveness probes and idle reaping
+const DEFAULT_POOL_PROBE_INTERVAL_SECS: u64 = 30;
 const CONTAINER_NAME_PREFIX: &str = "nuclaw-pool-";
 
+/// Lifecycle operations the pool needs from a container engine
+#[async_trait]
+pub trait PoolBackend: Send + Sync {
+    /// Start a long-lived container for a group
+    async fn start(&self, container_id: &str, group_folder: &str, is_main: bool) -> Result<()>;
+    /// Run the agent for `input` inside a running container
+    async fn exec(
+        &self,
+        container_id: &str,
+        input: &ContainerInput,
+        progress: Option<&ProgressSender>,
+    ) -> Result<ContainerOutput>;
+    /// Whether the container is still running
+    async fn is_alive(&self, container_id: &str) -> bool;
+    /// Stop and remove the container
+    async fn remove(&self, container_id: &str);
+}
+
+/// Pool backend driving `docker`/`podman` with `run -d` and `exec`
+pub struct DockerPoolBackend;
+
+#[async_trait]
+impl PoolBackend for DockerPoolBackend {
+    async fn start(&self, container_id: &str, group_folder: &str, is_main: bool) -> Result<()> {
+        start_pooled_container(container_id, group_folder, is_main).await
+    }
+
+    async fn exec(
+        &self,
+        container_id: &str,
+        input: &ContainerInput,
+        progress: Option<&ProgressSender>,
+    ) -> Result<ContainerOutput> {
+        run_container_in_pool(container_id, input, progress).await
+    }
+
+    async fn is_alive(&self, container_id: &str) -> bool {
+        AsyncCommand::new(get_container_command())
+            .args(["inspect", "--format", "{{.State.Running}}", container_id])
+            .output()
+            .await
+            .map(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == "true")
+            .unwrap_or(false)
+    }
+
+    async fn remove(&self, container_id: &str) {
+        let _ = AsyncCommand::new(get_container_command())
+            .args(["rm", "-f", container_id])
+            .output()
+            .await;
+    }
+}
+
 #[derive(Debug, Clone)]
 struct PooledContainerInner {
     container_id: String,
     group_folder: String,
+    is_main: bool,
     in_use: bool,
+    /// Still starting; not yet usable or probeable
+    starting: bool,
+    runs: u32,
+    last_used: Instant,
 }
 
-#[derive(Clone)]
-pub struct PooledContainer {
-    inner: Arc<Mutex<PooledContainerInner>>,
-    pool: Arc<ContainerPool>,
-}
+impl PooledContainerInner {
+    fn new(container_id: String, group_folder: &str, is_main: bool) -> Self {
+        Self {
+            container_id,
+            group_folder: group_folder.to_string(),
+            is_main,
+            in_use: false,
+            starting: true,
+            runs: 0,
+            last_used: Instant::now(),
+        }
+    }
 
-impl PooledContainer {
-    pub async fn run(self, mut input: ContainerInput) -> Result<ContainerOutput> {
-        let (container_id, group_folder) = {
-            let inner = self.inner.lock().await;
-            (inner.container_id.clone(), inner.group_folder.clone())
-        };
+    fn is_idle(&self) -> bool {
+        !self.in_use && !self.starting
+    }
 
-        let result = run_container_in_pool(&container_id, &group_folder, &mut input).await;
+    fn serves(&self, group_folder: &str, is_main: bool) -> bool {
+        self.group_folder == group_folder && self.is_main == is_main
+    }
+}
 
-        {
-            let mut inner = self.inner.lock().await;
-            inner.in_use = false;
-        }
+/// A warm container checked out of the pool for one run
+pub struct PooledContainer {
+    container_id: String,
+    pool: ContainerPool,
+    released: bool,
+}
 
+impl PooledContainer {
+    pub fn container_id(&self) -> &str {
+        &self.container_id
+    }
+
+    /// Run the agent in this container and hand it back to the pool
+    pub async fn run(
+        mut self,
+        input: ContainerInput,
+        progress: Option<&ProgressSender>,
+    ) -> Result<ContainerOutput> {
+        let result = self.pool.backend.exec(&self.container_id, &input, progress).await;
+        // A failed or timed-out run may leave the container in a bad state
+        let healthy = matches!(&result, Ok(output) if output.status == "success");
+        self.released = true;
+        self.pool.finish_run(&self.container_id, healthy).await;
         result
     }
 }
 
-impl Clone for ContainerPool {
-    fn clone(&self) -> Self {
-        Self {
-            containers: Arc::clone(&self.containers),
-            max_size: self.max_size,
-            min_size: self.min_size,
-            enabled: self.enabled,
-            // Clone the semaphore
-            semaphore: Arc::clone(&self.semaphore),
+impl Drop for PooledContainer {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        // Checked out but never run: return it to the pool
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let pool = self.pool.clone();
+            let container_id = self.container_id.clone();
+            handle.spawn(async move { pool.release(&container_id).await });
         }
     }
 }
 
+/// Pool counters, shared between clones of the pool
+#[derive(Debug, Default)]
+struct PoolCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    started: AtomicU64,
+    start_failures: AtomicU64,
+    recycled: AtomicU64,
+    reaped: AtomicU64,
+    unhealthy: AtomicU64,
+}
+
+/// Snapshot of the pool for monitoring
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PoolStats {
+    pub enabled: bool,
+    pub total: usize,
+    pub in_use: usize,
+    pub idle: usize,
+    pub starting: usize,
+    pub max_size: usize,
+    /// Containers per group folder
+    pub groups: HashMap<String, usize>,
+    /// Runs served by an already-warm container
+    pub hits: u64,
+    /// Runs that had to wait for a new container or fall back to a cold start
+    pub misses: u64,
+    pub started: u64,
+    pub start_failures: u64,
+    /// Containers removed after `max_runs` runs or a failed run
+    pub recycled: u64,
+    /// Containers removed after sitting idle past the TTL
+    pub reaped: u64,
+    /// Containers removed after failing a liveness probe
+    pub unhealthy: u64,
+}
+
+#[derive(Clone)]
 pub struct ContainerPool {
-    pub containers: Arc<Mutex<HashMap<String, PooledContainerInner>>>,
+    containers: Arc<Mutex<HashMap<String, PooledContainerInner>>>,
     pub max_size: usize,
     pub min_size: usize,
     pub enabled: bool,
-    /// Semaphore to limit concurrent container creation
-    semaphore: Arc<tokio::sync::Semaphore>,
+    /// Idle containers older than this are reaped (down to `min_size`)
+    pub idle_ttl: Duration,
+    /// Runs after which a container is recycled
+    pub max_runs: u32,
+    /// Interval between liveness probes
+    pub probe_interval: Duration,
+    backend: Arc<dyn PoolBackend>,
+    counters: Arc<PoolCounters>,
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
 }
 
 impl ContainerPool {
     pub fn new() -> Self {
+        Self::with_backend(Arc::new(DockerPoolBackend))
+    }
+
+    /// Pool configured from the environment, using `backend` to manage containers
+    pub fn with_backend(backend: Arc<dyn PoolBackend>) -> Self {
         let enabled = std::env::var("CONTAINER_POOL_ENABLED")
             .map(|v| v == "true")
             .unwrap_or(false);
 
-        let max_size = std::env::var("CONTAINER_POOL_MAX_SIZE")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(DEFAULT_POOL_MAX_SIZE);
-
-        let min_size = std::env::var("CONTAINER_POOL_MIN_SIZE")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(DEFAULT_POOL_MIN_SIZE);
-
         Self {
             containers: Arc::new(Mutex::new(HashMap::new())),
-            max_size,
-            min_size,
+            max_size: env_or("CONTAINER_POOL_MAX_SIZE", DEFAULT_POOL_MAX_SIZE),
+            min_size: env_or("CONTAINER_POOL_MIN_SIZE", DEFAULT_POOL_MIN_SIZE),
             enabled,
-            // Semaphore with max_size permits to limit concurrent container creation
-            semaphore: Arc::new(tokio::sync::Semaphore::new(max_size)),
+            idle_ttl: Duration::from_secs(env_or(
+                "CONTAINER_POOL_IDLE_TTL_SECS",
+                DEFAULT_POOL_IDLE_TTL_SECS,
+            )),
+            max_runs: env_or("CONTAINER_POOL_MAX_RUNS", DEFAULT_POOL_MAX_RUNS),
+            probe_interval: Duration::from_secs(env_or(
+                "CONTAINER_POOL_PROBE_INTERVAL_SECS",
+                DEFAULT_POOL_PROBE_INTERVAL_SECS,
+            )),
+            backend,
+            counters: Arc::new(PoolCounters::default()),
         }
     }
 
@@ -782,8 +1420,59 @@ impl ContainerPool {
         self.enabled
     }
 
-    pub async fn warmup(&self) -> Result<()> {
-        if !self.enabled {
+    fn new_container_id(group_folder: &str) -> String {
+        let name = one_shot_container_name(group_folder);
+        format!("{}{}", CONTAINER_NAME_PREFIX, &name["nuclaw-".len()..])
+    }
+
+    /// Start one container for a group and add it to the pool as idle
+    async fn start_container(&self, group_folder: &str, is_main: bool, in_use: bool) -> Option<String> {
+        let container_id = {
+            let mut containers = self.containers.lock().await;
+            if containers.len() >= self.max_size {
+                return None;
+            }
+            let container_id = Self::new_container_id(group_folder);
+            let mut inner = PooledContainerInner::new(container_id.clone(), group_folder, is_main);
+            inner.in_use = in_use;
+            // Reserve the slot before the slow start so concurrent callers respect max_size
+            containers.insert(container_id.clone(), inner);
+            container_id
+        };
+
+        match self.backend.start(&container_id, group_folder, is_main).await {
+            Ok(()) => {
+                self.counters.started.fetch_add(1, Ordering::Relaxed);
+                if let Some(inner) = self.containers.lock().await.get_mut(&container_id) {
+                    inner.starting = false;
+                    inner.last_used = Instant::now();
+                }
+                info!("Warm container {} started for group {}", container_id, group_folder);
+                Some(container_id)
+            }
+            Err(e) => {
+                self.counters.start_failures.fetch_add(1, Ordering::Relaxed);
+                self.containers.lock().await.remove(&container_id);
+                self.backend.remove(&container_id).await;
+                warn!("Failed to start pooled container for {}: {}", group_folder, e);
+                None
+            }
+        }
+    }
+
+    /// Groups to warm at startup (CONTAINER_POOL_WARM_GROUPS, comma-separated)
+    pub fn warm_groups() -> Vec<String> {
+        std::env::var("CONTAINER_POOL_WARM_GROUPS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|g| g.trim().to_string())
+            .filter(|g| !g.is_empty())
+            .collect()
+    }
+
+    /// Start up to `min_size` warm containers, spread over `groups`
+    pub async fn warmup(&self, groups: &[String]) -> Result<()> {
+        if !self.enabled || groups.is_empty() {
             return Ok(());
         }
 
@@ -792,106 +1481,106 @@ impl ContainerPool {
             self.min_size
         );
 
-        for i in 0..self.min_size {
-            let container_id = format!("{}{}", CONTAINER_NAME_PREFIX, i);
-            match start_pooled_container(&container_id, "default").await {
-                Ok(_) => {
-                    let mut containers = self.containers.lock().await;
-                    containers.insert(
-                        container_id.clone(),
-                        PooledContainerInner {
-                            container_id,
-                            group_folder: "default".to_string(),
-                            in_use: false,
-                        },
-                    );
-                    info!("Warm container {} started", i);
-                }
-                Err(e) => {
-                    warn!("Failed to warm container {}: {}", i, e);
-                }
-            }
+        for group_folder in groups.iter().cycle().take(self.min_size) {
+            self.start_container(group_folder, false, false).await;
         }
 
         Ok(())
     }
 
-    /// Acquire a container from the pool
-    /// Uses semaphore to prevent race conditions during container creation
+    /// Acquire a container for a non-main chat of a group
     pub async fn acquire(&self, group_folder: &str) -> Option<PooledContainer> {
+        self.acquire_for(group_folder, false).await
+    }
+
+    /// Acquire a container for a group, starting one if none is idle
+    ///
+    /// Returns `None` when the pool is disabled, full of busy containers or
+    /// the container fails to start; callers then fall back to a cold run.
+    pub async fn acquire_for(&self, group_folder: &str, is_main: bool) -> Option<PooledContainer> {
         if !self.enabled {
             return None;
         }
 
-        let mut containers = self.containers.lock().await;
-
-        // First, try to find an available container
-        for (_id, container) in containers.iter_mut() {
-            if !container.in_use && container.group_folder == group_folder {
-                container.in_use = true;
-                return Some(PooledContainer {
-                    inner: Arc::new(Mutex::new(container.clone())),
-                    pool: Arc::new(self.clone()),
-                });
+        let evict = {
+            let mut containers = self.containers.lock().await;
+            if let Some(inner) = containers
+                .values_mut()
+                .filter(|c| c.is_idle() && c.serves(group_folder, is_main))
+                .max_by_key(|c| c.last_used)
+            {
+                inner.in_use = true;
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                return Some(self.checkout(inner.container_id.clone()));
             }
-        }
 
-        // Need to create a new container - use semaphore to limit concurrency
-        // Acquire permit - will wait if no permits available
-        let _permit = match self.semaphore.acquire().await {
-            Ok(p) => p,
-            Err(_) => return None, // Pool was closed
+            // Full: make room by evicting the least recently used idle container
+            if containers.len() >= self.max_size {
+                let lru = containers
+                    .values()
+                    .filter(|c| c.is_idle())
+                    .min_by_key(|c| c.last_used)
+                    .map(|c| c.container_id.clone());
+                if let Some(id) = &lru {
+                    containers.remove(id);
+                }
+                lru
+            } else {
+                None
+            }
         };
 
-        // We have a permit, proceed with container creation
-        let mut containers = self.containers.lock().await;
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        if let Some(id) = evict {
+            self.counters.recycled.fetch_add(1, Ordering::Relaxed);
+            self.backend.remove(&id).await;
+        }
+        let container_id = self.start_container(group_folder, is_main, true).await?;
+        Some(self.checkout(container_id))
+    }
 
-        // Check if we can still create a new container (another thread might have created one)
-        if containers.len() < self.max_size {
-            let new_id = format!("{}{}", CONTAINER_NAME_PREFIX, containers.len());
-            let new_group_folder = group_folder.to_string();
-
-            // Release lock before async operation
-            drop(containers);
-
-            // Start the container
-            match start_pooled_container(&new_id, &new_group_folder).await {
-                Ok(_) => {
-                    let mut containers = self.containers.lock().await;
-
-                    // Double-check size after async operation
-                    if containers.len() < self.max_size {
-                        let inner = PooledContainerInner {
-                            container_id: new_id.clone(),
-                            group_folder: new_group_folder.clone(),
-                            in_use: true,
-                        };
-                        containers.insert(new_id.clone(), inner);
-
-                        return Some(PooledContainer {
-                            inner: Arc::new(Mutex::new(PooledContainerInner {
-                                container_id: new_id,
-                                group_folder: new_group_folder,
-                                in_use: true,
-                            })),
-                            pool: Arc::new(self.clone()),
-                        });
-                    }
-                }
-                Err(_) => {
-                    // Container creation failed
-                }
-            }
+    fn checkout(&self, container_id: String) -> PooledContainer {
+        PooledContainer {
+            container_id,
+            pool: self.clone(),
+            released: false,
         }
+    }
 
-        // Failed to create container - permit will be released when _permit is dropped
-        None
+    /// Record a finished run; recycle the container if it is worn out or failed
+    async fn finish_run(&self, container_id: &str, healthy: bool) {
+        let recycle = {
+            let mut containers = self.containers.lock().await;
+            let Some(inner) = containers.get_mut(container_id) else {
+                return;
+            };
+            inner.in_use = false;
+            inner.runs += 1;
+            inner.last_used = Instant::now();
+            let recycle = !healthy || inner.runs >= self.max_runs;
+            if recycle {
+                containers.remove(container_id)
+            } else {
+                None
+            }
+        };
+
+        if let Some(inner) = recycle {
+            info!(
+                "Recycling pooled container {} after {} run(s)",
+                inner.container_id, inner.runs
+            );
+            self.counters.recycled.fetch_add(1, Ordering::Relaxed);
+            self.backend.remove(container_id).await;
+            // Keep the group warm for its next message
+            let pool = self.clone();
+            tokio::spawn(async move {
+                pool.start_container(&inner.group_folder, inner.is_main, false).await;
+            });
+        }
     }
 
-    /// Release a container back to the pool
-    /// Note: This is typically called automatically when PooledContainer is dropped
-    /// Kept for manual release if needed
-    #[allow(dead_code)]
+    /// Release a container back to the pool without counting a run
     pub async fn release(&self, container_id: &str) {
         if !self.enabled {
             return;
@@ -902,159 +1591,261 @@ impl ContainerPool {
             container.in_use = false;
         }
     }
-}
 
-impl Default for ContainerPool {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+    /// Probe idle containers and reap those idle past the TTL
+    pub async fn maintain(&self) {
+        if !self.enabled {
+            return;
+        }
 
-async fn start_pooled_container(container_id: &str, group_folder: &str) -> Result<()> {
-    let cmd = get_container_command();
-    let group_dir = prepare_group_context(group_folder)?;
-    let image = std::env::var("CONTAINER_IMAGE")
-        .unwrap_or_else(|_| "anthropic/claude-code:latest".to_string());
+        let idle: Vec<(String, Instant)> = {
+            let containers = self.containers.lock().await;
+            containers
+                .values()
+                .filter(|c| c.is_idle())
+                .map(|c| (c.container_id.clone(), c.last_used))
+                .collect()
+        };
 
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new(cmd)
-            .args([
-                "run",
-                "--name",
-                container_id,
-                "--workspace",
-                &group_dir.to_string_lossy(),
-            ])
-            .output()
-            .map_err(|e| NuClawError::Container {
-                message: format!("Failed to start container: {}", e),
-            })?;
-
-        if !output.status.success() {
-            return Err(NuClawError::Container {
-                message: format!(
-                    "Container start failed: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ),
-            });
+        let mut dead = Vec::new();
+        for (id, _) in &idle {
+            if !self.backend.is_alive(id).await {
+                dead.push(id.clone());
+            }
+        }
+
+        let mut expired: Vec<(String, Instant)> = idle
+            .into_iter()
+            .filter(|(id, last_used)| !dead.contains(id) && last_used.elapsed() >= self.idle_ttl)
+            .collect();
+        expired.sort_by_key(|(_, last_used)| *last_used);
+
+        let removed: Vec<(String, bool)> = {
+            let mut containers = self.containers.lock().await;
+            let mut removed = Vec::new();
+            for id in dead {
+                if containers.get(&id).is_some_and(|c| c.is_idle()) {
+                    containers.remove(&id);
+                    removed.push((id, false));
+                }
+            }
+            for (id, _) in expired {
+                if containers.len() <= self.min_size {
+                    break;
+                }
+                if containers.get(&id).is_some_and(|c| c.is_idle()) {
+                    containers.remove(&id);
+                    removed.push((id, true));
+                }
+            }
+            removed
+        };
+
+        for (id, expired) in removed {
+            if expired {
+                info!("Reaping idle pooled container {}", id);
+                self.counters.reaped.fetch_add(1, Ordering::Relaxed);
+            } else {
+                warn!("Pooled container {} failed its liveness probe", id);
+                self.counters.unhealthy.fetch_add(1, Ordering::Relaxed);
+            }
+            self.backend.remove(&id).await;
         }
     }
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        let mut cmd = Command::new(cmd);
-        cmd.arg("run")
-            .arg("--name")
-            .arg(container_id)
-            .arg("-d")
-            .arg("-v")
-            .arg(format!("{}:/workspace/group", group_dir.display()))
-            .arg("-e")
-            .arg("CLAUDE_CODE_OAUTH_TOKEN");
+    /// Run `maintain` every `probe_interval` in the background
+    pub fn spawn_maintenance(&self) -> tokio::task::JoinHandle<()> {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(pool.probe_interval);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                pool.maintain().await;
+                tracing::debug!("Container pool: {:?}", pool.stats().await);
+            }
+        })
+    }
 
-        if anthropic_api_key().is_some() {
-            cmd.arg("-e").arg("ANTHROPIC_API_KEY");
+    /// Remove every pooled container (on shutdown)
+    pub async fn shutdown(&self) {
+        let ids: Vec<String> = self.containers.lock().await.drain().map(|(id, _)| id).collect();
+        for id in ids {
+            self.backend.remove(&id).await;
         }
-        if anthropic_base_url().is_some() {
-            cmd.arg("-e").arg("ANTHROPIC_BASE_URL");
+    }
+
+    pub async fn stats(&self) -> PoolStats {
+        let containers = self.containers.lock().await;
+        let mut groups = HashMap::new();
+        for c in containers.values() {
+            *groups.entry(c.group_folder.clone()).or_insert(0) += 1;
         }
-        if claude_model().is_some() {
-            cmd.arg("-e").arg("CLAUDE_MODEL");
+        PoolStats {
+            enabled: self.enabled,
+            total: containers.len(),
+            in_use: containers.values().filter(|c| c.in_use).count(),
+            idle: containers.values().filter(|c| c.is_idle()).count(),
+            starting: containers.values().filter(|c| c.starting).count(),
+            max_size: self.max_size,
+            groups,
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            started: self.counters.started.load(Ordering::Relaxed),
+            start_failures: self.counters.start_failures.load(Ordering::Relaxed),
+            recycled: self.counters.recycled.load(Ordering::Relaxed),
+            reaped: self.counters.reaped.load(Ordering::Relaxed),
+            unhealthy: self.counters.unhealthy.load(Ordering::Relaxed),
         }
+    }
+}
 
-        cmd.arg("--entrypoint")
-            .arg("sleep")
-            .arg(image)
-            .arg("infinity");
+impl Default for ContainerPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        let output = cmd.output().map_err(|e| NuClawError::Container {
-            message: format!("Failed to start container: {}", e),
-        })?;
+/// Process-wide container pool, configured from the environment
+pub fn global_pool() -> &'static ContainerPool {
+    static POOL: OnceLock<ContainerPool> = OnceLock::new();
+    POOL.get_or_init(ContainerPool::new)
+}
 
-        if !output.status.success() {
-            return Err(NuClawError::Container {
-                message: format!(
-                    "Container start failed: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ),
-            });
-        }
+async fn start_pooled_container(container_id: &str, group_folder: &str, is_main: bool) -> Result<()> {
+    let group_dir = prepare_group_context(group_folder)?;
+    let image = std::env::var("CONTAINER_IMAGE")
+        .unwrap_or_else(|_| "anthropic/claude-code:latest".to_string());
+    let limits = load_workflow_config().container_limits_for_group(group_folder);
+    let mut extra_mounts = group_mounts(group_folder, is_main)?;
+    extra_mounts.push(ipc_mount(group_folder)?);
+
+    let mut cmd = AsyncCommand::new(get_container_command());
+    cmd.arg("run")
+        .arg("--name")
+        .arg(container_id)
+        .arg("-d")
+        .args(container_limit_args(&limits))
+        .args(engine_run_args(runtime_kind()))
+        .arg("-v")
+        .arg(format!("{}:/workspace/group", group_dir.display()))
+        .args(mount_args(&extra_mounts))
+        .arg("-e")
+        .arg("CLAUDE_CODE_OAUTH_TOKEN");
+
+    if anthropic_api_key().is_some() {
+        cmd.arg("-e").arg("ANTHROPIC_API_KEY");
+    }
+    if anthropic_base_url().is_some() {
+        cmd.arg("-e").arg("ANTHROPIC_BASE_URL");
+    }
+    if claude_model().is_some() {
+        cmd.arg("-e").arg("CLAUDE_MODEL");
+    }
+
+    // Keep the container alive; each run is an `exec`
+    cmd.arg("--entrypoint")
+        .arg("sleep")
+        .arg(image)
+        .arg("infinity");
+
+    let output = cmd.output().await.map_err(|e| NuClawError::Container {
+        message: format!("Failed to start container: {}", e),
+    })?;
+
+    if !output.status.success() {
+        return Err(NuClawError::Container {
+            message: format!(
+                "Container start failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        });
     }
 
     Ok(())
 }
 
+/// Directory in the group folder holding per-run input files for pooled containers
+const POOL_INPUT_DIR: &str = ".nuclaw";
+
 async fn run_container_in_pool(
     container_id: &str,
-    group_folder: &str,
-    input: &mut ContainerInput,
+    input: &ContainerInput,
+    progress: Option<&ProgressSender>,
 ) -> Result<ContainerOutput> {
-    write_ipc_files(group_folder, input)?;
-
-    let input_path = data_dir().join("temp").join(format!(
-        "input_{}.json",
-        input
-            .session_id
-            .clone()
-            .unwrap_or_else(|| "default".to_string())
-    ));
+    let group_dir = prepare_group_context(&input.group_folder)?;
+    write_ipc_files(&input.group_folder, input)?;
 
+    // The group folder is the only host path the warm container can see
+    let input_dir = group_dir.join(POOL_INPUT_DIR);
+    fs::create_dir_all(&input_dir).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to create input directory: {}", e),
+    })?;
+    let input_name = format!("input_{}.json", uuid::Uuid::new_v4().simple());
+    let input_path = input_dir.join(&input_name);
     let input_json = serde_json::to_string(input).map_err(|e| NuClawError::Container {
         message: format!("Failed to serialize input: {}", e),
     })?;
-    std::fs::write(&input_path, &input_json).map_err(|e| NuClawError::FileSystem {
+    fs::write(&input_path, &input_json).map_err(|e| NuClawError::FileSystem {
         message: format!("Failed to write input file: {}", e),
     })?;
 
-    let cmd = get_container_command();
-
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new(cmd)
-            .args([
-                "exec",
-                "--workspace",
-                group_folder,
-                "--input",
-                &input_path.to_string_lossy(),
-            ])
-            .arg("--name")
-            .arg(assistant_name())
-            .output()
-            .map_err(|e| NuClawError::Container {
-                message: format!("Failed to execute container: {}", e),
-            })?;
-
-        let _ = std::fs::remove_file(&input_path);
-
-        parse_container_output(
-            &String::from_utf8_lossy(&output.stdout),
-            output.status.success(),
-            0,
-        )
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        let output = Command::new(cmd)
-            .arg("exec")
-            .arg("-i")
-            .arg(container_id)
-            .arg("/usr/local/bin/claude")
-            .arg("/workspace/input.json")
-            .output()
-            .map_err(|e| NuClawError::Container {
-                message: format!("Failed to execute container: {}", e),
-            })?;
+    let mut cmd = AsyncCommand::new(get_container_command());
+    cmd.arg("exec").arg("-i");
+    add_secret_env(&mut cmd, &group_secrets_for_run(&input.group_folder));
+    cmd.arg(container_id)
+        .arg("/usr/local/bin/claude")
+        .arg(format!("/workspace/group/{}/{}", POOL_INPUT_DIR, input_name))
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
 
-        let _ = std::fs::remove_file(&input_path);
+    let limits = load_workflow_config().container_limits_for_group(&input.group_folder);
+    let result =
+        run_exec_with_output(&mut cmd, container_id, &limits, container_timeout(), progress).await;
+    let _ = fs::remove_file(&input_path);
+    result
+}
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let combined = format!("{}\n{}", stdout, stderr);
-        parse_container_output(&combined, output.status.success(), 0)
-    }
+/// Run an `exec` into a pooled container with the usual timeout and output limits
+async fn run_exec_with_output(
+    cmd: &mut AsyncCommand,
+    container_id: &str,
+    limits: &ContainerLimits,
+    timeout_duration: Duration,
+    progress: Option<&ProgressSender>,
+) -> Result<ContainerOutput> {
+    let mut child = cmd.spawn().map_err(|e| NuClawError::Container {
+        message: format!("Failed to execute container: {}", e),
+    })?;
+    let stdout = child.stdout.take().ok_or_else(|| NuClawError::Container {
+        message: "Container stdout not captured".to_string(),
+    })?;
+    let stderr_task = child.stderr.take().map(|stderr| tokio::spawn(capture_stderr(stderr)));
+
+    let Ok(output) = timeout(timeout_duration, capture_output(stdout, progress)).await else {
+        let _ = child.kill().await;
+        // The agent may still be running inside; the caller recycles the container
+        let mut output = parse_container_output("", false, 0)?;
+        output.error = Some(format!(
+            "Container run timed out after {}s",
+            timeout_duration.as_secs()
+        ));
+        return Ok(output);
+    };
+    let exit_status = child.wait().await.map_err(|e| NuClawError::Container {
+        message: format!("Failed to wait for container: {}", e),
+    })?;
+    let stderr = match stderr_task {
+        Some(task) => task.await.unwrap_or_default(),
+        None => String::new(),
+    };
+
+    let output = parse_container_output(&output?, exit_status.success(), 0)?;
+    // An OOM kill inside the container stops it entirely
+    let exit = inspect_container_exit(container_id).unwrap_or_default();
+    Ok(apply_limit_status(output, &exit, &stderr, limits))
 }
 
 #[cfg(test)]
@@ -1116,6 +1907,137 @@ mod container_pool_tests {
         let pool = ContainerPool::new();
         pool.release("any_id").await;
     }
+
+    /// Backend that records calls instead of talking to docker
+    #[derive(Default)]
+    struct MockBackend {
+        started: std::sync::Mutex<Vec<String>>,
+        removed: std::sync::Mutex<Vec<String>>,
+        dead: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl PoolBackend for MockBackend {
+        async fn start(&self, container_id: &str, _group_folder: &str, _is_main: bool) -> Result<()> {
+            self.started.lock().unwrap().push(container_id.to_string());
+            Ok(())
+        }
+
+        async fn exec(
+            &self,
+            container_id: &str,
+            _input: &ContainerInput,
+            _progress: Option<&ProgressSender>,
+        ) -> Result<ContainerOutput> {
+            parse_container_output(container_id, true, 0)
+        }
+
+        async fn is_alive(&self, container_id: &str) -> bool {
+            !self.dead.lock().unwrap().iter().any(|id| id == container_id)
+        }
+
+        async fn remove(&self, container_id: &str) {
+            self.removed.lock().unwrap().push(container_id.to_string());
+        }
+    }
+
+    fn mock_pool(max_size: usize, max_runs: u32) -> (ContainerPool, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::default());
+        let mut pool = ContainerPool::with_backend(backend.clone());
+        pool.enabled = true;
+        pool.max_size = max_size;
+        pool.min_size = 0;
+        pool.max_runs = max_runs;
+        (pool, backend)
+    }
+
+    fn input(group_folder: &str) -> ContainerInput {
+        ContainerInput {
+            prompt: "hi".to_string(),
+            session_id: None,
+            group_folder: group_folder.to_string(),
+            chat_jid: "telegram:1".to_string(),
+            is_main: false,
+            is_scheduled_task: false,
+            session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_container_pool_reuses_warm_container() {
+        let (pool, backend) = mock_pool(3, 10);
+
+        let first = pool.acquire("team").await.unwrap();
+        let id = first.container_id().to_string();
+        assert!(id.starts_with(CONTAINER_NAME_PREFIX));
+        let output = first.run(input("team"), None).await.unwrap();
+        assert_eq!(output.result.as_deref(), Some(id.as_str()));
+
+        let second = pool.acquire("team").await.unwrap();
+        assert_eq!(second.container_id(), id);
+        drop(second);
+        tokio::task::yield_now().await;
+
+        let stats = pool.stats().await;
+        assert_eq!(stats.total, 1);
+        assert_eq!((stats.hits, stats.misses, stats.started), (1, 1, 1));
+        assert_eq!(backend.started.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_container_pool_recycles_after_max_runs() {
+        let (pool, backend) = mock_pool(3, 2);
+
+        let id = {
+            let container = pool.acquire("team").await.unwrap();
+            let id = container.container_id().to_string();
+            container.run(input("team"), None).await.unwrap();
+            id
+        };
+        pool.acquire("team").await.unwrap().run(input("team"), None).await.unwrap();
+
+        assert_eq!(backend.removed.lock().unwrap().as_slice(), [id.clone()]);
+        assert_eq!(pool.stats().await.recycled, 1);
+    }
+
+    #[tokio::test]
+    async fn test_container_pool_evicts_lru_when_full() {
+        let (pool, backend) = mock_pool(1, 10);
+
+        let team = pool.acquire("team").await.unwrap();
+        let team_id = team.container_id().to_string();
+        // Busy containers are never evicted
+        assert!(pool.acquire("ops").await.is_none());
+        team.run(input("team"), None).await.unwrap();
+
+        let ops = pool.acquire("ops").await.unwrap();
+        assert_ne!(ops.container_id(), team_id);
+        assert_eq!(backend.removed.lock().unwrap().as_slice(), [team_id]);
+        assert_eq!(pool.stats().await.groups.get("ops"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_container_pool_maintain_probes_and_reaps() {
+        let (mut pool, backend) = mock_pool(3, 10);
+        pool.idle_ttl = Duration::ZERO;
+
+        let a = pool.acquire("team").await.unwrap();
+        let b = pool.acquire("ops").await.unwrap();
+        let (a_id, b_id) = (a.container_id().to_string(), b.container_id().to_string());
+        a.run(input("team"), None).await.unwrap();
+        b.run(input("ops"), None).await.unwrap();
+
+        backend.dead.lock().unwrap().push(a_id.clone());
+        pool.maintain().await;
+
+        let stats = pool.stats().await;
+        assert_eq!(stats.total, 0);
+        assert_eq!((stats.unhealthy, stats.reaped), (1, 1));
+        let removed = backend.removed.lock().unwrap();
+        assert!(removed.contains(&a_id) && removed.contains(&b_id));
+    }
 }
 
 #[cfg(test)]
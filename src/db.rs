@@ -11,6 +11,8 @@ use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+#[cfg(test)]
+use std::path::Path;
 
 /// Database configuration
 #[derive(Debug, Clone)]
@@ -178,13 +180,25 @@ fn initialize_schema(conn: &Connection) -> Result<(), NuClawError> {
             last_result TEXT,
             status TEXT DEFAULT 'active',
             created_at TEXT NOT NULL,
-            context_mode TEXT DEFAULT 'isolated'
+            context_mode TEXT DEFAULT 'isolated',
+            timezone TEXT,
+            misfire_policy TEXT,
+            max_retries INTEGER,
+            retry_backoff_ms INTEGER,
+            failure_count INTEGER NOT NULL DEFAULT 0,
+            delivery TEXT
         )",
         [],
     )
     .map_err(|e| NuClawError::Database {
         message: format!("Failed to create scheduled_tasks table: {}", e),
     })?;
+    ensure_column(conn, "scheduled_tasks", "timezone", "TEXT")?;
+    ensure_column(conn, "scheduled_tasks", "misfire_policy", "TEXT")?;
+    ensure_column(conn, "scheduled_tasks", "max_retries", "INTEGER")?;
+    ensure_column(conn, "scheduled_tasks", "retry_backoff_ms", "INTEGER")?;
+    ensure_column(conn, "scheduled_tasks", "failure_count", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "scheduled_tasks", "delivery", "TEXT")?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS task_run_logs (
@@ -202,6 +216,40 @@ fn initialize_schema(conn: &Connection) -> Result<(), NuClawError> {
         message: format!("Failed to create task_run_logs table: {}", e),
     })?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            recorded_at TEXT NOT NULL,
+            group_folder TEXT NOT NULL,
+            chat_jid TEXT NOT NULL,
+            user TEXT NOT NULL,
+            provider TEXT,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cost_usd REAL NOT NULL DEFAULT 0
+        )",
+        [],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to create token_usage table: {}", e),
+    })?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_sessions (
+            group_folder TEXT NOT NULL,
+            chat_jid TEXT NOT NULL,
+            session_id TEXT,
+            started_at TEXT,
+            last_used_at TEXT NOT NULL,
+            PRIMARY KEY (group_folder, chat_jid)
+        )",
+        [],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to create agent_sessions table: {}", e),
+    })?;
+
     // Create indexes for better query performance
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_messages_chat_jid ON messages(chat_jid)",
@@ -243,6 +291,34 @@ fn initialize_schema(conn: &Connection) -> Result<(), NuClawError> {
         message: format!("Failed to create task_run_logs task_id index: {}", e),
     })?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_token_usage_group_time ON token_usage(group_folder, recorded_at)",
+        [],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to create token_usage index: {}", e),
+    })?;
+
+    Ok(())
+}
+
+/// Add a column to a table created by an older version, if it is missing
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), NuClawError> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?", table))
+        .and_then(|mut stmt| stmt.exists([column]))
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to inspect {} table: {}", table, e),
+        })?;
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to add {}.{}: {}", table, column, e),
+        })?;
+    }
     Ok(())
 }
 
@@ -364,6 +440,30 @@ impl Fts5Manager for Connection {
     }
 }
 
+/// Open a fresh database file `name` in the store directory, for tests
+#[cfg(test)]
+pub(crate) fn test_db(name: &str) -> (Database, PathBuf) {
+    let _ = std::fs::create_dir_all(store_dir());
+    let path = store_dir().join(name);
+    // Start clean, even after a run that left WAL files behind
+    remove_test_db(&path);
+    let db = Database::with_config(DatabaseConfig {
+        db_path: path.clone(),
+        pool_size: 2,
+        connection_timeout_ms: 5000,
+    })
+    .unwrap();
+    (db, path)
+}
+
+/// Remove a test database and its WAL files
+#[cfg(test)]
+pub(crate) fn remove_test_db(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(path.with_extension("db-wal"));
+    let _ = std::fs::remove_file(path.with_extension("db-shm"));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -550,6 +650,32 @@ mod tests {
         cleanup_test_db(&db_path);
     }
 
+    #[test]
+    fn test_schema_upgrades_old_tables() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE scheduled_tasks (
+                id TEXT PRIMARY KEY, group_folder TEXT NOT NULL, chat_jid TEXT NOT NULL,
+                prompt TEXT NOT NULL, schedule_type TEXT NOT NULL, schedule_value TEXT NOT NULL,
+                next_run TEXT, last_run TEXT, last_result TEXT, status TEXT DEFAULT 'active',
+                created_at TEXT NOT NULL, context_mode TEXT DEFAULT 'isolated'
+            )",
+            [],
+        )
+        .unwrap();
+
+        initialize_schema(&conn).unwrap();
+        // Running it again must not try to add the column twice
+        initialize_schema(&conn).unwrap();
+
+        let has_timezone: bool = conn
+            .prepare("SELECT 1 FROM pragma_table_info('scheduled_tasks') WHERE name = 'timezone'")
+            .unwrap()
+            .exists([])
+            .unwrap();
+        assert!(has_timezone);
+    }
+
     #[test]
     fn test_clone_database() {
         setup_test_dirs();
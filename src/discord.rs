@@ -58,6 +58,11 @@ mod tests {
             result: Some("discord mock response".to_string()),
             new_session_id: None,
             error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         };
 
         let mock_runtime = Arc::new(MockRuntime::new(mock_output));
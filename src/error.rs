@@ -13,6 +13,12 @@ pub enum NuClawError {
     #[error("API error: {message}")]
     Api { message: String },
 
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String },
+
+    #[error("Provider overloaded: {message}")]
+    Overloaded { message: String },
+
     #[error("WhatsApp error: {message}")]
     WhatsApp { message: String },
 
@@ -52,6 +58,13 @@ pub enum NuClawError {
 
 pub type Result<T> = std::result::Result<T, NuClawError>;
 
+impl NuClawError {
+    /// Whether the error is transient and the request may succeed later
+    pub fn is_transient(&self) -> bool {
+        matches!(self, NuClawError::RateLimited { .. } | NuClawError::Overloaded { .. })
+    }
+}
+
 impl From<rusqlite::Error> for NuClawError {
     fn from(e: rusqlite::Error) -> Self {
         NuClawError::Database {
@@ -123,6 +136,12 @@ mod tests {
         let _ = NuClawError::Api {
             message: "test".to_string(),
         };
+        let _ = NuClawError::RateLimited {
+            message: "test".to_string(),
+        };
+        let _ = NuClawError::Overloaded {
+            message: "test".to_string(),
+        };
         let _ = NuClawError::WhatsApp {
             message: "test".to_string(),
         };
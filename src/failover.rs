@@ -0,0 +1,469 @@
+//! Provider failover for NuClaw
+//!
+//! Tries an ordered chain of configured providers/models and keeps a
+//! per-provider circuit breaker so an unhealthy upstream is skipped until
+//! its cooldown expires.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::error::{NuClawError, Result};
+use crate::providers::{create_provider, ChatMessage, ChatOptions, Provider, ProviderRegistry, Usage};
+use crate::workflow::FailoverTarget;
+
+/// Default consecutive failures before a breaker opens
+pub const DEFAULT_BREAKER_THRESHOLD: u32 = 3;
+/// Default time a breaker stays open before allowing a trial request
+pub const DEFAULT_BREAKER_COOLDOWN_SECS: u64 = 60;
+/// Default timeout for a single provider attempt
+pub const DEFAULT_ATTEMPT_TIMEOUT_SECS: u64 = 60;
+
+/// Get the breaker failure threshold
+pub fn breaker_threshold() -> u32 {
+    std::env::var("PROVIDER_BREAKER_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_BREAKER_THRESHOLD)
+}
+
+/// Get the breaker cooldown
+pub fn breaker_cooldown() -> Duration {
+    let secs = std::env::var("PROVIDER_BREAKER_COOLDOWN_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_BREAKER_COOLDOWN_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Get the per-attempt provider timeout
+pub fn attempt_timeout() -> Duration {
+    let secs = std::env::var("PROVIDER_ATTEMPT_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_ATTEMPT_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are rejected until the cooldown expires
+    Open,
+    /// Cooldown expired; one trial request may go through
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Consecutive-failure circuit breaker for one provider
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+    /// A half-open trial is waiting for its outcome
+    trial_in_flight: AtomicBool,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(BreakerInner::default()),
+            trial_in_flight: AtomicBool::new(false),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            Some(opened) if opened.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+
+    /// Whether a request may be sent to this provider
+    ///
+    /// While half-open only one caller gets through; the trial lasts until
+    /// its success or failure is recorded.
+    pub fn allow_request(&self) -> bool {
+        match self.state() {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => self
+                .trial_in_flight
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok(),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        self.trial_in_flight.store(false, Ordering::Release);
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        // A failed trial in half-open state re-opens immediately
+        if inner.consecutive_failures >= self.failure_threshold || inner.opened_at.is_some() {
+            inner.opened_at = Some(Instant::now());
+        }
+        self.trial_in_flight.store(false, Ordering::Release);
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.inner.lock().unwrap().consecutive_failures
+    }
+}
+
+/// Circuit breakers keyed by provider name
+#[derive(Debug)]
+pub struct BreakerRegistry {
+    failure_threshold: u32,
+    cooldown: Duration,
+    breakers: Mutex<HashMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl BreakerRegistry {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get or create the breaker for a provider
+    pub fn get(&self, provider: &str) -> Arc<CircuitBreaker> {
+        let mut breakers = self.breakers.lock().unwrap();
+        breakers
+            .entry(provider.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(self.failure_threshold, self.cooldown)))
+            .clone()
+    }
+}
+
+/// Process-wide breaker registry, shared by all runners
+pub fn global_breakers() -> Arc<BreakerRegistry> {
+    static BREAKERS: OnceLock<Arc<BreakerRegistry>> = OnceLock::new();
+    BREAKERS
+        .get_or_init(|| Arc::new(BreakerRegistry::new(breaker_threshold(), breaker_cooldown())))
+        .clone()
+}
+
+/// Response from the chain along with the provider that produced it
+#[derive(Debug, Clone)]
+pub struct FailoverResponse {
+    pub text: String,
+    pub provider: String,
+    pub model: String,
+    /// Token usage reported by the provider, if any
+    pub usage: Option<Usage>,
+}
+
+/// Ordered provider chain with circuit breaking
+pub struct FailoverChain {
+    targets: Vec<FailoverTarget>,
+    registry: Arc<ProviderRegistry>,
+    breakers: Arc<BreakerRegistry>,
+    attempt_timeout: Duration,
+}
+
+impl FailoverChain {
+    pub fn new(targets: Vec<FailoverTarget>, registry: Arc<ProviderRegistry>) -> Self {
+        Self {
+            targets,
+            registry,
+            breakers: global_breakers(),
+            attempt_timeout: attempt_timeout(),
+        }
+    }
+
+    pub fn with_breakers(mut self, breakers: Arc<BreakerRegistry>) -> Self {
+        self.breakers = breakers;
+        self
+    }
+
+    pub fn with_attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = timeout;
+        self
+    }
+
+    pub fn targets(&self) -> &[FailoverTarget] {
+        &self.targets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Context window and output limit that suit every configured target
+    ///
+    /// The same turns may be sent to any target, so history has to fit the
+    /// smallest window. `None` when no target is configured.
+    pub fn context_limits(&self) -> Option<(usize, usize)> {
+        self.targets
+            .iter()
+            .filter_map(|target| self.target_provider(target).ok())
+            .map(|(provider, _)| (provider.context_window(), provider.max_output_tokens()))
+            .reduce(|(window, output), (other_window, other_output)| {
+                (window.min(other_window), output.max(other_output))
+            })
+    }
+
+    /// Provider for a target, set to the target's model, and that model
+    ///
+    /// Fails with the reason the target cannot be used.
+    fn target_provider(&self, target: &FailoverTarget) -> std::result::Result<(Box<dyn Provider>, String), String> {
+        let mut config = self
+            .registry
+            .get_config(&target.provider)
+            .or_else(|| self.registry.load_config(&target.provider))
+            .ok_or_else(|| format!("{}: unknown provider", target.provider))?;
+        if target.model.is_some() {
+            config.model = target.model.clone();
+        }
+        let provider = create_provider(&target.provider, &config)
+            .ok_or_else(|| format!("{}: not configured", target.provider))?;
+        Ok((provider, config.model.unwrap_or_default()))
+    }
+
+    /// Send the prompt to each target in order until one answers
+    pub async fn chat_with_system(
+        &self,
+        system: Option<&str>,
+        prompt: &str,
+        temperature: f64,
+    ) -> Result<FailoverResponse> {
+        let mut messages = Vec::with_capacity(2);
+        if let Some(system) = system {
+            messages.push(ChatMessage::system(system));
+        }
+        messages.push(ChatMessage::user(prompt));
+        self.chat_messages(&messages, &ChatOptions::default().with_temperature(temperature))
+            .await
+    }
+
+    /// Send a message list to each target in order until one answers
+    ///
+    /// The target's model overrides `options.model`.
+    pub async fn chat_messages(&self, messages: &[ChatMessage], options: &ChatOptions) -> Result<FailoverResponse> {
+        let mut failures = Vec::new();
+
+        for target in &self.targets {
+            let (provider, model) = match self.target_provider(target) {
+                Ok(resolved) => resolved,
+                Err(reason) => {
+                    failures.push(reason);
+                    continue;
+                }
+            };
+
+            // Only a request that is sent may claim the half-open trial
+            let breaker = self.breakers.get(&target.provider);
+            if !breaker.allow_request() {
+                tracing::debug!("Skipping provider {}: circuit open", target.provider);
+                failures.push(format!("{}: circuit open", target.provider));
+                continue;
+            }
+            let options = ChatOptions {
+                model: Some(model.clone()),
+                ..options.clone()
+            };
+
+            let attempt = tokio::time::timeout(
+                self.attempt_timeout,
+                provider.chat_messages(messages, &options),
+            )
+            .await;
+
+            match attempt {
+                Ok(Ok(response)) => {
+                    breaker.record_success();
+                    return Ok(FailoverResponse {
+                        text: response.text.unwrap_or_default(),
+                        provider: target.provider.clone(),
+                        model,
+                        usage: response.usage,
+                    });
+                }
+                Ok(Err(e)) => {
+                    breaker.record_failure();
+                    tracing::warn!("Provider {} failed: {}", target.provider, e);
+                    failures.push(format!("{}: {}", target.provider, e));
+                }
+                Err(_) => {
+                    breaker.record_failure();
+                    tracing::warn!("Provider {} timed out", target.provider);
+                    failures.push(format!("{}: timed out", target.provider));
+                }
+            }
+        }
+
+        Err(NuClawError::Api {
+            message: format!("All providers failed: {}", failures.join("; ")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ProviderConfig;
+
+    fn target(provider: &str) -> FailoverTarget {
+        FailoverTarget {
+            provider: provider.to_string(),
+            model: None,
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_after_threshold() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.record_failure();
+        assert!(breaker.allow_request());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow_request());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn test_breaker_half_open_after_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(0));
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.allow_request());
+        // One trial at a time until its outcome is known
+        assert!(!breaker.allow_request());
+        breaker.record_failure();
+        assert!(breaker.allow_request());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow_request());
+        assert!(breaker.allow_request());
+    }
+
+    #[test]
+    fn test_breaker_registry_reuses_breakers() {
+        let registry = BreakerRegistry::new(1, Duration::from_secs(60));
+        registry.get("openai").record_failure();
+        assert_eq!(registry.get("openai").state(), CircuitState::Open);
+        assert_eq!(registry.get("anthropic").state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_chain_all_fail_trips_breaker() {
+        let registry = ProviderRegistry::new();
+        registry.set_config(ProviderConfig {
+            name: "custom".to_string(),
+            api_key: Some("key".to_string()),
+            // Nothing listens on the discard port, so the request fails fast
+            base_url: Some("http://127.0.0.1:9".to_string()),
+            model: Some("local".to_string()),
+        });
+        registry.set_config(ProviderConfig {
+            name: "openai".to_string(),
+            api_key: None,
+            base_url: None,
+            model: None,
+        });
+
+        let breakers = Arc::new(BreakerRegistry::new(1, Duration::from_secs(60)));
+        let chain = FailoverChain::new(vec![target("openai"), target("custom")], Arc::new(registry))
+            .with_breakers(breakers.clone())
+            .with_attempt_timeout(Duration::from_secs(5));
+
+        let err = chain.chat_with_system(None, "hi", 0.0).await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("openai: not configured"));
+        assert!(message.contains("custom:"));
+        assert_eq!(breakers.get("custom").state(), CircuitState::Open);
+        // Unconfigured providers do not count against the breaker
+        assert_eq!(breakers.get("openai").state(), CircuitState::Closed);
+
+        let err = chain.chat_with_system(None, "hi", 0.0).await.unwrap_err();
+        assert!(err.to_string().contains("custom: circuit open"));
+    }
+
+    #[test]
+    fn test_context_limits_fit_every_target() {
+        use crate::providers::{
+            ANTHROPIC_VISION_CONTEXT_WINDOW, ANTHROPIC_VISION_MAX_OUTPUT_TOKENS,
+            OPENAI_CONTEXT_WINDOW, OPENAI_MAX_OUTPUT_TOKENS,
+        };
+
+        let registry = ProviderRegistry::new();
+        for name in ["openai", "anthropic"] {
+            registry.set_config(ProviderConfig {
+                name: name.to_string(),
+                api_key: Some("key".to_string()),
+                base_url: None,
+                model: None,
+            });
+        }
+        let registry = Arc::new(registry);
+
+        let openai = FailoverChain::new(vec![target("openai")], registry.clone());
+        assert_eq!(
+            openai.context_limits(),
+            Some((OPENAI_CONTEXT_WINDOW, OPENAI_MAX_OUTPUT_TOKENS))
+        );
+
+        let anthropic = FailoverChain::new(vec![target("anthropic")], registry.clone());
+        assert_eq!(
+            anthropic.context_limits(),
+            Some((ANTHROPIC_VISION_CONTEXT_WINDOW, ANTHROPIC_VISION_MAX_OUTPUT_TOKENS))
+        );
+
+        let both = FailoverChain::new(vec![target("anthropic"), target("openai")], registry.clone());
+        assert_eq!(
+            both.context_limits(),
+            Some((OPENAI_CONTEXT_WINDOW, OPENAI_MAX_OUTPUT_TOKENS))
+        );
+
+        // A target's model sets its limits
+        let legacy = FailoverChain::new(
+            vec![
+                target("anthropic"),
+                FailoverTarget {
+                    provider: "openai".to_string(),
+                    model: Some("gpt-3.5-turbo".to_string()),
+                },
+            ],
+            registry.clone(),
+        );
+        assert_eq!(legacy.context_limits(), Some((16_385, ANTHROPIC_VISION_MAX_OUTPUT_TOKENS)));
+
+        let empty = FailoverChain::new(Vec::new(), registry);
+        assert_eq!(empty.context_limits(), None);
+    }
+
+    #[tokio::test]
+    async fn test_empty_chain_fails() {
+        let chain = FailoverChain::new(Vec::new(), Arc::new(ProviderRegistry::new()));
+        assert!(chain.is_empty());
+        assert!(chain.chat_with_system(None, "hi", 0.0).await.is_err());
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Provides Feishu/Lark Bot connectivity via Bot API with webhook support.
 
+use crate::artifacts::{Artifact, ArtifactKind};
 use crate::config::{assistant_name, data_dir};
 use crate::db::Database;
 use crate::error::{NuClawError, Result};
@@ -28,6 +29,9 @@ use tracing::{debug, error, info, warn};
 /// Default Feishu API base URL
 const FEISHU_API_BASE: &str = "https://open.feishu.cn/open-apis";
 
+/// Largest image Feishu accepts as an image message; bigger ones go as files
+const FEISHU_IMAGE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
 /// Default Feishu poll interval: 2 seconds
 const DEFAULT_FEISHU_POLL_INTERVAL_MS: u64 = 2000;
 
@@ -381,6 +385,7 @@ impl FeishuClient {
         let app = Router::new()
             .route(&format!("/{}", webhook_path), post(handle_feishu_webhook))
             .route("/health", get(health_check))
+            .route("/pool", get(pool_stats))
             .with_state(client);
 
         info!("Starting Feishu webhook server on {}", addr);
@@ -533,16 +538,45 @@ impl FeishuClient {
             }
         };
 
+        if crate::session::is_reset_command(&content) {
+            crate::session::reset_session(&self.db, &group_folder, &msg.chat_jid, &msg.timestamp)?;
+            let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+            self.ensure_valid_token().await?;
+            self.send_message(&chat_id, crate::session::SESSION_RESET_REPLY).await?;
+            return Ok(Some(crate::session::SESSION_RESET_REPLY.to_string()));
+        }
+
+        let scope = crate::task_scheduler::TaskScope::new(&group_folder, !is_group);
+        if let Some(reply) =
+            crate::task_commands::handle_task_command(&self.db, &scope, &msg.chat_jid, &content)
+        {
+            let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+            self.ensure_valid_token().await?;
+            self.send_message(&chat_id, &reply).await?;
+            return Ok(Some(reply));
+        }
+
+        let session = crate::session::active_session(&self.db, &group_folder, &msg.chat_jid);
         let input = crate::types::ContainerInput {
             prompt: content.clone(),
-            session_id: Some(format!("feishu_{}", msg.id)),
-            group_folder,
+            session_id: session.as_ref().and_then(|s| s.session_id.clone()),
+            group_folder: group_folder.clone(),
             chat_jid: msg.chat_jid.clone(),
             is_main: !is_group,
             is_scheduled_task: false,
             session_workspace_id: None,
+            history: self.conversation_history(msg, session.as_ref()),
+            run_id: None,
         };
 
+        let budget = crate::budget::check_budget_for(&self.db, &group_folder, &msg.sender);
+        if let crate::budget::BudgetStatus::Exhausted(notice) = budget {
+            let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+            self.ensure_valid_token().await?;
+            self.send_message(&chat_id, &notice).await?;
+            return Ok(Some(notice));
+        }
+
         let runner = crate::agent_runner::create_runner()?;
         let result = tokio::time::timeout(
             crate::container_runner::container_timeout(),
@@ -555,18 +589,29 @@ impl FeishuClient {
 
         match result {
             Ok(Ok(output)) => {
+                crate::usage::record_output_usage(&self.db, &group_folder, &msg.chat_jid, &msg.sender, &output);
+                crate::session::record_session(&self.db, &group_folder, &msg.chat_jid, &msg.timestamp, &output);
                 if let Some(response) = output.result {
                     if response.trim().is_empty() {
                         tracing::warn!("Agent returned empty response, skipping");
+                        self.send_artifacts(&chat_id, &output.artifacts).await;
                         return Ok(None);
                     }
                     self.ensure_valid_token().await?;
                     self.send_message(&chat_id, &response).await?;
+                    self.send_artifacts(&chat_id, &output.artifacts).await;
+                    self.store_reply_background(msg, &response);
+                    if let crate::budget::BudgetStatus::Warning(warning) = budget {
+                        self.send_message(&chat_id, &warning.message).await?;
+                        warning.mark_sent();
+                    }
                     return Ok(Some(response));
                 }
                 error!("Agent returned no result: status={}", output.status);
                 self.ensure_valid_token().await?;
-                self.send_message(&chat_id, "Sorry, I couldn't process your request.").await?;
+                let reply = output.limit_error().unwrap_or("Sorry, I couldn't process your request.");
+                self.send_message(&chat_id, reply).await?;
+                self.send_artifacts(&chat_id, &output.artifacts).await;
             }
             Ok(Err(e)) => {
                 error!("Agent error: {}", e);
@@ -583,6 +628,35 @@ impl FeishuClient {
         Ok(None)
     }
 
+    /// Load previous turns of this chat, excluding the message being answered
+    fn conversation_history(
+        &self,
+        msg: &NewMessage,
+        session: Option<&crate::session::AgentSession>,
+    ) -> Vec<crate::providers::ChatMessage> {
+        let mut query = crate::history::HistoryQuery::new(&msg.chat_jid).excluding(&msg.id);
+        if let Some(started_at) = session.and_then(|s| s.started_at.as_deref()) {
+            query = query.since(started_at);
+        }
+        crate::history::load_history(&self.db, &query).unwrap_or_else(|e| {
+            warn!("Failed to load history for {}: {}", msg.chat_jid, e);
+            Vec::new()
+        })
+    }
+
+    /// Persist a reply in the background so it shows up in later history
+    fn store_reply_background(&self, msg: &NewMessage, response: &str) {
+        let db = self.db.clone();
+        let msg = msg.clone();
+        let response = response.to_string();
+        let sender_name = self.assistant_name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::history::store_reply(&db, &msg, &sender_name, &response) {
+                error!("Failed to store reply: {}", e);
+            }
+        });
+    }
+
     /// Send a message to a Feishu chat
     pub async fn send_message(&mut self, receive_id: &str, text: &str) -> Result<()> {
         if text.trim().is_empty() {
@@ -596,6 +670,11 @@ impl FeishuClient {
             text.len()
         );
 
+        self.send_content(receive_id, "text", serde_json::json!({ "text": text })).await
+    }
+
+    /// Send a message of any type to a Feishu chat
+    async fn send_content(&mut self, receive_id: &str, msg_type: &str, content: serde_json::Value) -> Result<()> {
         // Ensure token is valid before sending
         self.ensure_valid_token().await?;
 
@@ -604,7 +683,7 @@ impl FeishuClient {
         })?;
 
         let url = format!("{}/im/v1/messages", FEISHU_API_BASE);
-        
+
         // Determine receive_id_type based on the format of receive_id
         let receive_id_type = if receive_id.starts_with("oc_") || receive_id.starts_with("chat_") {
             "chat_id"
@@ -614,8 +693,8 @@ impl FeishuClient {
 
         let payload = FeishuSendMessageRequest {
             receive_id: receive_id.to_string(),
-            msg_type: "text".to_string(),
-            content: serde_json::to_string(&serde_json::json!({ "text": text }))
+            msg_type: msg_type.to_string(),
+            content: serde_json::to_string(&content)
                 .map_err(|e| NuClawError::Feishu {
                     message: format!("Failed to serialize message: {}", e),
                 })?,
@@ -649,6 +728,65 @@ impl FeishuClient {
         Ok(())
     }
 
+    /// Upload the files a run produced, logging failures
+    async fn send_artifacts(&mut self, receive_id: &str, artifacts: &[Artifact]) {
+        for artifact in artifacts {
+            if let Err(e) = self.send_file(receive_id, artifact).await {
+                error!("Failed to upload {}: {}", artifact.name, e);
+            }
+        }
+    }
+
+    /// Upload an artifact and send it as an image or file message
+    async fn send_file(&mut self, receive_id: &str, artifact: &Artifact) -> Result<()> {
+        self.ensure_valid_token().await?;
+        let token = self.tenant_access_token.clone().ok_or_else(|| NuClawError::Feishu {
+            message: "Not connected to Feishu".to_string(),
+        })?;
+
+        let bytes = tokio::fs::read(&artifact.path).await.map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to read {}: {}", artifact.path.display(), e),
+        })?;
+        let part = reqwest::multipart::Part::bytes(bytes).file_name(artifact.name.clone());
+        let (endpoint, form, key, msg_type) = match artifact.kind {
+            ArtifactKind::Image if artifact.size <= FEISHU_IMAGE_MAX_BYTES => (
+                "images",
+                reqwest::multipart::Form::new().text("image_type", "message").part("image", part),
+                "image_key",
+                "image",
+            ),
+            _ => (
+                "files",
+                reqwest::multipart::Form::new()
+                    .text("file_type", "stream")
+                    .text("file_name", artifact.name.clone())
+                    .part("file", part),
+                "file_key",
+                "file",
+            ),
+        };
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/im/v1/{}", FEISHU_API_BASE, endpoint))
+            .header("Authorization", format!("Bearer {}", token))
+            .multipart(form)
+            .timeout(Duration::from_secs(120))
+            .send()
+            .await
+            .map_err(|e| NuClawError::Feishu {
+                message: format!("Failed to upload {}: {}", artifact.name, e),
+            })?;
+        let body: serde_json::Value = response.json().await.map_err(|e| NuClawError::Feishu {
+            message: format!("Failed to parse upload response: {}", e),
+        })?;
+        let file_key = body["data"][key].as_str().ok_or_else(|| NuClawError::Feishu {
+            message: format!("Upload of {} failed: {}", artifact.name, body),
+        })?;
+
+        self.send_content(receive_id, msg_type, serde_json::json!({ key: file_key }))
+            .await
+    }
+
     async fn check_dm_policy(&self, _user_id: &str) -> Result<bool> {
         match self.dm_policy {
             FeishuDMPolicy::Disabled => Ok(false),
@@ -803,6 +941,11 @@ async fn health_check() -> (StatusCode, &'static str) {
     (StatusCode::OK, "OK")
 }
 
+/// Container pool statistics for monitoring
+async fn pool_stats() -> axum::Json<crate::container_runner::PoolStats> {
+    axum::Json(crate::container_runner::global_pool().stats().await)
+}
+
 /// Determines whether Feishu should auto-start based on environment configuration.
 pub fn should_auto_start_feishu() -> bool {
     std::env::var("FEISHU_APP_ID").is_ok() && std::env::var("FEISHU_APP_SECRET").is_ok()
@@ -0,0 +1,316 @@
+//! Conversation history module for NuClaw
+//!
+//! Loads previous turns of a chat from the `messages` table and shapes them
+//! into alternating user/assistant turns that fit a provider context window.
+
+use crate::db::Database;
+use crate::error::{NuClawError, Result};
+use crate::providers::ChatMessage;
+use crate::types::NewMessage;
+
+/// Default number of stored messages loaded as history
+pub const DEFAULT_HISTORY_MAX_TURNS: usize = 20;
+
+/// Rough characters-per-token ratio used for budget estimation
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Get the maximum number of history messages to load per chat
+pub fn history_max_turns() -> usize {
+    std::env::var("CONVERSATION_HISTORY_TURNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_MAX_TURNS)
+}
+
+/// Query describing which stored messages make up a conversation
+#[derive(Debug, Clone)]
+pub struct HistoryQuery {
+    pub chat_jid: String,
+    /// Only include messages at or after this timestamp (session start)
+    pub since: Option<String>,
+    /// Message id to leave out, usually the message being answered
+    pub exclude_id: Option<String>,
+    pub max_turns: usize,
+}
+
+impl HistoryQuery {
+    pub fn new(chat_jid: impl Into<String>) -> Self {
+        Self {
+            chat_jid: chat_jid.into(),
+            since: None,
+            exclude_id: None,
+            max_turns: history_max_turns(),
+        }
+    }
+
+    pub fn since(mut self, timestamp: impl Into<String>) -> Self {
+        self.since = Some(timestamp.into());
+        self
+    }
+
+    pub fn excluding(mut self, message_id: impl Into<String>) -> Self {
+        self.exclude_id = Some(message_id.into());
+        self
+    }
+
+    pub fn limit(mut self, max_turns: usize) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+}
+
+/// Load the most recent turns of a chat, oldest first
+pub fn load_history(db: &Database, query: &HistoryQuery) -> Result<Vec<ChatMessage>> {
+    if query.max_turns == 0 {
+        return Ok(Vec::new());
+    }
+
+    let conn = db.get_connection()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT content, is_from_me FROM messages
+             WHERE chat_jid = ?1
+               AND (?2 IS NULL OR timestamp >= ?2)
+               AND (?3 IS NULL OR id != ?3)
+             ORDER BY timestamp DESC, rowid DESC
+             LIMIT ?4",
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to prepare history query: {}", e),
+        })?;
+
+    let rows = stmt
+        .query_map(
+            rusqlite::params![
+                query.chat_jid,
+                query.since,
+                query.exclude_id,
+                query.max_turns as i64
+            ],
+            |row| {
+                let content: String = row.get(0)?;
+                let is_from_me: i64 = row.get(1)?;
+                Ok(if is_from_me != 0 {
+                    ChatMessage::assistant(content)
+                } else {
+                    ChatMessage::user(content)
+                })
+            },
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to load history: {}", e),
+        })?;
+
+    let mut turns = rows
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to read history row: {}", e),
+        })?;
+    turns.reverse();
+
+    Ok(normalize_turns(turns))
+}
+
+/// Store an assistant reply so it is available as history for later turns
+///
+/// The reply reuses the timestamp of the message it answers so ordering stays
+/// consistent with the channel's timestamp format; insertion order breaks ties.
+pub fn store_reply(db: &Database, reply_to: &NewMessage, sender_name: &str, content: &str) -> Result<()> {
+    db.store_message(&NewMessage {
+        id: format!("self_{}", reply_to.id),
+        chat_jid: reply_to.chat_jid.clone(),
+        sender: "self".to_string(),
+        sender_name: sender_name.to_string(),
+        content: content.to_string(),
+        timestamp: reply_to.timestamp.clone(),
+    })
+}
+
+/// Merge consecutive same-role turns and drop leading assistant turns
+///
+/// Chat APIs expect conversations to start with a user turn and alternate roles.
+pub fn normalize_turns(turns: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let mut normalized: Vec<ChatMessage> = Vec::with_capacity(turns.len());
+
+    for turn in turns {
+        if turn.content.trim().is_empty() {
+            continue;
+        }
+        if normalized.is_empty() && turn.role != "user" {
+            continue;
+        }
+        match normalized.last_mut() {
+            Some(last) if last.role == turn.role => {
+                last.content.push('\n');
+                last.content.push_str(&turn.content);
+            }
+            _ => normalized.push(turn),
+        }
+    }
+
+    normalized
+}
+
+/// Estimate the token count of a piece of text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Drop the oldest turns until history fits within `token_budget`
+///
+/// The budget is typically `Provider::context_window` minus the output
+/// reservation, system prompt and current prompt.
+pub fn trim_to_budget(mut turns: Vec<ChatMessage>, token_budget: usize) -> Vec<ChatMessage> {
+    let mut total: usize = turns.iter().map(|t| estimate_tokens(&t.content)).sum();
+
+    let mut drop = 0;
+    while total > token_budget && drop < turns.len() {
+        total -= estimate_tokens(&turns[drop].content);
+        drop += 1;
+    }
+    turns.drain(0..drop);
+
+    // Keep the conversation starting on a user turn
+    while turns.first().map(|t| t.role != "user").unwrap_or(false) {
+        turns.remove(0);
+    }
+
+    turns
+}
+
+/// Compute the history token budget for a context window
+pub fn history_budget(context_window: usize, max_output_tokens: usize, system: &str, prompt: &str) -> usize {
+    context_window
+        .saturating_sub(max_output_tokens)
+        .saturating_sub(estimate_tokens(system))
+        .saturating_sub(estimate_tokens(prompt))
+}
+
+/// Build the turns for a request: trimmed history followed by the current prompt
+///
+/// A trailing unanswered user turn is merged into the prompt so roles keep alternating.
+pub fn build_turns(history: &[ChatMessage], prompt: &str, token_budget: usize) -> Vec<ChatMessage> {
+    let mut turns = trim_to_budget(normalize_turns(history.to_vec()), token_budget);
+    turns.push(ChatMessage::user(prompt));
+    normalize_turns(turns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{remove_test_db, test_db};
+
+    fn message(id: &str, content: &str, timestamp: &str) -> NewMessage {
+        NewMessage {
+            id: id.to_string(),
+            chat_jid: "telegram:42".to_string(),
+            sender: "42".to_string(),
+            sender_name: "alice".to_string(),
+            content: content.to_string(),
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_load_history_roles_and_order() {
+        let (db, path) = test_db("test_history_order.db");
+        db.store_message(&message("1", "hi", "1000")).unwrap();
+        db.store_message(&message("self_1", "hello!", "1001")).unwrap();
+        db.store_message(&message("2", "how are you?", "1002")).unwrap();
+        db.store_message(&message("3", "current", "1003")).unwrap();
+
+        let turns = load_history(&db, &HistoryQuery::new("telegram:42").excluding("3")).unwrap();
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[0].role, "user");
+        assert_eq!(turns[0].content, "hi");
+        assert_eq!(turns[1].role, "assistant");
+        assert_eq!(turns[2].content, "how are you?");
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_load_history_since_and_limit() {
+        let (db, path) = test_db("test_history_since.db");
+        db.store_message(&message("1", "old", "1000")).unwrap();
+        db.store_message(&message("2", "new", "2000")).unwrap();
+        db.store_message(&message("self_2", "reply", "2001")).unwrap();
+
+        let turns = load_history(&db, &HistoryQuery::new("telegram:42").since("1500")).unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].content, "new");
+
+        let turns = load_history(&db, &HistoryQuery::new("telegram:42").limit(1)).unwrap();
+        // Only the assistant reply fits the limit, and a leading reply is dropped
+        assert!(turns.is_empty());
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_store_reply_marks_from_me() {
+        let (db, path) = test_db("test_history_reply.db");
+        let question = message("7", "question", "1000");
+        db.store_message(&question).unwrap();
+        store_reply(&db, &question, "Andy", "answer").unwrap();
+
+        let turns = load_history(&db, &HistoryQuery::new("telegram:42")).unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[1].role, "assistant");
+        assert_eq!(turns[1].content, "answer");
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_normalize_turns() {
+        let turns = normalize_turns(vec![
+            ChatMessage::assistant("stray"),
+            ChatMessage::user("a"),
+            ChatMessage::user("b"),
+            ChatMessage::assistant("c"),
+            ChatMessage::user("  "),
+        ]);
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].content, "a\nb");
+        assert_eq!(turns[1].role, "assistant");
+    }
+
+    #[test]
+    fn test_trim_to_budget_drops_oldest() {
+        let turns = vec![
+            ChatMessage::user("x".repeat(400)),
+            ChatMessage::assistant("y".repeat(400)),
+            ChatMessage::user("z".repeat(40)),
+            ChatMessage::assistant("w".repeat(40)),
+        ];
+        let trimmed = trim_to_budget(turns, 50);
+        assert_eq!(trimmed.len(), 2);
+        assert_eq!(trimmed[0].role, "user");
+        assert!(trimmed[0].content.starts_with('z'));
+    }
+
+    #[test]
+    fn test_history_budget() {
+        assert_eq!(history_budget(1000, 100, "", ""), 900);
+        assert_eq!(history_budget(100, 200, "", ""), 0);
+        assert_eq!(history_budget(1000, 0, &"s".repeat(40), &"p".repeat(40)), 980);
+    }
+
+    #[test]
+    fn test_build_turns_ends_with_prompt() {
+        let history = vec![
+            ChatMessage::user("a"),
+            ChatMessage::assistant("b"),
+            ChatMessage::user("unanswered"),
+        ];
+        let turns = build_turns(&history, "now", 1000);
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[2].role, "user");
+        assert_eq!(turns[2].content, "unanswered\nnow");
+
+        let turns = build_turns(&[], "now", 0);
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].content, "now");
+    }
+}
@@ -0,0 +1,789 @@
+//! Agent IPC - Lets agents ask the host to act on their behalf
+//!
+//! Each group's IPC directory (`data/ipc/<group>`) is mounted into the agent
+//! at `/workspace/ipc`. The agent writes one JSON request per file into
+//! `requests/` (writing to a dot-file first and renaming it is recommended);
+//! the host watcher validates the request against the group's permissions,
+//! executes it and writes the outcome to `responses/` under the same name.
+//!
+//! Requests carry the `run_id` from the agent's input. Requests without an
+//! explicit `chat_jid` target the chat of that run, so concurrent chats of one
+//! group never receive each other's messages.
+//!
+//! Supported requests:
+//! - `send_message`: send text to a chat
+//! - `schedule_task`: create a scheduled task
+//! - `cancel_task`: cancel a scheduled task
+//! - `store_memory`: remember something in the group's `MEMORY.md`
+//!
+//! The main group may target any chat, group or task. Other groups may only
+//! message chats they have served and manage their own tasks.
+
+use crate::channels::send_to_jid;
+use crate::config::{data_dir, groups_dir};
+use crate::container_runner::create_group_ipc_directory;
+use crate::context::FileMemory;
+use crate::db::Database;
+use crate::error::{NuClawError, Result};
+use crate::mounts::ValidatedMount;
+use crate::task_scheduler::{NewTask, TaskManager, TaskScope};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+/// IPC directory mount point inside the agent
+pub const IPC_MOUNT_PATH: &str = "/workspace/ipc";
+/// Subdirectory the agent writes requests to
+pub const REQUESTS_DIR: &str = "requests";
+/// Subdirectory the host writes responses to
+pub const RESPONSES_DIR: &str = "responses";
+/// Default poll interval: 1 second
+const DEFAULT_IPC_POLL_INTERVAL_MS: u64 = 1000;
+/// Larger request files are rejected unread
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+/// Requests handled per group on each poll
+const MAX_REQUESTS_PER_POLL: usize = 20;
+/// Longest memory key accepted
+const MAX_MEMORY_KEY_LEN: usize = 100;
+/// Runs remembered per group for resolving their chat
+const MAX_TRACKED_RUNS: usize = 100;
+
+/// Get IPC poll interval from environment or default
+pub fn ipc_poll_interval() -> Duration {
+    let interval_ms = std::env::var("IPC_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|ms| *ms > 0)
+        .unwrap_or(DEFAULT_IPC_POLL_INTERVAL_MS);
+    Duration::from_millis(interval_ms)
+}
+
+/// An action the agent asks the host to perform
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcAction {
+    /// Send a message; defaults to the chat the agent is answering
+    SendMessage {
+        #[serde(default)]
+        chat_jid: Option<String>,
+        text: String,
+    },
+    /// Schedule a task; defaults to the agent's own group and chat
+    ScheduleTask {
+        prompt: String,
+        schedule_type: String,
+        schedule_value: String,
+        #[serde(default)]
+        chat_jid: Option<String>,
+        #[serde(default)]
+        group_folder: Option<String>,
+        #[serde(default)]
+        context_mode: Option<String>,
+        #[serde(default)]
+        timezone: Option<String>,
+        #[serde(default)]
+        misfire_policy: Option<String>,
+        #[serde(default)]
+        max_retries: Option<u32>,
+        #[serde(default)]
+        retry_backoff_ms: Option<i64>,
+        #[serde(default)]
+        delivery: Option<String>,
+    },
+    CancelTask {
+        task_id: String,
+    },
+    StoreMemory {
+        key: String,
+        content: String,
+    },
+}
+
+/// A request file written by the agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IpcRequest {
+    /// Echoed back in the response so the agent can match them up
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The issuing run, as given in the agent's input
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(flatten)]
+    pub action: IpcAction,
+}
+
+/// A response file written by the host
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IpcResponse {
+    pub id: Option<String>,
+    /// `ok` or `error`
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl IpcResponse {
+    fn from_result(id: Option<String>, result: Result<serde_json::Value>) -> Self {
+        match result {
+            Ok(value) => Self {
+                id,
+                status: "ok".to_string(),
+                result: Some(value),
+                error: None,
+            },
+            Err(e) => Self {
+                id,
+                status: "error".to_string(),
+                result: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// What the host knows about a group from the runs it has started
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupIpcContext {
+    pub is_main: bool,
+    /// Chats the group has served, most recent last
+    pub chat_jids: Vec<String>,
+    /// Recent runs as `(run_id, chat_jid)`, most recent last
+    pub runs: Vec<(String, String)>,
+}
+
+impl GroupIpcContext {
+    /// The chat a run is answering
+    ///
+    /// Without a run id the chat is only known when the group has served a
+    /// single chat.
+    pub fn chat_for_run(&self, run_id: Option<&str>) -> Option<&str> {
+        match run_id {
+            Some(run_id) => self
+                .runs
+                .iter()
+                .rev()
+                .find(|(id, _)| id == run_id)
+                .map(|(_, jid)| jid.as_str()),
+            None if self.chat_jids.len() == 1 => self.chat_jids.first().map(String::as_str),
+            None => None,
+        }
+    }
+
+    pub fn owns_chat(&self, chat_jid: &str) -> bool {
+        self.chat_jids.iter().any(|jid| jid == chat_jid)
+    }
+}
+
+fn group_contexts() -> &'static RwLock<HashMap<String, GroupIpcContext>> {
+    static CONTEXTS: OnceLock<RwLock<HashMap<String, GroupIpcContext>>> = OnceLock::new();
+    CONTEXTS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Record that a group is running for a chat
+///
+/// Called whenever an agent run is prepared; IPC requests from groups the
+/// host has not started are rejected, and `run_id` lets the run's requests
+/// find its chat.
+pub fn register_group_chat(group_folder: &str, chat_jid: &str, is_main: bool, run_id: Option<&str>) {
+    if let Ok(mut contexts) = group_contexts().write() {
+        let context = contexts.entry(group_folder.to_string()).or_default();
+        context.is_main = is_main;
+        context.chat_jids.retain(|jid| jid != chat_jid);
+        context.chat_jids.push(chat_jid.to_string());
+        if let Some(run_id) = run_id {
+            context.runs.push((run_id.to_string(), chat_jid.to_string()));
+            let excess = context.runs.len().saturating_sub(MAX_TRACKED_RUNS);
+            context.runs.drain(..excess);
+        }
+    }
+}
+
+/// The registered context for a group, if it has run since startup
+pub fn group_context(group_folder: &str) -> Option<GroupIpcContext> {
+    group_contexts().read().ok()?.get(group_folder).cloned()
+}
+
+/// Mount for a group's IPC directory, creating its request/response folders
+pub fn ipc_mount(group_folder: &str) -> Result<ValidatedMount> {
+    let ipc_dir = create_group_ipc_directory(group_folder)?;
+    for sub in [REQUESTS_DIR, RESPONSES_DIR] {
+        fs::create_dir_all(ipc_dir.join(sub)).map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to create IPC {} directory: {}", sub, e),
+        })?;
+    }
+    Ok(ValidatedMount {
+        host_path: ipc_dir,
+        container_path: IPC_MOUNT_PATH.to_string(),
+        read_write: true,
+    })
+}
+
+/// Check a request against the permissions of the group that sent it
+///
+/// Task ownership for `cancel_task` needs the database and is checked when
+/// the request is executed.
+pub fn authorize(group_folder: &str, context: &GroupIpcContext, action: &IpcAction) -> Result<()> {
+    if context.is_main {
+        return Ok(());
+    }
+    let deny = |message: String| Err(NuClawError::Security { message });
+    match action {
+        IpcAction::SendMessage {
+            chat_jid: Some(jid),
+            ..
+        } if !context.owns_chat(jid) => deny(format!(
+            "Group {} may not send messages to {}",
+            group_folder, jid
+        )),
+        IpcAction::ScheduleTask {
+            group_folder: Some(target),
+            ..
+        } if target != group_folder => deny(format!(
+            "Group {} may not schedule tasks for group {}",
+            group_folder, target
+        )),
+        IpcAction::ScheduleTask {
+            chat_jid: Some(jid),
+            ..
+        } if !context.owns_chat(jid) => deny(format!(
+            "Group {} may not schedule tasks for chat {}",
+            group_folder, jid
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Delivers `send_message` requests
+#[async_trait]
+pub trait MessageSender: Send + Sync {
+    async fn send(&self, chat_jid: &str, text: &str) -> Result<()>;
+}
+
+/// Sends through whichever channel owns the chat jid
+pub struct ChannelSender {
+    db: Database,
+}
+
+impl ChannelSender {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl MessageSender for ChannelSender {
+    async fn send(&self, chat_jid: &str, text: &str) -> Result<()> {
+        send_to_jid(&self.db, chat_jid, text).await
+    }
+}
+
+/// Host-side watcher that executes agent IPC requests
+#[derive(Clone)]
+pub struct IpcWatcher {
+    db: Database,
+    sender: Arc<dyn MessageSender>,
+    root: PathBuf,
+    poll_interval: Duration,
+}
+
+impl IpcWatcher {
+    /// Create a watcher over `data/ipc`
+    pub fn new(db: Database, sender: Arc<dyn MessageSender>) -> Self {
+        Self {
+            db,
+            sender,
+            root: data_dir().join("ipc"),
+            poll_interval: ipc_poll_interval(),
+        }
+    }
+
+    /// Watch a different IPC root
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Poll for requests until the task is aborted
+    pub async fn run(&self) {
+        let mut ticker = interval(self.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        tracing::info!("IPC watcher started on {}", self.root.display());
+        loop {
+            ticker.tick().await;
+            self.process_pending().await;
+        }
+    }
+
+    /// Run the watcher in the background
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    /// Handle every pending request once; returns the number handled
+    pub async fn process_pending(&self) -> usize {
+        let Ok(groups) = fs::read_dir(&self.root) else {
+            return 0;
+        };
+        let mut handled = 0;
+        for group in groups.flatten() {
+            if !group.path().is_dir() {
+                continue;
+            }
+            let group_folder = group.file_name().to_string_lossy().into_owned();
+            for path in pending_requests(&group.path().join(REQUESTS_DIR)) {
+                self.process_file(&group_folder, &path).await;
+                handled += 1;
+            }
+        }
+        handled
+    }
+
+    async fn process_file(&self, group_folder: &str, path: &Path) {
+        let (id, result) = match read_request(path) {
+            Ok(request) => {
+                let id = request.id.clone();
+                (id, self.execute(group_folder, request.run_id.as_deref(), request.action).await)
+            }
+            Err(e) => (None, Err(e)),
+        };
+        if let Err(e) = &result {
+            tracing::warn!("IPC request {} from group {} failed: {}", path.display(), group_folder, e);
+        }
+        let _ = fs::remove_file(path);
+
+        let Some(name) = path.file_name() else {
+            return;
+        };
+        let responses = path
+            .parent()
+            .and_then(Path::parent)
+            .map(|dir| dir.join(RESPONSES_DIR))
+            .unwrap_or_default();
+        if let Err(e) = write_response(&responses, &name.to_string_lossy(), &IpcResponse::from_result(id, result)) {
+            tracing::warn!("Failed to write IPC response for group {}: {}", group_folder, e);
+        }
+    }
+
+    /// Authorize and execute one action for a group, on behalf of `run_id`
+    pub async fn execute(
+        &self,
+        group_folder: &str,
+        run_id: Option<&str>,
+        action: IpcAction,
+    ) -> Result<serde_json::Value> {
+        let context = group_context(group_folder).ok_or_else(|| NuClawError::Security {
+            message: format!("Group {} has no active agent session", group_folder),
+        })?;
+        authorize(group_folder, &context, &action)?;
+        let current_chat = || {
+            context
+                .chat_for_run(run_id)
+                .map(str::to_string)
+                .ok_or_else(|| NuClawError::Validation {
+                    message: "No chat to target: pass chat_jid or the run_id from your input".to_string(),
+                })
+        };
+
+        match action {
+            IpcAction::SendMessage { chat_jid, text } => {
+                let chat_jid = chat_jid.map(Ok).unwrap_or_else(current_chat)?;
+                self.sender.send(&chat_jid, &text).await?;
+                tracing::info!("IPC: group {} sent a message to {}", group_folder, chat_jid);
+                Ok(serde_json::json!({ "chat_jid": chat_jid }))
+            }
+            IpcAction::ScheduleTask {
+                prompt,
+                schedule_type,
+                schedule_value,
+                chat_jid,
+                group_folder: target_group,
+                context_mode,
+                timezone,
+                misfire_policy,
+                max_retries,
+                retry_backoff_ms,
+                delivery,
+            } => {
+                let tasks = TaskManager::new(&self.db, TaskScope::new(group_folder, context.is_main));
+                let task = tasks.create(NewTask {
+                    prompt,
+                    schedule_type,
+                    schedule_value,
+                    group_folder: target_group,
+                    chat_jid: chat_jid.map(Ok).unwrap_or_else(current_chat)?,
+                    context_mode,
+                    timezone,
+                    misfire_policy,
+                    max_retries,
+                    retry_backoff_ms,
+                    delivery,
+                })?;
+                tracing::info!("IPC: group {} scheduled task {}", group_folder, task.id);
+                Ok(serde_json::json!({ "task_id": task.id, "next_run": task.next_run }))
+            }
+            IpcAction::CancelTask { task_id } => {
+                let tasks = TaskManager::new(&self.db, TaskScope::new(group_folder, context.is_main));
+                let task = tasks.cancel(&task_id)?;
+                tracing::info!("IPC: group {} cancelled task {}", group_folder, task.id);
+                Ok(serde_json::json!({ "task_id": task.id }))
+            }
+            IpcAction::StoreMemory { key, content } => {
+                let key = key.trim().to_string();
+                if key.is_empty() || key.len() > MAX_MEMORY_KEY_LEN {
+                    return Err(NuClawError::Validation {
+                        message: format!("Memory key must be 1-{} characters", MAX_MEMORY_KEY_LEN),
+                    });
+                }
+                FileMemory::new(groups_dir())
+                    .remember(group_folder, &key, &content)
+                    .map_err(|e| NuClawError::FileSystem {
+                        message: format!("Failed to store memory: {}", e),
+                    })?;
+                Ok(serde_json::json!({ "key": key }))
+            }
+        }
+    }
+}
+
+/// Request files ready to process, oldest name first
+fn pending_requests(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            // Dot-files are still being written
+            !name.starts_with('.') && name.ends_with(".json") && path.is_file()
+        })
+        .collect();
+    paths.sort();
+    paths.truncate(MAX_REQUESTS_PER_POLL);
+    paths
+}
+
+fn read_request(path: &Path) -> Result<IpcRequest> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to read IPC request: {}", e),
+    })?;
+    if !metadata.file_type().is_file() {
+        return Err(NuClawError::Security {
+            message: "IPC request is not a regular file".to_string(),
+        });
+    }
+    if metadata.len() > MAX_REQUEST_BYTES {
+        return Err(NuClawError::Validation {
+            message: format!("IPC request exceeds {} bytes", MAX_REQUEST_BYTES),
+        });
+    }
+    let content = fs::read_to_string(path).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to read IPC request: {}", e),
+    })?;
+    serde_json::from_str(&content).map_err(|e| NuClawError::Validation {
+        message: format!("Invalid IPC request: {}", e),
+    })
+}
+
+/// Write a response atomically so the agent never reads a partial file
+///
+/// The responses directory is writable by the agent, so the temp file is
+/// created fresh without following symlinks, and a directory or target that
+/// is a symlink is refused.
+fn write_response(dir: &Path, name: &str, response: &IpcResponse) -> Result<()> {
+    let to_fs_error = |e: std::io::Error| NuClawError::FileSystem {
+        message: format!("Failed to write IPC response: {}", e),
+    };
+    fs::create_dir_all(dir).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to create IPC responses directory: {}", e),
+    })?;
+    let is_symlink = |path: &Path| {
+        fs::symlink_metadata(path)
+            .map(|meta| meta.file_type().is_symlink())
+            .unwrap_or(false)
+    };
+    let target = dir.join(name);
+    if is_symlink(dir) || is_symlink(&target) {
+        return Err(NuClawError::Security {
+            message: format!("IPC response target {} is a symlink", target.display()),
+        });
+    }
+    let json = serde_json::to_string_pretty(response).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to serialize IPC response: {}", e),
+    })?;
+
+    let tmp = dir.join(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4().simple()));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    let mut file = options.open(&tmp).map_err(to_fs_error)?;
+    let written = file.write_all(json.as_bytes()).map_err(to_fs_error);
+    drop(file);
+    let result = written.and_then(|_| fs::rename(&tmp, &target).map_err(to_fs_error));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{remove_test_db, test_db};
+    use crate::task_scheduler::get_task;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct RecordingSender {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl MessageSender for RecordingSender {
+        async fn send(&self, chat_jid: &str, text: &str) -> Result<()> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((chat_jid.to_string(), text.to_string()));
+            Ok(())
+        }
+    }
+
+    fn write_request(root: &Path, group: &str, name: &str, json: &str) {
+        let dir = root.join(group).join(REQUESTS_DIR);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(name), json).unwrap();
+    }
+
+    fn read_response(root: &Path, group: &str, name: &str) -> IpcResponse {
+        let content = fs::read_to_string(root.join(group).join(RESPONSES_DIR).join(name)).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn test_parse_requests() {
+        let request: IpcRequest = serde_json::from_str(
+            r#"{"id": "r1", "type": "send_message", "text": "hi"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.id.as_deref(), Some("r1"));
+        assert_eq!(
+            request.action,
+            IpcAction::SendMessage {
+                chat_jid: None,
+                text: "hi".to_string()
+            }
+        );
+
+        let request: IpcRequest = serde_json::from_str(
+            r#"{"type": "schedule_task", "prompt": "p", "schedule_type": "cron", "schedule_value": "0 0 9 * * *"}"#,
+        )
+        .unwrap();
+        assert!(matches!(request.action, IpcAction::ScheduleTask { .. }));
+        assert!(serde_json::from_str::<IpcRequest>(r#"{"type": "reboot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_authorize_non_main_group() {
+        let context = GroupIpcContext {
+            is_main: false,
+            chat_jids: vec!["telegram:1".to_string()],
+            ..Default::default()
+        };
+        let send = |jid: &str| IpcAction::SendMessage {
+            chat_jid: Some(jid.to_string()),
+            text: "hi".to_string(),
+        };
+        assert!(authorize("team", &context, &send("telegram:1")).is_ok());
+        assert!(authorize("team", &context, &send("telegram:2")).is_err());
+
+        let schedule = IpcAction::ScheduleTask {
+            prompt: "p".to_string(),
+            schedule_type: "once".to_string(),
+            schedule_value: "2030-01-01T00:00:00Z".to_string(),
+            chat_jid: None,
+            group_folder: Some("other".to_string()),
+            context_mode: None,
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            delivery: None,
+        };
+        assert!(authorize("team", &context, &schedule).is_err());
+
+        let main = GroupIpcContext {
+            is_main: true,
+            ..Default::default()
+        };
+        assert!(authorize("main", &main, &send("telegram:2")).is_ok());
+        assert!(authorize("main", &main, &schedule).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_executes_requests() {
+        let root = TempDir::new().unwrap();
+        let (db, db_path) = test_db("test_ipc_watcher.db");
+        let sender = Arc::new(RecordingSender::default());
+        let watcher = IpcWatcher::new(db.clone(), sender.clone()).with_root(root.path());
+        register_group_chat("ipc_test_team", "telegram:100", false, None);
+
+        write_request(root.path(), "ipc_test_team", "1.json", r#"{"id": "a", "type": "send_message", "text": "hello"}"#);
+        write_request(
+            root.path(),
+            "ipc_test_team",
+            "2.json",
+            r#"{"id": "b", "type": "send_message", "chat_jid": "telegram:999", "text": "spam"}"#,
+        );
+        write_request(
+            root.path(),
+            "ipc_test_team",
+            "3.json",
+            r#"{"id": "c", "type": "schedule_task", "prompt": "Remind me", "schedule_type": "interval", "schedule_value": "86400000"}"#,
+        );
+        write_request(root.path(), "ipc_test_team", ".4.json", "partial");
+
+        assert_eq!(watcher.process_pending().await, 3);
+        assert_eq!(
+            *sender.sent.lock().unwrap(),
+            vec![("telegram:100".to_string(), "hello".to_string())]
+        );
+
+        assert_eq!(read_response(root.path(), "ipc_test_team", "1.json").status, "ok");
+        let denied = read_response(root.path(), "ipc_test_team", "2.json");
+        assert_eq!(denied.status, "error");
+        assert!(denied.error.unwrap().contains("may not send"));
+
+        let scheduled = read_response(root.path(), "ipc_test_team", "3.json");
+        assert_eq!(scheduled.id.as_deref(), Some("c"));
+        let task_id = scheduled.result.unwrap()["task_id"].as_str().unwrap().to_string();
+        let task = get_task(&db, &task_id).unwrap().unwrap();
+        assert_eq!(task.group_folder, "ipc_test_team");
+        assert_eq!(task.chat_jid, "telegram:100");
+        assert!(task.next_run.is_some());
+
+        // Processed requests are removed; in-progress dot-files are left alone
+        assert!(!root.path().join("ipc_test_team/requests/1.json").exists());
+        assert!(root.path().join("ipc_test_team/requests/.4.json").exists());
+
+        remove_test_db(&db_path);
+    }
+
+    #[tokio::test]
+    async fn test_requests_target_their_run_chat() {
+        let (db, db_path) = test_db("test_ipc_runs.db");
+        let sender = Arc::new(RecordingSender::default());
+        let watcher = IpcWatcher::new(db.clone(), sender.clone());
+        register_group_chat("ipc_runs_team", "telegram:1", false, Some("run-a"));
+        register_group_chat("ipc_runs_team", "telegram:2", false, Some("run-b"));
+
+        let send = |text: &str| IpcAction::SendMessage {
+            chat_jid: None,
+            text: text.to_string(),
+        };
+        watcher.execute("ipc_runs_team", Some("run-a"), send("to a")).await.unwrap();
+        watcher.execute("ipc_runs_team", Some("run-b"), send("to b")).await.unwrap();
+        assert_eq!(
+            *sender.sent.lock().unwrap(),
+            vec![
+                ("telegram:1".to_string(), "to a".to_string()),
+                ("telegram:2".to_string(), "to b".to_string()),
+            ]
+        );
+
+        // With two chats, a request without a known run has no default chat
+        assert!(watcher.execute("ipc_runs_team", None, send("lost")).await.is_err());
+        assert!(watcher.execute("ipc_runs_team", Some("run-x"), send("lost")).await.is_err());
+
+        remove_test_db(&db_path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_response_refuses_symlinks() {
+        let root = TempDir::new().unwrap();
+        let responses = root.path().join(RESPONSES_DIR);
+        fs::create_dir_all(&responses).unwrap();
+        let victim = root.path().join("victim.txt");
+        fs::write(&victim, "host file").unwrap();
+        let response = IpcResponse::from_result(None, Ok(serde_json::json!({})));
+
+        std::os::unix::fs::symlink(&victim, responses.join("a.json")).unwrap();
+        assert!(write_response(&responses, "a.json", &response).is_err());
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "host file");
+
+        write_response(&responses, "b.json", &response).unwrap();
+        assert_eq!(read_response(root.path(), "", "b.json").status, "ok");
+        let leftovers: Vec<_> = fs::read_dir(&responses)
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_task_respects_group_ownership() {
+        let (db, db_path) = test_db("test_ipc_cancel.db");
+        let watcher = IpcWatcher::new(db.clone(), Arc::new(RecordingSender::default()));
+        register_group_chat("ipc_cancel_owner", "telegram:1", false, None);
+        register_group_chat("ipc_cancel_other", "telegram:2", false, None);
+
+        let created = watcher
+            .execute(
+                "ipc_cancel_owner",
+                None,
+                IpcAction::ScheduleTask {
+                    prompt: "p".to_string(),
+                    schedule_type: "interval".to_string(),
+                    schedule_value: "60000".to_string(),
+                    chat_jid: None,
+                    group_folder: None,
+                    context_mode: None,
+                    timezone: None,
+                    misfire_policy: None,
+                    max_retries: None,
+                    retry_backoff_ms: None,
+                    delivery: None,
+                },
+            )
+            .await
+            .unwrap();
+        let task_id = created["task_id"].as_str().unwrap().to_string();
+
+        let cancel = IpcAction::CancelTask {
+            task_id: task_id.clone(),
+        };
+        assert!(watcher.execute("ipc_cancel_other", None, cancel.clone()).await.is_err());
+        assert!(watcher.execute("ipc_cancel_owner", None, cancel).await.is_ok());
+        assert_eq!(get_task(&db, &task_id).unwrap().unwrap().status, "cancelled");
+
+        // Groups that never ran cannot issue requests
+        let unknown = watcher
+            .execute(
+                "ipc_never_ran",
+                None,
+                IpcAction::StoreMemory {
+                    key: "k".to_string(),
+                    content: "v".to_string(),
+                },
+            )
+            .await;
+        assert!(unknown.is_err());
+
+        remove_test_db(&db_path);
+    }
+}
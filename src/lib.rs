@@ -10,24 +10,34 @@
 //! - Provider/Channel registry
 
 pub mod agent_runner;
+pub mod artifacts;
 pub mod auth;
 pub mod autoresearch;
+pub mod budget;
 pub mod channels;
 pub mod config;
 pub mod container_runner;
 pub mod db;
 pub mod discord;
 pub mod error;
+pub mod failover;
 pub mod feishu;
+pub mod history;
+pub mod ipc;
 pub mod logging;
 pub mod maintenance;
 pub mod memory;
+pub mod mounts;
 pub mod onboard;
 pub mod orchestrator;
 pub mod providers;
+pub mod retry;
 pub mod router;
 pub mod runtime;
+pub mod sandbox;
+pub mod secrets;
 pub mod security;
+pub mod session;
 pub mod skills;
 pub mod skill_to_rig;
 pub mod tool_registry;
@@ -35,9 +45,11 @@ pub mod skill_watcher;
 pub mod hot_reload_registry;
 pub mod skill_hot_reloader;
 pub mod wasm_executor;
+pub mod task_commands;
 pub mod task_scheduler;
 pub mod telegram;
 pub mod types;
+pub mod usage;
 pub mod utils;
 pub mod whatsapp;
 pub mod wechat;
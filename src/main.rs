@@ -7,6 +7,7 @@
 //! - Scheduled task management
 //! - SQLite persistence
 
+use nuclaw::agent_runner::{agent_runner_mode, AgentRunnerMode};
 use nuclaw::config;
 use nuclaw::container_runner::ensure_container_system_running;
 use nuclaw::db;
@@ -65,6 +66,82 @@ struct Args {
 
     #[structopt(long)]
     telegram_pair_revoke: Option<String>,
+
+    /// Show token usage aggregated per period (daily or monthly)
+    #[arg(long)]
+    usage: Option<String>,
+
+    /// Limit the usage report to one group folder
+    #[arg(long)]
+    usage_group: Option<String>,
+
+    /// Store a group secret, reading the value from stdin (needs --secret-group)
+    #[arg(long)]
+    secret_set: Option<String>,
+
+    /// List secret names, for one group with --secret-group (values are never shown)
+    #[arg(long)]
+    secret_list: bool,
+
+    /// Delete a group secret (needs --secret-group)
+    #[arg(long)]
+    secret_revoke: Option<String>,
+
+    /// Group folder the secret commands apply to
+    #[arg(long)]
+    secret_group: Option<String>,
+
+    /// List scheduled tasks, for one group with --task-group
+    #[arg(long)]
+    tasks: bool,
+
+    /// Create a task from "<cron|interval|once> <value> <prompt>" (needs --task-group and --task-chat)
+    #[arg(long)]
+    task_schedule: Option<String>,
+
+    /// Pause a scheduled task
+    #[arg(long)]
+    task_pause: Option<String>,
+
+    /// Resume a paused task
+    #[arg(long)]
+    task_resume: Option<String>,
+
+    /// Cancel a scheduled task
+    #[arg(long)]
+    task_cancel: Option<String>,
+
+    /// Group folder the task commands apply to
+    #[arg(long)]
+    task_group: Option<String>,
+
+    /// Chat a new task reports to, e.g. telegram:group:-100123
+    #[arg(long)]
+    task_chat: Option<String>,
+
+    /// IANA timezone of a new task's schedule (defaults to the group's)
+    #[arg(long)]
+    task_timezone: Option<String>,
+
+    /// How a new task handles runs missed while NuClaw was down: skip, run_once, run_all[:N]
+    #[arg(long)]
+    task_misfire: Option<String>,
+
+    /// Retries after a failed run before a new task is paused
+    #[arg(long)]
+    task_retries: Option<u32>,
+
+    /// Delay before a new task's first retry, e.g. 30s or 5m; doubled for each further one
+    #[arg(long)]
+    task_backoff: Option<String>,
+
+    /// Which results of a new task are posted to its chat: always, on_change or on_error
+    #[arg(long)]
+    task_deliver: Option<String>,
+
+    /// Show the recent runs of a task, including skipped ones
+    #[arg(long)]
+    task_runs: Option<String>,
 }
 
 #[tokio::main]
@@ -106,6 +183,26 @@ async fn main() -> Result<()> {
         run_telegram_pair_list_command()?;
     } else if args.telegram_pair_revoke.is_some() {
         run_telegram_pair_revoke_command(args.telegram_pair_revoke.unwrap())?;
+    } else if let Some(period) = args.usage.as_deref() {
+        run_usage_command(&db, period, args.usage_group.as_deref())?;
+    } else if let Some(name) = args.secret_set.as_deref() {
+        run_secret_set_command(secret_group(&args)?, name)?;
+    } else if args.secret_list {
+        run_secret_list_command(args.secret_group.as_deref())?;
+    } else if let Some(name) = args.secret_revoke.as_deref() {
+        run_secret_revoke_command(secret_group(&args)?, name)?;
+    } else if args.tasks {
+        run_task_list_command(&db, args.task_group.as_deref())?;
+    } else if let Some(spec) = args.task_schedule.as_deref() {
+        run_task_schedule_command(&db, &args, spec)?;
+    } else if let Some(id) = args.task_pause.as_deref() {
+        run_task_update_command(&db, "pause", id)?;
+    } else if let Some(id) = args.task_resume.as_deref() {
+        run_task_update_command(&db, "resume", id)?;
+    } else if let Some(id) = args.task_cancel.as_deref() {
+        run_task_update_command(&db, "cancel", id)?;
+    } else if let Some(id) = args.task_runs.as_deref() {
+        run_task_runs_command(&db, id)?;
     } else if args.scheduler {
         // Run task scheduler
         run_scheduler(db).await?;
@@ -136,14 +233,30 @@ async fn main() -> Result<()> {
 async fn run_main_application(db: db::Database) -> Result<()> {
     info!("Running main application...");
 
-    // Ensure container system is running and log any errors
-    if let Err(e) = ensure_container_system_running() {
-        warn!(
-            "Container system not available: {}. Continuing anyway...",
-            e
-        );
+    // Pick the agent runtime; only the container runner cannot do without one
+    let runner_mode = agent_runner_mode();
+    match ensure_container_system_running() {
+        Ok(runtime) => info!("Agent runtime: {}", runtime),
+        Err(e) if runner_mode == AgentRunnerMode::Container => return Err(e),
+        Err(e) => warn!("No container runtime available ({}); using the {:?} agent runner", e, runner_mode),
     }
 
+    // Start warm containers and their health checks when pooling is enabled
+    let pool = nuclaw::container_runner::global_pool();
+    let pool_handle = if pool.is_enabled() {
+        let _ = pool.warmup(&nuclaw::container_runner::ContainerPool::warm_groups()).await;
+        Some(pool.spawn_maintenance())
+    } else {
+        None
+    };
+
+    // Execute host actions (messages, tasks, memory) requested by agents
+    let ipc_handle = nuclaw::ipc::IpcWatcher::new(
+        db.clone(),
+        std::sync::Arc::new(nuclaw::ipc::ChannelSender::new(db.clone())),
+    )
+    .spawn();
+
     // Setup signal handlers for graceful shutdown
     let (shutdown_tx, mut shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
 
@@ -217,6 +330,11 @@ async fn run_main_application(db: db::Database) -> Result<()> {
     scheduler_handle.abort();
     telegram_handle.abort();
     feishu_handle.abort();
+    ipc_handle.abort();
+    if let Some(handle) = pool_handle {
+        handle.abort();
+        pool.shutdown().await;
+    }
 
     info!("NuClaw shutdown complete.");
     Ok(())
@@ -277,7 +395,7 @@ async fn run_scheduler(db: db::Database) -> Result<()> {
 
 /// Internal function to start WhatsApp bot (used by auto-start)
 async fn run_whatsapp_bot_internal(db: db::Database) -> Result<()> {
-    let runtime = std::sync::Arc::new(nuclaw::runtime::DockerRuntime);
+    let runtime = nuclaw::runtime::default_runtime();
     let router = std::sync::Arc::new(nuclaw::router::EventRouter::new(runtime));
     let mut client = nuclaw::whatsapp::WhatsAppClient::new(db, router);
     client.connect().await?;
@@ -554,6 +672,197 @@ fn run_telegram_pair_list_command() -> Result<()> {
     Ok(())
 }
 
+fn run_usage_command(db: &db::Database, period: &str, group_folder: Option<&str>) -> Result<()> {
+    use nuclaw::usage::{usage_summary, UsagePeriod};
+
+    let period: UsagePeriod = period.parse()?;
+    let rows = usage_summary(db, period, group_folder)?;
+
+    if rows.is_empty() {
+        println!("No token usage recorded.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<10}  {:<16}  {:<16}  {:<28}  {:>8}  {:>12}  {:>12}  {:>10}",
+        "PERIOD", "GROUP", "USER", "MODEL", "REQUESTS", "INPUT", "OUTPUT", "COST USD"
+    );
+    let mut total_cost = 0.0;
+    for row in &rows {
+        total_cost += row.cost_usd;
+        println!(
+            "{:<10}  {:<16}  {:<16}  {:<28}  {:>8}  {:>12}  {:>12}  {:>10.4}",
+            row.period,
+            row.group_folder,
+            row.user,
+            row.model,
+            row.requests,
+            row.input_tokens,
+            row.output_tokens,
+            row.cost_usd
+        );
+    }
+    println!("Total cost: ${:.4}", total_cost);
+
+    Ok(())
+}
+
+fn secret_group(args: &Args) -> Result<&str> {
+    args.secret_group.as_deref().ok_or_else(|| NuClawError::Config {
+        message: "--secret-group is required".to_string(),
+    })
+}
+
+fn run_secret_set_command(group_folder: &str, name: &str) -> Result<()> {
+    use std::io::{BufRead, IsTerminal};
+
+    if std::io::stdin().is_terminal() {
+        println!("Enter the value for {} and press Enter:", name);
+    }
+    let mut value = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut value)
+        .map_err(|e| NuClawError::FileSystem {
+            message: format!("Failed to read secret value: {}", e),
+        })?;
+    let value = value.trim_end_matches(['\r', '\n']);
+    if value.is_empty() {
+        return Err(NuClawError::Validation {
+            message: "Secret value is empty".to_string(),
+        });
+    }
+
+    nuclaw::secrets::SecretStore::open()?.set(group_folder, name, value)?;
+    println!("✓ Secret {} stored for group {}.", name, group_folder);
+    Ok(())
+}
+
+fn run_secret_list_command(group_folder: Option<&str>) -> Result<()> {
+    let secrets = nuclaw::secrets::SecretStore::open()?.list(group_folder)?;
+    if secrets.is_empty() {
+        println!("No secrets stored.");
+        return Ok(());
+    }
+
+    println!("{:<16}  {:<32}  {:<25}", "GROUP", "NAME", "UPDATED");
+    for secret in &secrets {
+        println!(
+            "{:<16}  {:<32}  {:<25}",
+            secret.group_folder, secret.name, secret.updated_at
+        );
+    }
+    Ok(())
+}
+
+fn run_secret_revoke_command(group_folder: &str, name: &str) -> Result<()> {
+    if nuclaw::secrets::SecretStore::open()?.revoke(group_folder, name)? {
+        println!("✓ Secret {} revoked for group {}.", name, group_folder);
+    } else {
+        println!("Secret {} not found for group {}.", name, group_folder);
+    }
+    Ok(())
+}
+
+fn run_task_list_command(db: &db::Database, group_folder: Option<&str>) -> Result<()> {
+    use nuclaw::task_scheduler::{TaskManager, TaskScope};
+
+    let scope = match group_folder {
+        Some(group) => TaskScope::new(group, false),
+        None => TaskScope::admin(),
+    };
+    let tasks = TaskManager::new(db, scope).list(false)?;
+    if tasks.is_empty() {
+        println!("No scheduled tasks.");
+        return Ok(());
+    }
+
+    for task in &tasks {
+        println!("{}", nuclaw::task_commands::format_task(task, true));
+    }
+    Ok(())
+}
+
+fn run_task_schedule_command(db: &db::Database, args: &Args, spec: &str) -> Result<()> {
+    use nuclaw::task_scheduler::{NewTask, TaskManager, TaskScope};
+
+    let (group_folder, chat_jid) = match (args.task_group.as_deref(), args.task_chat.as_deref()) {
+        (Some(group), Some(chat)) => (group, chat),
+        _ => {
+            return Err(NuClawError::Config {
+                message: "--task-group and --task-chat are required".to_string(),
+            })
+        }
+    };
+    let spec = nuclaw::task_commands::parse_schedule_spec(spec)?;
+    let retry_backoff_ms = match args.task_backoff.as_deref() {
+        Some(backoff) => Some(
+            nuclaw::task_commands::parse_interval_ms(backoff).ok_or_else(|| {
+                NuClawError::Config {
+                    message: format!(
+                        "Invalid --task-backoff '{}': use e.g. 30s, 5m or 1h",
+                        backoff
+                    ),
+                }
+            })?,
+        ),
+        None => spec.retry_backoff_ms,
+    };
+    let task = TaskManager::new(db, TaskScope::admin()).create(NewTask {
+        prompt: spec.prompt,
+        schedule_type: spec.schedule_type,
+        schedule_value: spec.schedule_value,
+        group_folder: Some(group_folder.to_string()),
+        chat_jid: chat_jid.to_string(),
+        context_mode: None,
+        timezone: args.task_timezone.clone().or(spec.timezone),
+        misfire_policy: args.task_misfire.clone().or(spec.misfire_policy),
+        max_retries: args.task_retries.or(spec.max_retries),
+        retry_backoff_ms,
+        delivery: args.task_deliver.clone().or(spec.delivery),
+    })?;
+    println!(
+        "✓ Task {} scheduled; next run {}.",
+        task.id,
+        nuclaw::task_commands::next_run_display(&task)
+    );
+    Ok(())
+}
+
+fn run_task_update_command(db: &db::Database, action: &str, id: &str) -> Result<()> {
+    use nuclaw::task_commands::resolve_task_id;
+    use nuclaw::task_scheduler::{TaskManager, TaskScope};
+
+    let manager = TaskManager::new(db, TaskScope::admin());
+    let id = resolve_task_id(&manager, id)?;
+    let task = match action {
+        "pause" => manager.pause(&id)?,
+        "resume" => manager.resume(&id)?,
+        _ => manager.cancel(&id)?,
+    };
+    println!("✓ Task {} is now {}.", task.id, task.status);
+    Ok(())
+}
+
+fn run_task_runs_command(db: &db::Database, id: &str) -> Result<()> {
+    use nuclaw::task_commands::{format_run, resolve_task_id};
+    use nuclaw::task_scheduler::{task_timezone, TaskManager, TaskScope};
+
+    let manager = TaskManager::new(db, TaskScope::admin());
+    let task = manager.get(&resolve_task_id(&manager, id)?)?;
+    let runs = manager.runs(&task.id, 20)?;
+    if runs.is_empty() {
+        println!("Task {} has not run yet.", task.id);
+        return Ok(());
+    }
+
+    let tz = task_timezone(&task);
+    for run in &runs {
+        println!("{}", format_run(run, tz));
+    }
+    Ok(())
+}
+
 fn run_telegram_pair_revoke_command(user_id: String) -> Result<()> {
     use nuclaw::telegram::PairingManager;
 
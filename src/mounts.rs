@@ -0,0 +1,353 @@
+//! Additional group mounts
+//!
+//! Groups may declare extra host directories in WORKFLOW.md. Each one must
+//! resolve (after following symlinks) inside a root listed in the mount
+//! allowlist, must not match a blocked pattern, and is mounted read-write only
+//! when both the allowlist root and the group's role permit it.
+
+use crate::config::mount_allowlist_path;
+use crate::context::security::{PathValidator, SecurityError};
+use crate::error::{NuClawError, Result};
+use crate::security::WorkspaceIsolation;
+use crate::workflow::{load_workflow_config, GroupMount};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directory inside the container that holds extra mounts
+pub const EXTRA_MOUNT_ROOT: &str = "/workspace/extra";
+
+/// A host directory that extra mounts may live under
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowedRoot {
+    pub path: String,
+    #[serde(default)]
+    pub allow_read_write: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Contents of `mount-allowlist.json`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MountAllowlist {
+    #[serde(default)]
+    pub allowed_roots: Vec<AllowedRoot>,
+    /// Substrings (or `*suffix` patterns) that may not appear in a mounted path
+    #[serde(default)]
+    pub blocked_patterns: Vec<String>,
+    /// Force read-only mounts for every group except main
+    #[serde(default)]
+    pub non_main_read_only: bool,
+}
+
+/// A mount that passed validation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatedMount {
+    /// Canonical host path
+    pub host_path: PathBuf,
+    /// Absolute path inside the container
+    pub container_path: String,
+    pub read_write: bool,
+}
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => home::home_dir()
+            .map(|home| home.join(rest.trim_start_matches('/')))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Load the mount allowlist; a missing file allows no extra mounts
+pub fn load_mount_allowlist(path: &Path) -> Result<MountAllowlist> {
+    if !path.exists() {
+        return Ok(MountAllowlist::default());
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to read mount allowlist {}: {}", path.display(), e),
+    })?;
+    serde_json::from_str(&content).map_err(|e| NuClawError::Config {
+        message: format!("Invalid mount allowlist {}: {}", path.display(), e),
+    })
+}
+
+fn matches_blocked_pattern(path: &Path, pattern: &str) -> bool {
+    let path = path.to_string_lossy().to_lowercase();
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_prefix('*') {
+        Some(suffix) => path.ends_with(suffix),
+        None => !pattern.is_empty() && path.contains(&pattern),
+    }
+}
+
+/// Container path for a mount: a single name under `/workspace/extra`
+fn container_path_for(mount: &GroupMount, host_path: &Path) -> Result<String> {
+    let name = match &mount.container_path {
+        Some(name) => name.trim_start_matches(EXTRA_MOUNT_ROOT).trim_matches('/').to_string(),
+        None => host_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(NuClawError::Security {
+            message: format!(
+                "Invalid container path for mount {}: use a simple name under {}",
+                mount.host_path, EXTRA_MOUNT_ROOT
+            ),
+        });
+    }
+    Ok(format!("{}/{}", EXTRA_MOUNT_ROOT, name))
+}
+
+impl MountAllowlist {
+    /// Validate one group mount against the allowlist
+    pub fn validate(&self, mount: &GroupMount, is_main: bool) -> Result<ValidatedMount> {
+        let reject = |reason: String| NuClawError::Security {
+            message: format!("Mount {} rejected: {}", mount.host_path, reason),
+        };
+
+        let isolation = WorkspaceIsolation::new(true);
+        let requested = isolation
+            .sanitize_path(&expand_home(&mount.host_path).to_string_lossy())
+            .ok_or_else(|| reject("path traversal is not allowed".to_string()))?;
+        if !requested.is_absolute() {
+            return Err(reject("host path must be absolute".to_string()));
+        }
+
+        let mut matched = None;
+        for root in &self.allowed_roots {
+            let root_path = expand_home(&root.path);
+            let validator = PathValidator::new(vec![root_path.clone()]);
+            match validator.validate_dir(&requested) {
+                Ok(canonical) => {
+                    matched = Some((root, canonical));
+                    break;
+                }
+                Err(SecurityError::PathOutsideAllowedRoots(_))
+                    if requested.starts_with(&root_path)
+                        && isolation.detect_symlink_escape(&requested, &root_path) =>
+                {
+                    return Err(reject(format!(
+                        "a symlink escapes the allowed root {}",
+                        root.path
+                    )));
+                }
+                // Outside this root, or the root itself does not exist
+                Err(SecurityError::PathOutsideAllowedRoots(_)) | Err(SecurityError::IoError(_)) => {
+                    continue
+                }
+                Err(e) => return Err(reject(e.to_string())),
+            }
+        }
+        let (root, host_path) = matched.ok_or_else(|| {
+            reject(format!(
+                "not inside an allowed root in {}",
+                mount_allowlist_path().display()
+            ))
+        })?;
+
+        if let Some(pattern) = self
+            .blocked_patterns
+            .iter()
+            .find(|pattern| matches_blocked_pattern(&host_path, pattern))
+        {
+            return Err(reject(format!("matches blocked pattern \"{}\"", pattern)));
+        }
+
+        let read_write =
+            mount.read_write && root.allow_read_write && (is_main || !self.non_main_read_only);
+        if mount.read_write && !read_write {
+            tracing::info!(
+                "Mount {} downgraded to read-only by the mount allowlist",
+                mount.host_path
+            );
+        }
+
+        Ok(ValidatedMount {
+            container_path: container_path_for(mount, &host_path)?,
+            host_path,
+            read_write,
+        })
+    }
+}
+
+/// Validate the extra mounts a group declares in WORKFLOW.md
+///
+/// Fails on the first mount that is not allowed, so a misconfigured group
+/// never runs with a partial set of mounts.
+pub fn group_mounts(group_folder: &str, is_main: bool) -> Result<Vec<ValidatedMount>> {
+    let workflow = load_workflow_config();
+    let mounts = match workflow.group(group_folder) {
+        Some(group) if !group.mounts.is_empty() => &group.mounts,
+        _ => return Ok(Vec::new()),
+    };
+    let allowlist = load_mount_allowlist(&mount_allowlist_path())?;
+    mounts
+        .iter()
+        .map(|mount| allowlist.validate(mount, is_main))
+        .collect()
+}
+
+/// `-v host:container[:ro]` arguments for docker/podman
+pub fn mount_args(mounts: &[ValidatedMount]) -> Vec<String> {
+    mounts
+        .iter()
+        .flat_map(|mount| {
+            let mode = if mount.read_write { "" } else { ":ro" };
+            [
+                "-v".to_string(),
+                format!("{}:{}{}", mount.host_path.display(), mount.container_path, mode),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn mount(host_path: &Path, read_write: bool) -> GroupMount {
+        GroupMount {
+            host_path: host_path.to_string_lossy().into_owned(),
+            container_path: None,
+            read_write,
+        }
+    }
+
+    fn allowlist(root: &Path, allow_read_write: bool) -> MountAllowlist {
+        MountAllowlist {
+            allowed_roots: vec![AllowedRoot {
+                path: root.to_string_lossy().into_owned(),
+                allow_read_write,
+                description: None,
+            }],
+            blocked_patterns: vec!["secret".to_string(), "*.key".to_string()],
+            non_main_read_only: true,
+        }
+    }
+
+    #[test]
+    fn test_parse_allowlist_file() {
+        let json = r#"{
+            "allowedRoots": [{"path": "~/projects", "allowReadWrite": true, "description": "Projects"}],
+            "blockedPatterns": ["password"],
+            "nonMainReadOnly": true
+        }"#;
+        let allowlist: MountAllowlist = serde_json::from_str(json).unwrap();
+        assert_eq!(allowlist.allowed_roots[0].path, "~/projects");
+        assert!(allowlist.allowed_roots[0].allow_read_write);
+        assert!(allowlist.non_main_read_only);
+
+        let missing = load_mount_allowlist(Path::new("/nonexistent/mount-allowlist.json")).unwrap();
+        assert!(missing.allowed_roots.is_empty());
+    }
+
+    #[test]
+    fn test_validate_mount_inside_root() {
+        let root = TempDir::new().unwrap();
+        let data = root.path().join("data");
+        std::fs::create_dir(&data).unwrap();
+
+        let validated = allowlist(root.path(), true).validate(&mount(&data, true), true).unwrap();
+        assert_eq!(validated.host_path, data.canonicalize().unwrap());
+        assert_eq!(validated.container_path, "/workspace/extra/data");
+        assert!(validated.read_write);
+
+        // Non-main groups are forced read-only
+        let validated = allowlist(root.path(), true).validate(&mount(&data, true), false).unwrap();
+        assert!(!validated.read_write);
+    }
+
+    #[test]
+    fn test_validate_mount_outside_root_rejected() {
+        let root = TempDir::new().unwrap();
+        let other = TempDir::new().unwrap();
+        let err = allowlist(root.path(), true)
+            .validate(&mount(other.path(), false), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("not inside an allowed root"));
+
+        let traversal = GroupMount {
+            host_path: format!("{}/../etc", root.path().display()),
+            container_path: None,
+            read_write: false,
+        };
+        let err = allowlist(root.path(), true).validate(&traversal, true).unwrap_err();
+        assert!(err.to_string().contains("path traversal"));
+
+        assert!(MountAllowlist::default().validate(&mount(root.path(), false), true).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_mount_symlink_escape_rejected() {
+        let root = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let link = root.path().join("escape");
+        std::os::unix::fs::symlink(outside.path(), &link).unwrap();
+
+        let err = allowlist(root.path(), true).validate(&mount(&link, false), true).unwrap_err();
+        assert!(err.to_string().contains("symlink escapes"));
+    }
+
+    #[test]
+    fn test_validate_mount_blocked_pattern_and_names() {
+        let root = TempDir::new().unwrap();
+        let secrets = root.path().join("my-secrets");
+        std::fs::create_dir(&secrets).unwrap();
+        let err = allowlist(root.path(), true).validate(&mount(&secrets, false), true).unwrap_err();
+        assert!(err.to_string().contains("blocked pattern"));
+
+        let docs = root.path().join("docs");
+        std::fs::create_dir(&docs).unwrap();
+        let renamed = GroupMount {
+            container_path: Some("reference".to_string()),
+            ..mount(&docs, false)
+        };
+        let validated = allowlist(root.path(), false).validate(&renamed, true).unwrap();
+        assert_eq!(validated.container_path, "/workspace/extra/reference");
+        assert!(!validated.read_write);
+
+        let nested = GroupMount {
+            container_path: Some("../group".to_string()),
+            ..mount(&docs, false)
+        };
+        assert!(allowlist(root.path(), true).validate(&nested, true).is_err());
+    }
+
+    #[test]
+    fn test_mount_args() {
+        let mounts = vec![
+            ValidatedMount {
+                host_path: PathBuf::from("/srv/docs"),
+                container_path: "/workspace/extra/docs".to_string(),
+                read_write: false,
+            },
+            ValidatedMount {
+                host_path: PathBuf::from("/srv/out"),
+                container_path: "/workspace/extra/out".to_string(),
+                read_write: true,
+            },
+        ];
+        assert_eq!(
+            mount_args(&mounts),
+            vec![
+                "-v",
+                "/srv/docs:/workspace/extra/docs:ro",
+                "-v",
+                "/srv/out:/workspace/extra/out",
+            ]
+        );
+    }
+}
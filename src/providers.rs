@@ -6,6 +6,14 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{NuClawError, Result};
+use crate::retry::{self, RetryPolicy};
+use crate::tool_registry::ToolDefinition;
+
+/// Default sampling temperature
+pub const DEFAULT_TEMPERATURE: f64 = 0.7;
+
+/// Default OpenRouter API endpoint (OpenAI-compatible)
+pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
 
 // Token limits for different providers
 /// Anthropic default context window
@@ -20,38 +28,158 @@ pub const ANTHROPIC_VISION_MAX_OUTPUT_TOKENS: usize = 8_192;
 pub const OPENAI_CONTEXT_WINDOW: usize = 128_000;
 /// OpenAI default max output tokens
 pub const OPENAI_MAX_OUTPUT_TOKENS: usize = 16_384;
+
+/// Context window and max output tokens of models whose limits differ from
+/// their provider's defaults
+///
+/// Router prefixes such as `openai/` are ignored.
+pub fn model_limits(model: &str) -> Option<(usize, usize)> {
+    const LIMITS: &[(&str, usize, usize)] = &[
+        ("gpt-4-32k", 32_768, 4_096),
+        ("gpt-4-0", 8_192, 4_096),
+        ("gpt-3.5-turbo", 16_385, 4_096),
+        ("claude-2", 100_000, 4_096),
+        ("claude-instant", 100_000, 4_096),
+    ];
+    let model = model.rsplit('/').next().unwrap_or(model);
+    if model == "gpt-4" {
+        return Some((8_192, 4_096));
+    }
+    LIMITS
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|&(_, window, output)| (window, output))
+}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    /// Tool calls requested by the assistant in this turn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Tool call this message answers (role "tool")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 impl ChatMessage {
-    pub fn system(content: impl Into<String>) -> Self {
+    fn new(role: &str, content: impl Into<String>) -> Self {
         Self {
-            role: "system".into(),
+            role: role.into(),
             content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
         }
     }
 
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", content)
+    }
+
     pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content)
+    }
+
+    /// Assistant turn that requested tool calls
+    pub fn assistant_with_tool_calls(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
         Self {
-            role: "user".into(),
-            content: content.into(),
+            tool_calls,
+            ..Self::new("assistant", content)
         }
     }
 
-    pub fn assistant(content: impl Into<String>) -> Self {
+    /// Result of a tool call, sent back to the model
+    pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
         Self {
-            role: "assistant".into(),
-            content: content.into(),
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new("tool", content)
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Tool call requested by the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// Token usage reported by a provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    #[serde(alias = "inputTokens")]
+    pub input_tokens: u64,
+    #[serde(alias = "outputTokens")]
+    pub output_tokens: u64,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Options for a structured chat request
+#[derive(Debug, Clone, Default)]
+pub struct ChatOptions {
+    /// Model to use (provider default when `None` or empty)
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    /// Output token limit (provider default when `None`)
+    pub max_tokens: Option<usize>,
+    /// Tools the model may call
+    pub tools: Vec<ToolDefinition>,
+}
+
+impl ChatOptions {
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    fn model_or<'a>(&'a self, default: &'a str) -> &'a str {
+        match self.model.as_deref() {
+            Some(model) if !model.is_empty() => model,
+            _ => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ChatResponse {
     pub text: Option<String>,
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: Option<Usage>,
+    /// Provider-reported stop reason (e.g. "end_turn", "tool_use", "stop")
+    pub stop_reason: Option<String>,
 }
 
 impl ChatResponse {
@@ -62,6 +190,10 @@ impl ChatResponse {
     pub fn text_or_empty(&self) -> &str {
         self.text.as_deref().unwrap_or("")
     }
+
+    pub fn has_tool_calls(&self) -> bool {
+        !self.tool_calls.is_empty()
+    }
 }
 
 #[async_trait]
@@ -80,6 +212,32 @@ pub trait Provider: Send + Sync {
         self.chat(message, model, temperature).await
     }
 
+    /// Send a full message list and get a structured response
+    ///
+    /// The default implementation flattens the conversation into
+    /// `chat_with_system` and reports text only.
+    async fn chat_messages(&self, messages: &[ChatMessage], options: &ChatOptions) -> Result<ChatResponse> {
+        let system = join_system_messages(messages);
+        let prompt = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.as_str())
+            .unwrap_or("");
+        let text = self
+            .chat_with_system(
+                system.as_deref(),
+                prompt,
+                options.model.as_deref().unwrap_or(""),
+                options.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            )
+            .await?;
+        Ok(ChatResponse {
+            text: Some(text),
+            ..Default::default()
+        })
+    }
+
     fn context_window(&self) -> usize {
         ANTHROPIC_CONTEXT_WINDOW
     }
@@ -166,7 +324,7 @@ impl ProviderConfig {
                 .or_else(|| std::env::var("CLAUDE_MODEL").ok())
                 .or_else(|| spec.default_model.map(|s| s.to_string()))
         } else {
-            None
+            std::env::var(format!("{}_MODEL", spec.name.to_uppercase())).ok()
         };
 
         Self {
@@ -264,11 +422,62 @@ pub fn provider_registry() -> ProviderRegistry {
     ProviderRegistry::new()
 }
 
+/// Join all system messages into a single system prompt
+fn join_system_messages(messages: &[ChatMessage]) -> Option<String> {
+    let system: Vec<&str> = messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .map(|m| m.content.as_str())
+        .collect();
+    if system.is_empty() {
+        None
+    } else {
+        Some(system.join("\n\n"))
+    }
+}
+
+/// Messages for a single-prompt request
+fn prompt_messages(system: Option<&str>, message: &str) -> Vec<ChatMessage> {
+    let mut messages = Vec::with_capacity(2);
+    if let Some(sys) = system {
+        messages.push(ChatMessage::system(sys));
+    }
+    messages.push(ChatMessage::user(message));
+    messages
+}
+
+fn api_error(message: impl Into<String>) -> NuClawError {
+    NuClawError::Api {
+        message: message.into(),
+    }
+}
+
+/// Send a JSON request and return the decoded JSON response
+async fn send_json(
+    policy: &RetryPolicy,
+    build: impl Fn() -> reqwest::RequestBuilder,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let response = retry::send_with_retry(policy, "LLM provider", || build().json(body).send()).await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(retry::classify_status(status, &body));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| api_error(format!("Failed to parse response: {}", e)))
+}
+
 pub struct AnthropicProvider {
     client: Client,
     api_key: String,
     base_url: String,
     default_model: String,
+    retry: RetryPolicy,
 }
 
 impl AnthropicProvider {
@@ -278,7 +487,128 @@ impl AnthropicProvider {
             api_key,
             base_url: base_url.unwrap_or_else(|| "https://api.anthropic.com".to_string()),
             default_model: model.unwrap_or_else(|| "claude-sonnet-4-20250514".to_string()),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Retry transient API failures according to `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Build a Messages API request body
+    ///
+    /// System messages become the top-level `system` field and consecutive
+    /// tool results are grouped into one user turn of `tool_result` blocks.
+    fn request_body(&self, messages: &[ChatMessage], options: &ChatOptions) -> serde_json::Value {
+        let mut turns: Vec<serde_json::Value> = Vec::new();
+
+        for message in messages {
+            match message.role.as_str() {
+                "system" => {}
+                "tool" => {
+                    let block = serde_json::json!({
+                        "type": "tool_result",
+                        "tool_use_id": message.tool_call_id.clone().unwrap_or_default(),
+                        "content": message.content,
+                    });
+                    let grouped = turns.last_mut().and_then(|last| {
+                        let is_tool_turn = last["role"] == "user"
+                            && last["content"]
+                                .as_array()
+                                .and_then(|blocks| blocks.first())
+                                .map(|b| b["type"] == "tool_result")
+                                .unwrap_or(false);
+                        if is_tool_turn {
+                            last["content"].as_array_mut()
+                        } else {
+                            None
+                        }
+                    });
+                    match grouped {
+                        Some(blocks) => blocks.push(block),
+                        None => turns.push(serde_json::json!({"role": "user", "content": [block]})),
+                    }
+                }
+                role if !message.tool_calls.is_empty() => {
+                    let mut blocks = Vec::new();
+                    if !message.content.is_empty() {
+                        blocks.push(serde_json::json!({"type": "text", "text": message.content}));
+                    }
+                    for call in &message.tool_calls {
+                        blocks.push(serde_json::json!({
+                            "type": "tool_use",
+                            "id": call.id,
+                            "name": call.name,
+                            "input": call.arguments,
+                        }));
+                    }
+                    turns.push(serde_json::json!({"role": role, "content": blocks}));
+                }
+                role => turns.push(serde_json::json!({"role": role, "content": message.content})),
+            }
+        }
+
+        let mut body = serde_json::json!({
+            "model": options.model_or(&self.default_model),
+            "max_tokens": options.max_tokens.unwrap_or(ANTHROPIC_MAX_OUTPUT_TOKENS),
+            "temperature": options.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            "messages": turns,
+        });
+        if let Some(system) = join_system_messages(messages) {
+            body["system"] = serde_json::Value::String(system);
         }
+        if !options.tools.is_empty() {
+            body["tools"] = options
+                .tools
+                .iter()
+                .map(|tool| {
+                    serde_json::json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.input_schema(),
+                    })
+                })
+                .collect();
+        }
+        body
+    }
+
+    /// Parse a Messages API response body
+    fn parse_response(body: &serde_json::Value) -> Result<ChatResponse> {
+        let blocks = body["content"]
+            .as_array()
+            .ok_or_else(|| api_error("Failed to parse response: missing content"))?;
+
+        let mut text: Option<String> = None;
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block["type"].as_str() {
+                Some("tool_use") => tool_calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].clone(),
+                }),
+                _ => {
+                    if let Some(part) = block["text"].as_str() {
+                        text.get_or_insert_with(String::new).push_str(part);
+                    }
+                }
+            }
+        }
+
+        let usage = body.get("usage").map(|usage| Usage {
+            input_tokens: usage["input_tokens"].as_u64().unwrap_or(0),
+            output_tokens: usage["output_tokens"].as_u64().unwrap_or(0),
+        });
+
+        Ok(ChatResponse {
+            text,
+            tool_calls,
+            usage,
+            stop_reason: body["stop_reason"].as_str().map(String::from),
+        })
     }
 }
 
@@ -300,111 +630,173 @@ impl Provider for AnthropicProvider {
         model: &str,
         temperature: f64,
     ) -> Result<String> {
-        let model = if model.is_empty() {
-            &self.default_model
-        } else {
-            model
-        };
-
-        #[derive(serde::Serialize)]
-        struct Request {
-            model: String,
-            max_tokens: usize,
-            temperature: f64,
-            system: Option<String>,
-            messages: Vec<Message>,
-        }
-
-        #[derive(serde::Serialize)]
-        struct Message {
-            role: String,
-            content: String,
-        }
+        let options = ChatOptions::default()
+            .with_model(model)
+            .with_temperature(temperature);
+        self.chat_messages(&prompt_messages(system, message), &options)
+            .await?
+            .text
+            .ok_or_else(|| api_error("No text in response"))
+    }
 
-        let request = Request {
-            model: model.to_string(),
-            max_tokens: ANTHROPIC_MAX_OUTPUT_TOKENS,
-            temperature,
-            system: system.map(|s| s.to_string()),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: message.to_string(),
-            }],
+    async fn chat_messages(&self, messages: &[ChatMessage], options: &ChatOptions) -> Result<ChatResponse> {
+        let url = format!("{}/v1/messages", self.base_url);
+        let build = || {
+            self.client
+                .post(&url)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
         };
-
-        let response = self
-            .client
-            .post(format!("{}/v1/messages", self.base_url))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| NuClawError::Api {
-                message: format!("Request failed: {}", e),
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(NuClawError::Api {
-                message: format!("API error {}: {}", status, body),
-            });
-        }
-
-        #[derive(serde::Deserialize)]
-        struct Response {
-            content: Vec<ContentBlock>,
-        }
-
-        #[derive(serde::Deserialize)]
-        struct ContentBlock {
-            text: Option<String>,
-        }
-
-        let resp: Response = response.json().await.map_err(|e| NuClawError::Api {
-            message: format!("Failed to parse response: {}", e),
-        })?;
-
-        resp.content
-            .into_iter()
-            .find_map(|c| c.text)
-            .ok_or_else(|| NuClawError::Api {
-                message: "No text in response".to_string(),
-            })
+        let body = send_json(&self.retry, build, &self.request_body(messages, options)).await?;
+        Self::parse_response(&body)
     }
 
     fn context_window(&self) -> usize {
-        ANTHROPIC_VISION_CONTEXT_WINDOW
+        model_limits(&self.default_model).map_or(ANTHROPIC_VISION_CONTEXT_WINDOW, |(window, _)| window)
     }
 
     fn max_output_tokens(&self) -> usize {
-        ANTHROPIC_VISION_MAX_OUTPUT_TOKENS
+        model_limits(&self.default_model).map_or(ANTHROPIC_VISION_MAX_OUTPUT_TOKENS, |(_, output)| output)
     }
 }
 
 pub struct OpenAIProvider {
     client: Client,
+    name: String,
     api_key: String,
     base_url: String,
     default_model: String,
+    retry: RetryPolicy,
 }
 
 impl OpenAIProvider {
     pub fn new(api_key: String, base_url: Option<String>, model: Option<String>) -> Self {
         Self {
             client: Client::new(),
+            name: "openai".to_string(),
             api_key,
             base_url: base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
             default_model: model.unwrap_or_else(|| "gpt-4o".to_string()),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Retry transient API failures according to `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Report a different provider name (for OpenAI-compatible gateways)
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Build a Chat Completions request body
+    fn request_body(&self, messages: &[ChatMessage], options: &ChatOptions) -> serde_json::Value {
+        let messages: Vec<serde_json::Value> = messages
+            .iter()
+            .map(|message| {
+                let mut value = serde_json::json!({
+                    "role": message.role,
+                    "content": message.content,
+                });
+                if !message.tool_calls.is_empty() {
+                    value["tool_calls"] = message
+                        .tool_calls
+                        .iter()
+                        .map(|call| {
+                            serde_json::json!({
+                                "id": call.id,
+                                "type": "function",
+                                "function": {
+                                    "name": call.name,
+                                    "arguments": call.arguments.to_string(),
+                                },
+                            })
+                        })
+                        .collect();
+                }
+                if let Some(id) = &message.tool_call_id {
+                    value["tool_call_id"] = serde_json::Value::String(id.clone());
+                }
+                value
+            })
+            .collect();
+
+        let mut body = serde_json::json!({
+            "model": options.model_or(&self.default_model),
+            "temperature": options.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            "messages": messages,
+        });
+        if let Some(max_tokens) = options.max_tokens {
+            body["max_tokens"] = max_tokens.into();
+        }
+        if !options.tools.is_empty() {
+            body["tools"] = options
+                .tools
+                .iter()
+                .map(|tool| {
+                    serde_json::json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.input_schema(),
+                        },
+                    })
+                })
+                .collect();
         }
+        body
+    }
+
+    /// Parse a Chat Completions response body
+    fn parse_response(body: &serde_json::Value) -> Result<ChatResponse> {
+        let choice = body["choices"]
+            .as_array()
+            .and_then(|choices| choices.first())
+            .ok_or_else(|| api_error("No choices in response"))?;
+        let message = &choice["message"];
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .map(|call| {
+                        // Arguments arrive as a JSON-encoded string
+                        let raw = call["function"]["arguments"].as_str().unwrap_or("{}");
+                        ToolCall {
+                            id: call["id"].as_str().unwrap_or_default().to_string(),
+                            name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                            arguments: serde_json::from_str(raw)
+                                .unwrap_or_else(|_| serde_json::Value::String(raw.to_string())),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let usage = body.get("usage").map(|usage| Usage {
+            input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+            output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+        });
+
+        Ok(ChatResponse {
+            text: message["content"].as_str().map(String::from),
+            tool_calls,
+            usage,
+            stop_reason: choice["finish_reason"].as_str().map(String::from),
+        })
     }
 }
 
 #[async_trait]
 impl Provider for OpenAIProvider {
     fn name(&self) -> &str {
-        "openai"
+        &self.name
     }
 
     async fn chat(&self, message: &str, model: &str, temperature: f64) -> Result<String> {
@@ -419,123 +811,91 @@ impl Provider for OpenAIProvider {
         model: &str,
         temperature: f64,
     ) -> Result<String> {
-        let model = if model.is_empty() {
-            &self.default_model
-        } else {
-            model
-        };
-
-        #[derive(serde::Serialize)]
-        struct Request {
-            model: String,
-            temperature: f64,
-            messages: Vec<Message>,
-        }
-
-        #[derive(serde::Serialize)]
-        struct Message {
-            role: String,
-            content: String,
-        }
-
-        let mut messages = Vec::new();
-        if let Some(sys) = system {
-            messages.push(Message {
-                role: "system".to_string(),
-                content: sys.to_string(),
-            });
-        }
-        messages.push(Message {
-            role: "user".to_string(),
-            content: message.to_string(),
-        });
+        let options = ChatOptions::default()
+            .with_model(model)
+            .with_temperature(temperature);
+        self.chat_messages(&prompt_messages(system, message), &options)
+            .await?
+            .text
+            .ok_or_else(|| api_error("No text in response"))
+    }
 
-        let request = Request {
-            model: model.to_string(),
-            temperature,
-            messages,
+    async fn chat_messages(&self, messages: &[ChatMessage], options: &ChatOptions) -> Result<ChatResponse> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let build = || {
+            self.client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", self.api_key))
         };
-
-        let response = self
-            .client
-            .post(format!("{}/chat/completions", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| NuClawError::Api {
-                message: format!("Request failed: {}", e),
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(NuClawError::Api {
-                message: format!("API error {}: {}", status, body),
-            });
-        }
-
-        #[derive(serde::Deserialize)]
-        struct Response {
-            choices: Vec<Choice>,
-        }
-
-        #[derive(serde::Deserialize)]
-        struct Choice {
-            message: ResponseMessage,
-        }
-
-        #[derive(serde::Deserialize)]
-        struct ResponseMessage {
-            content: String,
-        }
-
-        let resp: Response = response.json().await.map_err(|e| NuClawError::Api {
-            message: format!("Failed to parse response: {}", e),
-        })?;
-
-        resp.choices
-            .into_iter()
-            .next()
-            .map(|c| c.message.content)
-            .ok_or_else(|| NuClawError::Api {
-                message: "No choices in response".to_string(),
-            })
+        let body = send_json(&self.retry, build, &self.request_body(messages, options)).await?;
+        Self::parse_response(&body)
     }
 
     fn context_window(&self) -> usize {
-        OPENAI_CONTEXT_WINDOW
+        model_limits(&self.default_model).map_or(OPENAI_CONTEXT_WINDOW, |(window, _)| window)
     }
 
     fn max_output_tokens(&self) -> usize {
-        OPENAI_MAX_OUTPUT_TOKENS
+        model_limits(&self.default_model).map_or(OPENAI_MAX_OUTPUT_TOKENS, |(_, output)| output)
     }
 }
 
 pub fn create_provider(name: &str, config: &ProviderConfig) -> Option<Box<dyn Provider>> {
+    let retry = RetryPolicy::from_workflow();
     match name {
         "anthropic" => {
             if let Some(api_key) = &config.api_key {
-                Some(Box::new(AnthropicProvider::new(
-                    api_key.clone(),
-                    config.base_url.clone(),
-                    config.model.clone(),
-                )))
+                Some(Box::new(
+                    AnthropicProvider::new(api_key.clone(), config.base_url.clone(), config.model.clone())
+                        .with_retry_policy(retry),
+                ))
             } else {
                 None
             }
         }
         "openai" => {
             if let Some(api_key) = &config.api_key {
-                Some(Box::new(OpenAIProvider::new(
-                    api_key.clone(),
-                    config.base_url.clone(),
-                    config.model.clone(),
-                )))
+                Some(Box::new(
+                    OpenAIProvider::new(api_key.clone(), config.base_url.clone(), config.model.clone())
+                        .with_retry_policy(retry),
+                ))
+            } else {
+                None
+            }
+        }
+        "openrouter" => {
+            if let Some(api_key) = &config.api_key {
+                Some(Box::new(
+                    OpenAIProvider::new(
+                        api_key.clone(),
+                        config
+                            .base_url
+                            .clone()
+                            .or_else(|| Some(OPENROUTER_BASE_URL.to_string())),
+                        config.model.clone(),
+                    )
+                    .with_name("openrouter")
+                    .with_retry_policy(retry),
+                ))
             } else {
                 None
             }
         }
+        "custom" => {
+            // Self-hosted endpoints need an explicit base URL
+            match (&config.api_key, &config.base_url) {
+                (Some(api_key), Some(base_url)) => Some(Box::new(
+                    OpenAIProvider::new(
+                        api_key.clone(),
+                        Some(base_url.clone()),
+                        config.model.clone(),
+                    )
+                    .with_name("custom")
+                    .with_retry_policy(retry),
+                )),
+                _ => None,
+            }
+        }
         _ => None,
     }
 }
@@ -679,6 +1039,67 @@ mod tests {
         std::env::remove_var("ANTHROPIC_MODEL");
     }
 
+    #[test]
+    fn test_create_provider_openai_compatible() {
+        let config = ProviderConfig {
+            name: "openrouter".to_string(),
+            api_key: Some("key".to_string()),
+            base_url: None,
+            model: None,
+        };
+        let provider = create_provider("openrouter", &config).unwrap();
+        assert_eq!(provider.name(), "openrouter");
+        assert_eq!(provider.context_window(), OPENAI_CONTEXT_WINDOW);
+
+        let provider = create_provider("openai", &config).unwrap();
+        assert_eq!(provider.name(), "openai");
+    }
+
+    #[test]
+    fn test_model_limits() {
+        assert_eq!(model_limits("gpt-3.5-turbo-0125"), Some((16_385, 4_096)));
+        assert_eq!(model_limits("openai/gpt-4"), Some((8_192, 4_096)));
+        assert_eq!(model_limits("gpt-4-32k"), Some((32_768, 4_096)));
+        assert_eq!(model_limits("gpt-4o"), None);
+
+        let config = ProviderConfig {
+            name: "openai".to_string(),
+            api_key: Some("key".to_string()),
+            base_url: None,
+            model: Some("gpt-3.5-turbo".to_string()),
+        };
+        let provider = create_provider("openai", &config).unwrap();
+        assert_eq!(provider.context_window(), 16_385);
+        assert_eq!(provider.max_output_tokens(), 4_096);
+    }
+
+    #[test]
+    fn test_create_provider_custom_requires_base_url() {
+        let mut config = ProviderConfig {
+            name: "custom".to_string(),
+            api_key: Some("key".to_string()),
+            base_url: None,
+            model: Some("llama3".to_string()),
+        };
+        assert!(create_provider("custom", &config).is_none());
+
+        config.base_url = Some("http://localhost:11434/v1".to_string());
+        let provider = create_provider("custom", &config).unwrap();
+        assert_eq!(provider.name(), "custom");
+    }
+
+    #[test]
+    fn test_create_provider_unknown_or_unconfigured() {
+        let config = ProviderConfig {
+            name: "openai".to_string(),
+            api_key: None,
+            base_url: None,
+            model: None,
+        };
+        assert!(create_provider("openai", &config).is_none());
+        assert!(create_provider("nonexistent", &config).is_none());
+    }
+
     #[test]
     fn test_list_specs() {
         let registry = ProviderRegistry::new();
@@ -717,6 +1138,7 @@ mod tests {
     fn test_chat_response_has_text_true() {
         let resp = ChatResponse {
             text: Some("hello".to_string()),
+            ..Default::default()
         };
         assert!(resp.has_text());
     }
@@ -725,13 +1147,17 @@ mod tests {
     fn test_chat_response_has_text_false_empty() {
         let resp = ChatResponse {
             text: Some("".to_string()),
+            ..Default::default()
         };
         assert!(!resp.has_text());
     }
 
     #[test]
     fn test_chat_response_has_text_false_none() {
-        let resp = ChatResponse { text: None };
+        let resp = ChatResponse {
+            text: None,
+            ..Default::default()
+        };
         assert!(!resp.has_text());
     }
 
@@ -739,15 +1165,140 @@ mod tests {
     fn test_chat_response_text_or_empty() {
         let resp = ChatResponse {
             text: Some("test".to_string()),
+            ..Default::default()
         };
         assert_eq!(resp.text_or_empty(), "test");
     }
 
     #[test]
     fn test_chat_response_text_or_empty_none() {
-        let resp = ChatResponse { text: None };
+        let resp = ChatResponse {
+            text: None,
+            ..Default::default()
+        };
         assert_eq!(resp.text_or_empty(), "");
     }
+
+    fn weather_tool() -> ToolDefinition {
+        ToolDefinition {
+            name: "weather".to_string(),
+            description: "Get the weather".to_string(),
+            params: vec![],
+        }
+    }
+
+    fn tool_conversation() -> Vec<ChatMessage> {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "weather".to_string(),
+            arguments: serde_json::json!({"city": "Paris"}),
+        };
+        vec![
+            ChatMessage::system("Be brief"),
+            ChatMessage::user("Weather in Paris?"),
+            ChatMessage::assistant_with_tool_calls("", vec![call]),
+            ChatMessage::tool_result("call_1", "sunny"),
+        ]
+    }
+
+    #[test]
+    fn test_anthropic_request_body_messages() {
+        let provider = AnthropicProvider::new("key".to_string(), None, Some("claude-x".to_string()));
+        let options = ChatOptions::default().with_tools(vec![weather_tool()]);
+        let body = provider.request_body(&tool_conversation(), &options);
+
+        assert_eq!(body["model"], "claude-x");
+        assert_eq!(body["system"], "Be brief");
+        assert_eq!(body["max_tokens"], ANTHROPIC_MAX_OUTPUT_TOKENS);
+        assert_eq!(body["tools"][0]["name"], "weather");
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["content"], "Weather in Paris?");
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"]["city"], "Paris");
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+    }
+
+    #[test]
+    fn test_anthropic_parse_response() {
+        let body = serde_json::json!({
+            "content": [
+                {"type": "text", "text": "Checking"},
+                {"type": "tool_use", "id": "toolu_1", "name": "weather", "input": {"city": "Paris"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 12, "output_tokens": 5}
+        });
+        let resp = AnthropicProvider::parse_response(&body).unwrap();
+        assert_eq!(resp.text.as_deref(), Some("Checking"));
+        assert!(resp.has_tool_calls());
+        assert_eq!(resp.tool_calls[0].name, "weather");
+        assert_eq!(resp.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(resp.usage.unwrap().total_tokens(), 17);
+
+        assert!(AnthropicProvider::parse_response(&serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_openai_request_body_messages() {
+        let provider = OpenAIProvider::new("key".to_string(), None, None);
+        let options = ChatOptions::default()
+            .with_max_tokens(256)
+            .with_tools(vec![weather_tool()]);
+        let body = provider.request_body(&tool_conversation(), &options);
+
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(body["tools"][0]["function"]["name"], "weather");
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Paris\"}");
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn test_openai_parse_response() {
+        let body = serde_json::json!({
+            "choices": [{
+                "message": {
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "weather", "arguments": "{\"city\":\"Paris\"}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 20, "completion_tokens": 7}
+        });
+        let resp = OpenAIProvider::parse_response(&body).unwrap();
+        assert!(resp.text.is_none());
+        assert_eq!(resp.tool_calls[0].arguments["city"], "Paris");
+        assert_eq!(resp.stop_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(
+            resp.usage,
+            Some(Usage {
+                input_tokens: 20,
+                output_tokens: 7
+            })
+        );
+
+        let err = OpenAIProvider::parse_response(&serde_json::json!({"choices": []})).unwrap_err();
+        assert!(err.to_string().contains("No choices"));
+    }
+
+    #[test]
+    fn test_chat_options_model_or_default() {
+        assert_eq!(ChatOptions::default().model_or("fallback"), "fallback");
+        assert_eq!(ChatOptions::default().with_model("").model_or("fallback"), "fallback");
+        assert_eq!(ChatOptions::default().with_model("m").model_or("fallback"), "m");
+    }
 }
 
 // TODO: Fix provider_tests module - auto-generated file has compilation errors
@@ -0,0 +1,316 @@
+//! Retry policy for LLM API calls
+//!
+//! Retries transient failures (rate limits, overload, server errors and
+//! dropped connections) with jittered exponential backoff, honoring
+//! `retry-after` headers, and classifies the final failure as a `NuClawError`.
+
+use std::future::Future;
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::error::{NuClawError, Result};
+use crate::workflow::{load_workflow_config, AgentSettings};
+
+/// Delay before the first retry; doubled on every further attempt
+pub const INITIAL_BACKOFF_MS: u64 = 500;
+
+/// Non-standard status Anthropic returns when the API is overloaded
+const STATUS_OVERLOADED: u16 = 529;
+
+/// Retry settings for one kind of call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: usize,
+    pub initial_backoff: Duration,
+    /// Upper bound for a single backoff, including `retry-after` waits
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_agent_settings(&AgentSettings::default())
+    }
+}
+
+impl RetryPolicy {
+    /// Policy from `agent.max_retries` / `agent.retry_backoff_ms`
+    pub fn from_agent_settings(agent: &AgentSettings) -> Self {
+        let max_backoff = Duration::from_millis(agent.retry_backoff_ms.max(INITIAL_BACKOFF_MS));
+        Self {
+            max_retries: agent.max_retries,
+            initial_backoff: Duration::from_millis(INITIAL_BACKOFF_MS),
+            max_backoff,
+        }
+    }
+
+    /// Policy from the configured WORKFLOW.md
+    pub fn from_workflow() -> Self {
+        Self::from_agent_settings(&load_workflow_config().agent)
+    }
+
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Jittered exponential backoff for a retry (`attempt` starts at 0)
+    ///
+    /// The delay is drawn from the upper half of the exponential window so
+    /// concurrent clients spread out without retrying immediately.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt.min(20) as u32).unwrap_or(u32::MAX);
+        let window = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+            .as_millis() as u64;
+        Duration::from_millis(fastrand::u64(window / 2..=window))
+    }
+
+    /// Delay before the next retry, or `None` if the server asks to wait
+    /// longer than `max_backoff`
+    pub fn delay(&self, attempt: usize, retry_after: Option<Duration>) -> Option<Duration> {
+        match retry_after {
+            Some(wait) if wait > self.max_backoff => None,
+            Some(wait) => Some(wait),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Whether a response status is worth retrying
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.as_u16() == STATUS_OVERLOADED
+        || status.is_server_error()
+}
+
+/// Whether a transport error is worth retrying (connect failures, resets, timeouts)
+pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request() || error.is_body()
+}
+
+/// Whether an error message from a client library describes a transient failure
+///
+/// Used where only the rendered error is available (e.g. Rig completions).
+pub fn is_transient_message(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    ["429", "529", "rate limit", "overloaded", "connection", "timed out", "502", "503", "504"]
+        .iter()
+        .any(|needle| lower.contains(needle))
+}
+
+/// Parse `retry-after-ms`, or `retry-after` as seconds or an HTTP date
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = headers
+        .get("retry-after-ms")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+    {
+        return Some(Duration::from_millis(ms.max(0.0) as u64));
+    }
+
+    let value = headers.get("retry-after")?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return Some(Duration::from_millis((secs.max(0.0) * 1000.0) as u64));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Classify a failed response into a `NuClawError`
+pub fn classify_status(status: StatusCode, body: &str) -> NuClawError {
+    let message = format!("API error {}: {}", status, body);
+    match status.as_u16() {
+        429 => NuClawError::RateLimited { message },
+        STATUS_OVERLOADED => NuClawError::Overloaded { message },
+        401 | 403 => NuClawError::Auth { message },
+        _ => NuClawError::Api { message },
+    }
+}
+
+/// Classify a transport error into a `NuClawError`
+pub fn classify_error(error: &reqwest::Error, attempts: usize) -> NuClawError {
+    if error.is_timeout() {
+        NuClawError::Timeout {
+            operation: format!("LLM request after {} attempt(s): {}", attempts, error),
+        }
+    } else {
+        NuClawError::Api {
+            message: format!("Request failed after {} attempt(s): {}", attempts, error),
+        }
+    }
+}
+
+/// Send a request, retrying transient failures according to `policy`
+///
+/// `send` builds and sends a fresh request on every call. Successful and
+/// non-retryable responses are returned as-is, as is the last response once
+/// retries are exhausted, so callers can still read the error body. Transport
+/// errors that outlive the retries are classified into a `NuClawError`.
+pub async fn send_with_retry<F, Fut>(policy: &RetryPolicy, what: &str, mut send: F) -> Result<reqwest::Response>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = reqwest::Result<reqwest::Response>>,
+{
+    let mut attempt = 0;
+    loop {
+        let delay = match send().await {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => {
+                let status = response.status();
+                if !is_retryable_status(status) || attempt >= policy.max_retries {
+                    return Ok(response);
+                }
+                let Some(delay) = policy.delay(attempt, parse_retry_after(response.headers())) else {
+                    tracing::warn!("{} returned {} with a retry-after beyond the backoff limit", what, status);
+                    return Ok(response);
+                };
+                tracing::warn!(
+                    "{} returned {}, retrying in {:?} ({}/{})",
+                    what,
+                    status,
+                    delay,
+                    attempt + 1,
+                    policy.max_retries
+                );
+                delay
+            }
+            Err(e) => {
+                if !is_retryable_error(&e) || attempt >= policy.max_retries {
+                    return Err(classify_error(&e, attempt + 1));
+                }
+                let delay = policy.backoff(attempt);
+                tracing::warn!(
+                    "{} failed ({}), retrying in {:?} ({}/{})",
+                    what,
+                    e,
+                    delay,
+                    attempt + 1,
+                    policy.max_retries
+                );
+                delay
+            }
+        };
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn policy(max_retries: usize, max_backoff_ms: u64) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(max_backoff_ms),
+        }
+    }
+
+    #[test]
+    fn test_policy_from_agent_settings() {
+        let agent = AgentSettings {
+            max_retries: 5,
+            retry_backoff_ms: 2000,
+            ..Default::default()
+        };
+        let policy = RetryPolicy::from_agent_settings(&agent);
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.max_backoff, Duration::from_millis(2000));
+        assert_eq!(RetryPolicy::none().max_retries, 0);
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = policy(10, 1000);
+        for attempt in 0..10 {
+            let window = (100u64 << attempt).min(1000);
+            let delay = policy.backoff(attempt).as_millis() as u64;
+            assert!(delay >= window / 2 && delay <= window, "attempt {}: {}", attempt, delay);
+        }
+    }
+
+    #[test]
+    fn test_delay_honors_retry_after() {
+        let policy = policy(3, 5000);
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(2))), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(10))), None);
+        assert!(policy.delay(0, None).is_some());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(3)));
+
+        headers.insert("retry-after-ms", HeaderValue::from_static("250"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_millis(250)));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::from_u16(529).unwrap()));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn test_classify_status() {
+        assert!(matches!(
+            classify_status(StatusCode::TOO_MANY_REQUESTS, "slow down"),
+            NuClawError::RateLimited { .. }
+        ));
+        assert!(classify_status(StatusCode::from_u16(529).unwrap(), "").is_transient());
+        assert!(matches!(
+            classify_status(StatusCode::UNAUTHORIZED, "bad key"),
+            NuClawError::Auth { .. }
+        ));
+        assert!(matches!(
+            classify_status(StatusCode::BAD_GATEWAY, ""),
+            NuClawError::Api { .. }
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_message() {
+        assert!(is_transient_message("ProviderError: 529 Overloaded"));
+        assert!(is_transient_message("HttpError: connection reset by peer"));
+        assert!(!is_transient_message("invalid x-api-key"));
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_gives_up_on_connection_errors() {
+        let client = reqwest::Client::new();
+        let mut calls = 0;
+        let err = send_with_retry(&policy(2, 10), "test", || {
+            calls += 1;
+            // Nothing listens on the discard port
+            client.get("http://127.0.0.1:9").send()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(calls, 3);
+        assert!(err.to_string().contains("after 3 attempt(s)"));
+    }
+}
@@ -23,6 +23,16 @@ impl EventRouter {
         self.runtime.run(input).await
     }
 
+    /// Dispatch a chat event that resumes a stored agent session
+    ///
+    /// `session_id` replaces the per-message id the event would otherwise get;
+    /// `None` starts a fresh agent session.
+    pub async fn dispatch_in_session(&self, event: AppEvent, session_id: Option<String>) -> Result<ContainerOutput> {
+        let mut input = self.map_event_to_input(event).await?;
+        input.session_id = session_id;
+        self.runtime.run(input).await
+    }
+
     /// Internal logic to transform high-level events into execution requests.
     /// This is where business logic like "which group folder to use" resides.
     async fn map_event_to_input(&self, event: AppEvent) -> Result<ContainerInput> {
@@ -36,6 +46,8 @@ impl EventRouter {
                     is_main: !is_group,
                     is_scheduled_task: false,
                     session_workspace_id: None,
+                    history: Vec::new(),
+                    run_id: None,
                 })
             }
             AppEvent::ScheduledTask { task_id } => {
@@ -47,6 +59,8 @@ impl EventRouter {
                     is_main: true,
                     is_scheduled_task: true,
                     session_workspace_id: None,
+                    history: Vec::new(),
+                    run_id: None,
                 })
             }
         }
@@ -66,6 +80,11 @@ mod tests {
             result: Some("hello from mock".to_string()),
             new_session_id: None,
             error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         };
 
         let mock_runtime = Arc::new(MockRuntime::new(mock_output.clone()));
@@ -79,6 +98,8 @@ mod tests {
             is_main: true,
             is_scheduled_task: false,
             session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
         };
 
         let result = router.handle_event(input.clone()).await.unwrap();
@@ -95,6 +116,11 @@ mod tests {
             result: Some("processed".to_string()),
             new_session_id: None,
             error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         };
 
         let mock_runtime = Arc::new(MockRuntime::new(mock_output));
@@ -128,6 +154,11 @@ mod tests {
             result: None,
             new_session_id: None,
             error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         }));
         let router = EventRouter::new(mock_runtime.clone());
 
@@ -142,4 +173,36 @@ mod tests {
         assert!(invocations[0].prompt.contains("daily_report"));
         assert_eq!(invocations[0].is_scheduled_task, true);
     }
+
+    #[tokio::test]
+    async fn test_dispatch_in_session_overrides_session_id() {
+        let mock_runtime = Arc::new(MockRuntime::new(ContainerOutput {
+            status: "success".to_string(),
+            result: None,
+            new_session_id: None,
+            error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
+        }));
+        let router = EventRouter::new(mock_runtime.clone());
+        let event = |message_id: &str| AppEvent::ChatMessage {
+            platform: "whatsapp".to_string(),
+            chat_id: "1@s.whatsapp.net".to_string(),
+            user_id: "1".to_string(),
+            message_id: message_id.to_string(),
+            message_text: "hi".to_string(),
+            group_folder: "main".to_string(),
+            is_group: false,
+        };
+
+        router.dispatch_in_session(event("m1"), Some("sess-1".to_string())).await.unwrap();
+        router.dispatch_in_session(event("m2"), None).await.unwrap();
+
+        let invocations = mock_runtime.invocations.lock().unwrap();
+        assert_eq!(invocations[0].session_id.as_deref(), Some("sess-1"));
+        assert_eq!(invocations[1].session_id, None);
+    }
 }
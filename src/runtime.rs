@@ -1,10 +1,27 @@
 use async_trait::async_trait;
+use crate::container_runner::ProgressSender;
 use crate::types::{ContainerInput, ContainerOutput};
-use crate::error::Result;
+use crate::error::{NuClawError, Result};
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
 
 #[async_trait]
 pub trait Runtime: Send + Sync {
     async fn run(&self, input: ContainerInput) -> Result<ContainerOutput>;
+
+    /// Run and forward the agent's progress lines as they arrive
+    ///
+    /// Runtimes that cannot report progress just run.
+    async fn run_with_progress(
+        &self,
+        input: ContainerInput,
+        progress: ProgressSender,
+    ) -> Result<ContainerOutput> {
+        drop(progress);
+        self.run(input).await
+    }
 }
 
 pub struct DockerRuntime;
@@ -14,6 +31,230 @@ impl Runtime for DockerRuntime {
     async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
         crate::container_runner::run_container(input).await
     }
+
+    async fn run_with_progress(
+        &self,
+        input: ContainerInput,
+        progress: ProgressSender,
+    ) -> Result<ContainerOutput> {
+        crate::container_runner::run_container_with_progress(input, Some(progress)).await
+    }
+}
+
+/// Runs the agent with rootless Podman, keeping the host user's id in the container
+pub struct PodmanRuntime;
+
+#[async_trait]
+impl Runtime for PodmanRuntime {
+    async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
+        crate::container_runner::run_container(input).await
+    }
+
+    async fn run_with_progress(
+        &self,
+        input: ContainerInput,
+        progress: ProgressSender,
+    ) -> Result<ContainerOutput> {
+        crate::container_runner::run_container_with_progress(input, Some(progress)).await
+    }
+}
+
+/// Runs the agent in Linux namespaces (bwrap or unshare), no Docker daemon needed
+pub struct SandboxRuntime;
+
+#[async_trait]
+impl Runtime for SandboxRuntime {
+    async fn run(&self, input: ContainerInput) -> Result<ContainerOutput> {
+        crate::sandbox::run_sandboxed(input).await
+    }
+
+    async fn run_with_progress(
+        &self,
+        input: ContainerInput,
+        progress: ProgressSender,
+    ) -> Result<ContainerOutput> {
+        crate::sandbox::run_sandboxed_with_progress(input, Some(progress)).await
+    }
+}
+
+/// Which runtime executes agent containers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuntimeKind {
+    #[default]
+    Docker,
+    Podman,
+    Sandbox,
+}
+
+impl FromStr for RuntimeKind {
+    type Err = NuClawError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "docker" | "container" => Ok(RuntimeKind::Docker),
+            "podman" => Ok(RuntimeKind::Podman),
+            "sandbox" | "namespace" => Ok(RuntimeKind::Sandbox),
+            other => Err(NuClawError::Config {
+                message: format!("Unknown container runtime: {}", other),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeKind::Docker if cfg!(target_os = "macos") => write!(f, "container"),
+            RuntimeKind::Docker => write!(f, "docker"),
+            RuntimeKind::Podman => write!(f, "podman"),
+            RuntimeKind::Sandbox => write!(f, "sandbox"),
+        }
+    }
+}
+
+/// Order in which runtimes are tried when none is configured
+pub const RUNTIME_PROBE_ORDER: [RuntimeKind; 3] =
+    [RuntimeKind::Docker, RuntimeKind::Podman, RuntimeKind::Sandbox];
+
+/// Runtime chosen by the startup probe
+static SELECTED_RUNTIME: OnceLock<RuntimeKind> = OnceLock::new();
+
+/// Runtime set in CONTAINER_RUNTIME; `None` when unset or `auto`
+pub fn configured_runtime() -> Option<RuntimeKind> {
+    std::env::var("CONTAINER_RUNTIME")
+        .ok()
+        .filter(|v| !v.trim().is_empty() && v.trim() != "auto")
+        .and_then(|v| v.parse().ok())
+}
+
+/// Get the runtime kind: CONTAINER_RUNTIME, else the probed one, else docker
+pub fn runtime_kind() -> RuntimeKind {
+    configured_runtime()
+        .or_else(|| SELECTED_RUNTIME.get().copied())
+        .unwrap_or_default()
+}
+
+/// Whether a runtime can run agents on this host
+///
+/// Docker and Podman must answer `info` (Apple `container` must report or
+/// start its system service); the sandbox needs bwrap or unshare on Linux.
+pub fn probe_runtime(kind: RuntimeKind) -> bool {
+    fn succeeds(program: &str, args: &[&str]) -> bool {
+        Command::new(program)
+            .args(args)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    match kind {
+        RuntimeKind::Docker if cfg!(target_os = "macos") => {
+            succeeds("container", &["system", "status"]) || succeeds("container", &["system", "start"])
+        }
+        RuntimeKind::Docker => succeeds("docker", &["info"]),
+        RuntimeKind::Podman => succeeds("podman", &["info"]),
+        RuntimeKind::Sandbox => cfg!(target_os = "linux") && crate::sandbox::sandbox_backend().is_some(),
+    }
+}
+
+/// Whether Podman runs without root privileges
+pub fn podman_is_rootless() -> bool {
+    Command::new("podman")
+        .args(["info", "--format", "{{.Host.Security.Rootless}}"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "true")
+        .unwrap_or(false)
+}
+
+/// Pick the configured runtime if it works, else the first available one
+pub fn pick_runtime(
+    configured: Option<RuntimeKind>,
+    available: impl Fn(RuntimeKind) -> bool,
+) -> Result<RuntimeKind> {
+    if let Some(kind) = configured {
+        return if available(kind) {
+            Ok(kind)
+        } else {
+            Err(NuClawError::Container {
+                message: format!("Configured container runtime {} is not available", kind),
+            })
+        };
+    }
+    RUNTIME_PROBE_ORDER
+        .into_iter()
+        .find(|kind| available(*kind))
+        .ok_or_else(|| NuClawError::Container {
+            message: "No container runtime found: install Docker, Podman, or bubblewrap/unshare for the sandbox"
+                .to_string(),
+        })
+}
+
+/// Probe for a runtime and use it for all later runs
+pub fn select_runtime() -> Result<RuntimeKind> {
+    let kind = pick_runtime(configured_runtime(), probe_runtime)?;
+    let _ = SELECTED_RUNTIME.set(kind);
+    Ok(kind)
+}
+
+/// Create the configured runtime
+pub fn default_runtime() -> Arc<dyn Runtime> {
+    match runtime_kind() {
+        RuntimeKind::Docker => Arc::new(DockerRuntime),
+        RuntimeKind::Podman => Arc::new(PodmanRuntime),
+        RuntimeKind::Sandbox => Arc::new(SandboxRuntime),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn test_runtime_kind_from_env() {
+        std::env::remove_var("CONTAINER_RUNTIME");
+        assert_eq!(runtime_kind(), RuntimeKind::Docker);
+
+        std::env::set_var("CONTAINER_RUNTIME", "sandbox");
+        assert_eq!(runtime_kind(), RuntimeKind::Sandbox);
+
+        std::env::set_var("CONTAINER_RUNTIME", "bogus");
+        assert_eq!(runtime_kind(), RuntimeKind::Docker);
+        std::env::remove_var("CONTAINER_RUNTIME");
+    }
+
+    #[test]
+    #[serial]
+    fn test_configured_runtime() {
+        assert_eq!("Podman".parse::<RuntimeKind>().unwrap(), RuntimeKind::Podman);
+
+        std::env::set_var("CONTAINER_RUNTIME", "sandbox");
+        assert_eq!(configured_runtime(), Some(RuntimeKind::Sandbox));
+
+        std::env::set_var("CONTAINER_RUNTIME", "auto");
+        assert_eq!(configured_runtime(), None);
+        std::env::remove_var("CONTAINER_RUNTIME");
+        assert_eq!(configured_runtime(), None);
+    }
+
+    #[test]
+    fn test_pick_runtime() {
+        let only = |kinds: &'static [RuntimeKind]| move |kind| kinds.contains(&kind);
+
+        // Probing prefers docker, then podman, then the sandbox
+        assert_eq!(pick_runtime(None, only(&[RuntimeKind::Docker, RuntimeKind::Podman])).unwrap(), RuntimeKind::Docker);
+        assert_eq!(pick_runtime(None, only(&[RuntimeKind::Podman, RuntimeKind::Sandbox])).unwrap(), RuntimeKind::Podman);
+        assert_eq!(pick_runtime(None, only(&[RuntimeKind::Sandbox])).unwrap(), RuntimeKind::Sandbox);
+        assert!(pick_runtime(None, only(&[])).is_err());
+
+        // A configured runtime is used as is or reported as missing
+        assert_eq!(
+            pick_runtime(Some(RuntimeKind::Sandbox), only(&[RuntimeKind::Docker, RuntimeKind::Sandbox])).unwrap(),
+            RuntimeKind::Sandbox
+        );
+        assert!(pick_runtime(Some(RuntimeKind::Podman), only(&[RuntimeKind::Docker])).is_err());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,528 @@
+//! Sandbox Runner - Runs the agent in Linux namespaces without a Docker daemon
+//!
+//! Uses bubblewrap (`bwrap`) when it is installed, otherwise `unshare` with a
+//! `pivot_root` into a tmpfs root. Either way the agent gets:
+//! - Its own user, mount, PID, IPC and UTS namespaces (network optional)
+//! - A read-only view of the host's system directories
+//! - The group folder mounted read-write at `/workspace/group`
+//! - The group's IPC directory mounted read-write at `/workspace/ipc`
+//! - The same timeout and max output size as the Docker runner
+
+use crate::artifacts::{collect_run_artifacts, new_run_id, prepare_outbox};
+use crate::config::{
+    anthropic_api_key, anthropic_base_url, claude_model, data_dir,
+};
+use crate::container_runner::{
+    capture_output, container_timeout, parse_container_output, prepare_group_context,
+    write_ipc_files, ProgressSender,
+};
+use crate::error::{NuClawError, Result};
+use crate::ipc::ipc_mount;
+use crate::mounts::{group_mounts, ValidatedMount};
+use crate::secrets::group_secrets_for_run;
+use crate::types::{ContainerInput, ContainerOutput};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as AsyncCommand;
+use tokio::time::timeout;
+
+/// Group folder mount point inside the sandbox
+pub const SANDBOX_GROUP_DIR: &str = "/workspace/group";
+/// Input file mount point inside the sandbox
+pub const SANDBOX_INPUT_PATH: &str = "/workspace/input.json";
+/// Default agent command run inside the sandbox
+const DEFAULT_AGENT_COMMAND: &str = "claude";
+/// PATH inside the sandbox
+const SANDBOX_PATH: &str = "/usr/local/bin:/usr/bin:/bin:/usr/local/sbin:/usr/sbin:/sbin";
+/// Host directories exposed read-only inside the sandbox
+const SYSTEM_DIRS: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt"];
+
+/// Namespace tool used to build the sandbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxBackend {
+    /// bubblewrap (`bwrap`)
+    Bwrap,
+    /// util-linux `unshare` plus `pivot_root`
+    Unshare,
+}
+
+impl SandboxBackend {
+    pub fn program(&self) -> &'static str {
+        match self {
+            SandboxBackend::Bwrap => "bwrap",
+            SandboxBackend::Unshare => "unshare",
+        }
+    }
+}
+
+impl FromStr for SandboxBackend {
+    type Err = NuClawError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "bwrap" | "bubblewrap" => Ok(SandboxBackend::Bwrap),
+            "unshare" => Ok(SandboxBackend::Unshare),
+            other => Err(NuClawError::Config {
+                message: format!("Unknown sandbox backend: {}", other),
+            }),
+        }
+    }
+}
+
+/// Find an executable in PATH
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Sandbox backend from SANDBOX_BACKEND, or bwrap if installed, else unshare
+pub fn sandbox_backend() -> Option<SandboxBackend> {
+    if let Some(backend) = std::env::var("SANDBOX_BACKEND")
+        .ok()
+        .filter(|v| !v.trim().is_empty() && v.trim() != "auto")
+        .and_then(|v| v.parse().ok())
+    {
+        return Some(backend);
+    }
+    [SandboxBackend::Bwrap, SandboxBackend::Unshare]
+        .into_iter()
+        .find(|backend| find_executable(backend.program()).is_some())
+}
+
+/// Command run inside the sandbox (SANDBOX_AGENT_COMMAND, whitespace separated)
+pub fn sandbox_agent_command() -> Vec<String> {
+    let command = std::env::var("SANDBOX_AGENT_COMMAND")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_AGENT_COMMAND.to_string());
+    command.split_whitespace().map(String::from).collect()
+}
+
+/// Whether the sandbox shares the host network (SANDBOX_NETWORK, default true)
+///
+/// The agent needs network access to reach the LLM API, so network isolation
+/// is opt-in.
+pub fn sandbox_network_enabled() -> bool {
+    std::env::var("SANDBOX_NETWORK")
+        .map(|v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | "no" | "none"))
+        .unwrap_or(true)
+}
+
+/// Everything needed to launch one sandboxed agent run
+#[derive(Debug, Clone)]
+pub struct SandboxSpec {
+    pub backend: SandboxBackend,
+    pub group_dir: PathBuf,
+    pub input_path: PathBuf,
+    pub agent_command: Vec<String>,
+    pub network: bool,
+    /// Validated extra mounts from the group config
+    pub extra_mounts: Vec<ValidatedMount>,
+}
+
+impl SandboxSpec {
+    /// Arguments for the backend program
+    pub fn args(&self) -> Vec<String> {
+        match self.backend {
+            SandboxBackend::Bwrap => self.bwrap_args(),
+            SandboxBackend::Unshare => self.unshare_args(),
+        }
+    }
+
+    fn bwrap_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "--unshare-user",
+            "--unshare-pid",
+            "--unshare-ipc",
+            "--unshare-uts",
+            "--die-with-parent",
+            "--new-session",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        if !self.network {
+            args.push("--unshare-net".to_string());
+        }
+        for dir in SYSTEM_DIRS {
+            args.extend(["--ro-bind-try".to_string(), dir.to_string(), dir.to_string()]);
+        }
+        args.extend(
+            [
+                "--proc",
+                "/proc",
+                "--dev",
+                "/dev",
+                "--tmpfs",
+                "/tmp",
+                "--bind",
+                &self.group_dir.to_string_lossy(),
+                SANDBOX_GROUP_DIR,
+                "--ro-bind",
+                &self.input_path.to_string_lossy(),
+                SANDBOX_INPUT_PATH,
+            ]
+            .iter()
+            .map(|s| s.to_string()),
+        );
+        for mount in &self.extra_mounts {
+            let flag = if mount.read_write { "--bind" } else { "--ro-bind" };
+            args.extend([
+                flag.to_string(),
+                mount.host_path.to_string_lossy().into_owned(),
+                mount.container_path.clone(),
+            ]);
+        }
+        args.extend(["--chdir", SANDBOX_GROUP_DIR, "--"].iter().map(|s| s.to_string()));
+        args.extend(self.agent_command.iter().cloned());
+        args.push(SANDBOX_INPUT_PATH.to_string());
+        args
+    }
+
+    fn unshare_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "--user",
+            "--map-root-user",
+            "--mount",
+            "--pid",
+            "--ipc",
+            "--uts",
+            "--fork",
+            "--kill-child",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        if !self.network {
+            args.push("--net".to_string());
+        }
+        args.extend([
+            "--".to_string(),
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            UNSHARE_SETUP_SCRIPT.to_string(),
+            "nuclaw-sandbox".to_string(),
+            self.group_dir.to_string_lossy().into_owned(),
+            self.input_path.to_string_lossy().into_owned(),
+            SYSTEM_DIRS.join(" "),
+            self.unshare_mount_list(),
+        ]);
+        args.extend(self.agent_command.iter().cloned());
+        args.push(SANDBOX_INPUT_PATH.to_string());
+        args
+    }
+
+    /// Extra mounts as `mode|host|target` lines for the unshare setup script
+    fn unshare_mount_list(&self) -> String {
+        self.extra_mounts
+            .iter()
+            .map(|mount| {
+                format!(
+                    "{}|{}|{}",
+                    if mount.read_write { "rw" } else { "ro" },
+                    mount.host_path.display(),
+                    mount.container_path
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Builds a tmpfs root inside the new mount namespace and pivots into it
+///
+/// Positional arguments: group dir, input file, system dirs, extra mounts,
+/// agent command...
+const UNSHARE_SETUP_SCRIPT: &str = r#"set -e
+group="$1"; input="$2"; dirs="$3"; mounts="$4"; shift 4
+root=$(mktemp -d)
+mount -t tmpfs -o mode=755 tmpfs "$root"
+for d in $dirs; do
+  if [ -L "$d" ]; then
+    ln -s "$(readlink "$d")" "$root$d"
+  elif [ -d "$d" ]; then
+    mkdir -p "$root$d"
+    mount --rbind "$d" "$root$d"
+    mount -o remount,bind,ro "$root$d" 2>/dev/null || true
+  fi
+done
+mkdir -p "$root/workspace/group" "$root/proc" "$root/dev" "$root/tmp" "$root/.old"
+touch "$root/workspace/input.json"
+mount --bind "$group" "$root/workspace/group"
+mount --bind -o ro "$input" "$root/workspace/input.json"
+printf '%s\n' "$mounts" | while IFS='|' read -r mode host target; do
+  [ -n "$host" ] || continue
+  mkdir -p "$root$target"
+  mount --bind "$host" "$root$target"
+  if [ "$mode" = ro ]; then mount -o remount,bind,ro "$root$target"; fi
+done
+mount -t proc proc "$root/proc"
+mount --rbind /dev "$root/dev"
+cd "$root"
+pivot_root . .old
+umount -l /.old
+rmdir /.old
+cd /workspace/group
+exec "$@"
+"#;
+
+/// Write the agent input where the sandbox can bind it
+fn write_input_file(input: &ContainerInput) -> Result<PathBuf> {
+    let temp_dir = data_dir().join("temp");
+    fs::create_dir_all(&temp_dir).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to create temp directory: {}", e),
+    })?;
+    let input_path = temp_dir.join(format!("sandbox_input_{}.json", uuid::Uuid::new_v4()));
+    let input_json = serde_json::to_string(input).map_err(|e| NuClawError::Container {
+        message: format!("Failed to serialize input: {}", e),
+    })?;
+    fs::write(&input_path, input_json).map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to write input file: {}", e),
+    })?;
+    Ok(input_path)
+}
+
+/// Build the sandbox command with a minimal environment
+fn build_sandbox_command(spec: &SandboxSpec) -> AsyncCommand {
+    let mut cmd = AsyncCommand::new(spec.backend.program());
+    cmd.args(spec.args())
+        .env_clear()
+        .env("PATH", SANDBOX_PATH)
+        .env("HOME", SANDBOX_GROUP_DIR)
+        .env("LANG", "C.UTF-8");
+    for key in ["CLAUDE_CODE_OAUTH_TOKEN", "TERM"] {
+        if let Ok(value) = std::env::var(key) {
+            cmd.env(key, value);
+        }
+    }
+    if let Some(key) = anthropic_api_key() {
+        cmd.env("ANTHROPIC_API_KEY", key);
+    }
+    if let Some(url) = anthropic_base_url() {
+        cmd.env("ANTHROPIC_BASE_URL", url);
+    }
+    if let Some(model) = claude_model() {
+        cmd.env("CLAUDE_MODEL", model);
+    }
+    cmd.stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true);
+    cmd
+}
+
+/// Spawn the sandbox, feed it the input and collect its output
+async fn run_sandbox_command(
+    mut cmd: AsyncCommand,
+    input_path: &Path,
+    timeout_duration: std::time::Duration,
+    progress: Option<&ProgressSender>,
+) -> Result<ContainerOutput> {
+    let mut child = cmd.spawn().map_err(|e| NuClawError::Container {
+        message: format!("Failed to spawn sandbox: {}", e),
+    })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let input_content = fs::read(input_path).map_err(|e| NuClawError::Container {
+            message: format!("Failed to read input file: {}", e),
+        })?;
+        // The agent may not read stdin; a closed pipe is not an error
+        let _ = stdin.write_all(&input_content).await;
+        let _ = stdin.shutdown().await;
+    }
+    let stdout = child.stdout.take().ok_or_else(|| NuClawError::Container {
+        message: "Sandbox stdout not captured".to_string(),
+    })?;
+
+    let run = async {
+        let output = capture_output(stdout, progress).await?;
+        let status = child.wait().await.map_err(|e| NuClawError::Container {
+            message: format!("Failed to wait for sandbox: {}", e),
+        })?;
+        Ok::<_, NuClawError>((output, status))
+    };
+    match timeout(timeout_duration, run).await {
+        Ok(result) => {
+            let (output, status) = result?;
+            parse_container_output(&output, status.success(), 0)
+        }
+        // The child is killed when it is dropped on return (kill_on_drop)
+        Err(_) => {
+            let mut output = parse_container_output("", false, 0)?;
+            output.error = Some(format!(
+                "Sandbox timed out after {}s",
+                timeout_duration.as_secs()
+            ));
+            Ok(output)
+        }
+    }
+}
+
+/// Run the agent for `input` in a namespace sandbox
+pub async fn run_sandboxed(input: ContainerInput) -> Result<ContainerOutput> {
+    run_sandboxed_with_progress(input, None).await
+}
+
+/// Run the agent in a namespace sandbox, forwarding its progress lines
+pub async fn run_sandboxed_with_progress(
+    mut input: ContainerInput,
+    progress: Option<ProgressSender>,
+) -> Result<ContainerOutput> {
+    if !cfg!(target_os = "linux") {
+        return Err(NuClawError::Container {
+            message: "The namespace sandbox is only available on Linux".to_string(),
+        });
+    }
+    let backend = sandbox_backend().ok_or_else(|| NuClawError::Container {
+        message: "No sandbox backend found: install bubblewrap (bwrap) or util-linux (unshare)"
+            .to_string(),
+    })?;
+
+    let group_dir = prepare_group_context(&input.group_folder)?;
+    let run_id = input.run_id.get_or_insert_with(new_run_id).clone();
+    prepare_outbox(&input.group_folder, &run_id);
+    write_ipc_files(&input.group_folder, &input)?;
+    let input_path = write_input_file(&input)?;
+
+    let mut extra_mounts = group_mounts(&input.group_folder, input.is_main)?;
+    extra_mounts.push(ipc_mount(&input.group_folder)?);
+    let spec = SandboxSpec {
+        backend,
+        group_dir: group_dir.canonicalize().unwrap_or(group_dir),
+        input_path: input_path.clone(),
+        agent_command: sandbox_agent_command(),
+        network: sandbox_network_enabled(),
+        extra_mounts,
+    };
+    tracing::debug!("Running {} sandbox for group {}", backend.program(), input.group_folder);
+
+    let mut cmd = build_sandbox_command(&spec);
+    let secrets = group_secrets_for_run(&input.group_folder);
+    for (name, value) in secrets.iter() {
+        cmd.env(name, value);
+    }
+
+    let result = run_sandbox_command(
+        cmd,
+        &input_path,
+        container_timeout(),
+        progress.as_ref(),
+    )
+    .await;
+    let _ = fs::remove_file(&input_path);
+    let artifacts = collect_run_artifacts(&input.group_folder, &run_id);
+    result.map(|mut output| {
+        secrets.redact_output(&mut output);
+        output.artifacts = artifacts;
+        output
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    fn spec(backend: SandboxBackend, network: bool) -> SandboxSpec {
+        SandboxSpec {
+            backend,
+            group_dir: PathBuf::from("/data/groups/team"),
+            input_path: PathBuf::from("/data/temp/input.json"),
+            agent_command: vec!["claude".to_string(), "--print".to_string()],
+            network,
+            extra_mounts: vec![ValidatedMount {
+                host_path: PathBuf::from("/srv/docs"),
+                container_path: "/workspace/extra/docs".to_string(),
+                read_write: false,
+            }],
+        }
+    }
+
+    fn contains_pair(args: &[String], a: &str, b: &str) -> bool {
+        args.windows(2).any(|w| w[0] == a && w[1] == b)
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!("bwrap".parse::<SandboxBackend>().unwrap(), SandboxBackend::Bwrap);
+        assert_eq!("Unshare".parse::<SandboxBackend>().unwrap(), SandboxBackend::Unshare);
+        assert!("docker".parse::<SandboxBackend>().is_err());
+    }
+
+    #[test]
+    fn test_bwrap_args() {
+        let args = spec(SandboxBackend::Bwrap, true).args();
+        assert!(args.contains(&"--unshare-user".to_string()));
+        assert!(args.contains(&"--unshare-pid".to_string()));
+        assert!(!args.contains(&"--unshare-net".to_string()));
+        assert!(contains_pair(&args, "/data/groups/team", SANDBOX_GROUP_DIR));
+        assert!(contains_pair(&args, "--chdir", SANDBOX_GROUP_DIR));
+        assert!(contains_pair(&args, "--ro-bind", "/srv/docs"));
+        assert_eq!(&args[args.len() - 3..], ["claude", "--print", SANDBOX_INPUT_PATH]);
+
+        let isolated = spec(SandboxBackend::Bwrap, false).args();
+        assert!(isolated.contains(&"--unshare-net".to_string()));
+    }
+
+    #[test]
+    fn test_unshare_args() {
+        let args = spec(SandboxBackend::Unshare, false).args();
+        assert!(args.contains(&"--map-root-user".to_string()));
+        assert!(args.contains(&"--net".to_string()));
+        let script = args.iter().position(|a| a == UNSHARE_SETUP_SCRIPT).unwrap();
+        assert_eq!(args[script + 2], "/data/groups/team");
+        assert_eq!(args[script + 3], "/data/temp/input.json");
+        assert_eq!(args[script + 5], "ro|/srv/docs|/workspace/extra/docs");
+        assert_eq!(args.last().unwrap(), SANDBOX_INPUT_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_sandbox_env_settings() {
+        std::env::set_var("SANDBOX_AGENT_COMMAND", "node /opt/agent.js");
+        std::env::set_var("SANDBOX_NETWORK", "none");
+        std::env::set_var("SANDBOX_BACKEND", "unshare");
+        assert_eq!(sandbox_agent_command(), vec!["node", "/opt/agent.js"]);
+        assert!(!sandbox_network_enabled());
+        assert_eq!(sandbox_backend(), Some(SandboxBackend::Unshare));
+
+        std::env::remove_var("SANDBOX_AGENT_COMMAND");
+        std::env::remove_var("SANDBOX_NETWORK");
+        std::env::remove_var("SANDBOX_BACKEND");
+        assert_eq!(sandbox_agent_command(), vec![DEFAULT_AGENT_COMMAND]);
+        assert!(sandbox_network_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_run_sandbox_command_timeout_and_output() {
+        let input_path = std::env::temp_dir().join("nuclaw_sandbox_test_input.json");
+        fs::write(&input_path, "{}").unwrap();
+
+        let mut cmd = AsyncCommand::new("sh");
+        cmd.arg("-c")
+            .arg("echo '{\"status\":\"success\",\"result\":\"hi\"}'")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = run_sandbox_command(cmd, &input_path, std::time::Duration::from_secs(5), None)
+            .await
+            .unwrap();
+        assert_eq!(output.result.as_deref(), Some("hi"));
+
+        let mut cmd = AsyncCommand::new("sleep");
+        cmd.arg("10")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = run_sandbox_command(cmd, &input_path, std::time::Duration::from_millis(100), None)
+            .await
+            .unwrap();
+        assert_eq!(output.status, "error");
+        assert!(output.error.unwrap().contains("timed out"));
+
+        let _ = fs::remove_file(&input_path);
+    }
+}
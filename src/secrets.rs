@@ -0,0 +1,477 @@
+//! Group secrets - Encrypted per-group secrets injected into agent runs
+//!
+//! Each group's secrets live in `store/secrets/<group>.json`, every value
+//! encrypted with AES-256-GCM and bound to its group and name. The key comes
+//! from `NUCLAW_SECRET_KEY` (64 hex characters) or is generated once into
+//! `store/secrets.key`.
+//!
+//! A run only receives the secrets of its own group: as environment variables
+//! in the container or sandbox, and as plugin config for WASM skills that
+//! list them under `secrets` in API mode. Values are never logged; the
+//! `Debug` output of `GroupSecrets` shows names only and outputs are redacted
+//! before they are logged or returned.
+
+use crate::config::store_dir;
+use crate::container_runner::validate_group_folder;
+use crate::error::{NuClawError, Result};
+use crate::types::ContainerOutput;
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Text that replaces secret values in logged or returned output
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Values shorter than this are not redacted, as they would match ordinary text
+const MIN_REDACT_LEN: usize = 4;
+
+/// Names a secret may not take because the runtime sets them itself
+const RESERVED_NAMES: [&str; 3] = ["PATH", "HOME", "LANG"];
+
+/// Directory holding the per-group secret files
+pub fn secrets_dir() -> PathBuf {
+    store_dir().join("secrets")
+}
+
+/// File holding the generated secret key
+pub fn secret_key_path() -> PathBuf {
+    store_dir().join("secrets.key")
+}
+
+/// Check that a secret name is usable as an environment variable
+pub fn validate_secret_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = name.len() <= 128
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(NuClawError::Validation {
+            message: format!(
+                "Invalid secret name '{}': use letters, digits and underscores, not starting with a digit",
+                name
+            ),
+        });
+    }
+    if RESERVED_NAMES.contains(&name) {
+        return Err(NuClawError::Validation {
+            message: format!("Secret name '{}' is reserved", name),
+        });
+    }
+    Ok(())
+}
+
+/// One encrypted secret as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredSecret {
+    nonce: String,
+    ciphertext: String,
+    created_at: String,
+    updated_at: String,
+}
+
+/// What `list` shows about a secret; never the value
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretInfo {
+    pub group_folder: String,
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Decrypted secrets of one group, ready to inject into a run
+#[derive(Clone, Default)]
+pub struct GroupSecrets {
+    secrets: Vec<(String, String)>,
+}
+
+impl fmt::Debug for GroupSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroupSecrets").field("names", &self.names()).finish()
+    }
+}
+
+impl GroupSecrets {
+    pub fn new(secrets: Vec<(String, String)>) -> Self {
+        Self { secrets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.secrets.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Name and value pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.secrets.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Value of one secret
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter().find(|(n, _)| *n == name).map(|(_, value)| value)
+    }
+
+    /// Replace every secret value in `text`
+    pub fn redact(&self, text: &str) -> String {
+        let mut values: Vec<&str> = self
+            .secrets
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .filter(|value| value.len() >= MIN_REDACT_LEN)
+            .collect();
+        // Longer values first, so one containing another is fully replaced
+        values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+        values
+            .into_iter()
+            .fold(text.to_string(), |text, value| text.replace(value, REDACTED))
+    }
+
+    /// Redact the text fields of a run's output
+    pub fn redact_output(&self, output: &mut ContainerOutput) {
+        if self.is_empty() {
+            return;
+        }
+        for field in [&mut output.result, &mut output.error].into_iter().flatten() {
+            *field = self.redact(field);
+        }
+    }
+}
+
+/// Encrypted per-group secret store
+pub struct SecretStore {
+    dir: PathBuf,
+    cipher: Aes256Gcm,
+}
+
+impl SecretStore {
+    /// Open the default store, creating the key on first use
+    pub fn open() -> Result<Self> {
+        Ok(Self::with_key(secrets_dir(), &load_or_create_key()?))
+    }
+
+    /// Open a store in `dir` with an explicit key
+    pub fn with_key(dir: PathBuf, key: &[u8; 32]) -> Self {
+        Self {
+            dir,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    fn group_path(&self, group_folder: &str) -> Result<PathBuf> {
+        validate_group_folder(group_folder)?;
+        Ok(self.dir.join(format!("{}.json", group_folder)))
+    }
+
+    fn load(&self, group_folder: &str) -> Result<BTreeMap<String, StoredSecret>> {
+        let path = self.group_path(group_folder)?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => {
+                return Err(NuClawError::FileSystem {
+                    message: format!("Failed to read secrets for {}: {}", group_folder, e),
+                })
+            }
+        };
+        serde_json::from_str(&content).map_err(|e| NuClawError::Config {
+            message: format!("Invalid secrets file for {}: {}", group_folder, e),
+        })
+    }
+
+    fn save(&self, group_folder: &str, secrets: &BTreeMap<String, StoredSecret>) -> Result<()> {
+        let path = self.group_path(group_folder)?;
+        if secrets.is_empty() {
+            let _ = fs::remove_file(&path);
+            return Ok(());
+        }
+        let json = serde_json::to_string_pretty(secrets).map_err(|e| NuClawError::Config {
+            message: format!("Failed to serialize secrets: {}", e),
+        })?;
+        write_private(&path, json.as_bytes())
+    }
+
+    /// Encrypt and store a secret, replacing any earlier value
+    pub fn set(&self, group_folder: &str, name: &str, value: &str) -> Result<()> {
+        validate_secret_name(name)?;
+        let mut secrets = self.load(group_folder)?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = associated_data(group_folder, name);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: value.as_bytes(), aad: &aad })
+            .map_err(|_| NuClawError::Security {
+                message: format!("Failed to encrypt secret {}", name),
+            })?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let created_at = secrets
+            .get(name)
+            .map(|s| s.created_at.clone())
+            .unwrap_or_else(|| now.clone());
+        secrets.insert(
+            name.to_string(),
+            StoredSecret {
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+                created_at,
+                updated_at: now,
+            },
+        );
+        self.save(group_folder, &secrets)
+    }
+
+    /// Remove a secret; returns whether it existed
+    pub fn revoke(&self, group_folder: &str, name: &str) -> Result<bool> {
+        let mut secrets = self.load(group_folder)?;
+        if secrets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save(group_folder, &secrets)?;
+        Ok(true)
+    }
+
+    /// Secrets of one group, or of all groups when `group_folder` is `None`
+    pub fn list(&self, group_folder: Option<&str>) -> Result<Vec<SecretInfo>> {
+        let groups = match group_folder {
+            Some(group) => vec![group.to_string()],
+            None => self.groups()?,
+        };
+        let mut infos = Vec::new();
+        for group in groups {
+            for (name, secret) in self.load(&group)? {
+                infos.push(SecretInfo {
+                    group_folder: group.clone(),
+                    name,
+                    created_at: secret.created_at,
+                    updated_at: secret.updated_at,
+                });
+            }
+        }
+        Ok(infos)
+    }
+
+    /// Groups that have secrets
+    pub fn groups(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(NuClawError::FileSystem {
+                    message: format!("Failed to read secrets directory: {}", e),
+                })
+            }
+        };
+        let mut groups: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == "json").then(|| path.file_stem()?.to_str().map(str::to_string))?
+            })
+            .collect();
+        groups.sort();
+        Ok(groups)
+    }
+
+    /// Decrypt every secret of a group
+    pub fn group_secrets(&self, group_folder: &str) -> Result<GroupSecrets> {
+        let mut decrypted = Vec::new();
+        for (name, secret) in self.load(group_folder)? {
+            let decrypt_error = || NuClawError::Security {
+                message: format!("Failed to decrypt secret {} of group {}", name, group_folder),
+            };
+            let nonce = hex::decode(&secret.nonce).map_err(|_| decrypt_error())?;
+            let ciphertext = hex::decode(&secret.ciphertext).map_err(|_| decrypt_error())?;
+            if nonce.len() != 12 {
+                return Err(decrypt_error());
+            }
+            let aad = associated_data(group_folder, &name);
+            let plaintext = self
+                .cipher
+                .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad })
+                .map_err(|_| decrypt_error())?;
+            let value = String::from_utf8(plaintext).map_err(|_| decrypt_error())?;
+            decrypted.push((name, value));
+        }
+        Ok(GroupSecrets::new(decrypted))
+    }
+}
+
+/// Binds a ciphertext to its group and name so it cannot be moved to another
+fn associated_data(group_folder: &str, name: &str) -> Vec<u8> {
+    format!("{}\0{}", group_folder, name).into_bytes()
+}
+
+/// Write a file only the current user can read
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let to_fs_error = |e: std::io::Error| NuClawError::FileSystem {
+        message: format!("Failed to write {}: {}", path.display(), e),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(to_fs_error)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp).map_err(to_fs_error)?;
+    file.write_all(content).map_err(to_fs_error)?;
+    drop(file);
+    fs::rename(&tmp, path).map_err(to_fs_error)
+}
+
+/// Key from NUCLAW_SECRET_KEY, else from the key file, generating it if missing
+fn load_or_create_key() -> Result<[u8; 32]> {
+    if let Ok(hex_key) = std::env::var("NUCLAW_SECRET_KEY") {
+        return parse_key(&hex_key);
+    }
+    let path = secret_key_path();
+    match fs::read_to_string(&path) {
+        Ok(hex_key) => parse_key(&hex_key),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = Aes256Gcm::generate_key(OsRng);
+            write_private(&path, hex::encode(key).as_bytes())?;
+            tracing::info!("Generated secret key at {}", path.display());
+            Ok(key.into())
+        }
+        Err(e) => Err(NuClawError::FileSystem {
+            message: format!("Failed to read secret key: {}", e),
+        }),
+    }
+}
+
+fn parse_key(hex_key: &str) -> Result<[u8; 32]> {
+    hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| NuClawError::Config {
+            message: "Secret key must be 64 hex characters".to_string(),
+        })
+}
+
+/// Secrets to inject into a group's run, logging instead of failing
+///
+/// Groups without secrets never touch the key.
+pub fn group_secrets_for_run(group_folder: &str) -> GroupSecrets {
+    if validate_group_folder(group_folder).is_err()
+        || !secrets_dir().join(format!("{}.json", group_folder)).exists()
+    {
+        return GroupSecrets::default();
+    }
+    SecretStore::open()
+        .and_then(|store| store.group_secrets(group_folder))
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load secrets for {}: {}", group_folder, e);
+            GroupSecrets::default()
+        })
+}
+
+tokio::task_local! {
+    /// Secrets of the group whose API-mode tool calls are executing
+    static TOOL_SECRETS: GroupSecrets;
+}
+
+/// Run tool calls with a group's secrets available to them
+pub async fn with_tool_secrets<F: std::future::Future>(secrets: GroupSecrets, f: F) -> F::Output {
+    TOOL_SECRETS.scope(secrets, f).await
+}
+
+/// Secrets available to the tool call being executed
+pub fn current_tool_secrets() -> GroupSecrets {
+    TOOL_SECRETS.try_with(|secrets| secrets.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_store(dir: &TempDir) -> SecretStore {
+        SecretStore::with_key(dir.path().join("secrets"), &[7u8; 32])
+    }
+
+    #[test]
+    fn test_validate_secret_name() {
+        assert!(validate_secret_name("GITHUB_TOKEN").is_ok());
+        assert!(validate_secret_name("_private2").is_ok());
+        assert!(validate_secret_name("2FA").is_err());
+        assert!(validate_secret_name("MY-TOKEN").is_err());
+        assert!(validate_secret_name("").is_err());
+        assert!(validate_secret_name("PATH").is_err());
+    }
+
+    #[test]
+    fn test_set_list_and_revoke() {
+        let dir = TempDir::new().unwrap();
+        let store = test_store(&dir);
+
+        store.set("engineering", "GITHUB_TOKEN", "ghp_abc123").unwrap();
+        store.set("engineering", "NPM_TOKEN", "npm_xyz").unwrap();
+        store.set("sales", "CRM_KEY", "crm-key").unwrap();
+
+        let secrets = store.group_secrets("engineering").unwrap();
+        assert_eq!(secrets.get("GITHUB_TOKEN"), Some("ghp_abc123"));
+        assert_eq!(secrets.names(), vec!["GITHUB_TOKEN", "NPM_TOKEN"]);
+        assert_eq!(store.group_secrets("sales").unwrap().names(), vec!["CRM_KEY"]);
+        assert!(store.group_secrets("other").unwrap().is_empty());
+
+        let all = store.list(None).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(store.list(Some("sales")).unwrap()[0].name, "CRM_KEY");
+
+        assert!(store.revoke("engineering", "GITHUB_TOKEN").unwrap());
+        assert!(!store.revoke("engineering", "GITHUB_TOKEN").unwrap());
+        assert_eq!(store.group_secrets("engineering").unwrap().names(), vec!["NPM_TOKEN"]);
+    }
+
+    #[test]
+    fn test_values_are_encrypted_and_bound_to_group() {
+        let dir = TempDir::new().unwrap();
+        let store = test_store(&dir);
+        store.set("engineering", "GITHUB_TOKEN", "ghp_abc123").unwrap();
+
+        let path = dir.path().join("secrets/engineering.json");
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("ghp_abc123"));
+
+        // A file copied to another group does not decrypt
+        fs::copy(&path, dir.path().join("secrets/sales.json")).unwrap();
+        assert!(store.group_secrets("sales").is_err());
+
+        // Neither does it with another key
+        let other = SecretStore::with_key(dir.path().join("secrets"), &[8u8; 32]);
+        assert!(other.group_secrets("engineering").is_err());
+    }
+
+    #[test]
+    fn test_redact() {
+        let secrets = GroupSecrets::new(vec![
+            ("GITHUB_TOKEN".to_string(), "ghp_abc123".to_string()),
+            ("PIN".to_string(), "42".to_string()),
+        ]);
+        assert_eq!(
+            secrets.redact("token is ghp_abc123, pin 42"),
+            format!("token is {}, pin 42", REDACTED)
+        );
+        assert!(!format!("{:?}", secrets).contains("ghp_abc123"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_secrets_scope() {
+        assert!(current_tool_secrets().is_empty());
+        let secrets = GroupSecrets::new(vec![("API_KEY".to_string(), "value".to_string())]);
+        let names = with_tool_secrets(secrets, async { current_tool_secrets().names().len() }).await;
+        assert_eq!(names, 1);
+    }
+}
@@ -0,0 +1,296 @@
+//! Agent sessions - Resume the agent's conversation across container runs
+//!
+//! The session id an agent reports in `ContainerOutput::new_session_id` is
+//! stored per (group folder, chat) and passed back as
+//! `ContainerInput::session_id` on the chat's next message, so Claude Code
+//! resumes where it left off. Stored history is scoped to the session too.
+//!
+//! Sessions expire after `SESSION_IDLE_TTL_SECS` without use and can be reset
+//! from the chat with `/reset` (or `/new`).
+
+use crate::db::Database;
+use crate::error::{NuClawError, Result};
+use crate::types::ContainerOutput;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Default idle time before a session expires: 24 hours
+pub const DEFAULT_SESSION_IDLE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Reply sent after a chat resets its session
+pub const SESSION_RESET_REPLY: &str = "Started a new conversation.";
+
+/// Get the session idle TTL from environment or default; `0` disables expiry
+pub fn session_idle_ttl() -> Option<Duration> {
+    let secs = std::env::var("SESSION_IDLE_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SESSION_IDLE_TTL_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Whether a chat message asks to start a new session
+pub fn is_reset_command(content: &str) -> bool {
+    matches!(content.trim().to_lowercase().as_str(), "/reset" | "/new")
+}
+
+/// Stored session state for one chat of a group
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentSession {
+    pub group_folder: String,
+    pub chat_jid: String,
+    /// Agent session to resume; `None` right after a reset
+    pub session_id: Option<String>,
+    /// Timestamp of the message that started the session, in the channel's format
+    pub started_at: Option<String>,
+    /// RFC 3339 time of the last run that used the session
+    pub last_used_at: String,
+}
+
+impl AgentSession {
+    /// Whether the session has been idle longer than `ttl`
+    pub fn is_expired(&self, ttl: Option<Duration>, now: DateTime<Utc>) -> bool {
+        let Some(ttl) = ttl.and_then(|ttl| chrono::Duration::from_std(ttl).ok()) else {
+            return false;
+        };
+        match DateTime::parse_from_rfc3339(&self.last_used_at) {
+            Ok(last_used) => now - last_used.with_timezone(&Utc) > ttl,
+            Err(_) => true,
+        }
+    }
+}
+
+/// Load the session for a chat, dropping it if it has expired
+pub fn load_session(db: &Database, group_folder: &str, chat_jid: &str) -> Result<Option<AgentSession>> {
+    let conn = db.get_connection()?;
+    let session = conn
+        .query_row(
+            "SELECT session_id, started_at, last_used_at FROM agent_sessions
+             WHERE group_folder = ? AND chat_jid = ?",
+            [group_folder, chat_jid],
+            |row| {
+                Ok(AgentSession {
+                    group_folder: group_folder.to_string(),
+                    chat_jid: chat_jid.to_string(),
+                    session_id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    last_used_at: row.get(2)?,
+                })
+            },
+        )
+        .map(Some)
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            e => Err(NuClawError::Database {
+                message: format!("Failed to load session: {}", e),
+            }),
+        })?;
+
+    match session {
+        Some(session) if session.is_expired(session_idle_ttl(), Utc::now()) => {
+            tracing::info!("Session for {} in group {} expired", chat_jid, group_folder);
+            conn.execute(
+                "DELETE FROM agent_sessions WHERE group_folder = ? AND chat_jid = ?",
+                [group_folder, chat_jid],
+            )
+            .map_err(|e| NuClawError::Database {
+                message: format!("Failed to delete expired session: {}", e),
+            })?;
+            Ok(None)
+        }
+        session => Ok(session),
+    }
+}
+
+/// Load the session for a chat, logging instead of failing
+pub fn active_session(db: &Database, group_folder: &str, chat_jid: &str) -> Option<AgentSession> {
+    load_session(db, group_folder, chat_jid).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load session for {}: {}", chat_jid, e);
+        None
+    })
+}
+
+/// Store the session an agent run reported
+///
+/// `started_at` is the timestamp of the message being answered; it only
+/// becomes the session start when this is a new session.
+pub fn save_session(
+    db: &Database,
+    group_folder: &str,
+    chat_jid: &str,
+    session_id: &str,
+    started_at: &str,
+) -> Result<()> {
+    let conn = db.get_connection()?;
+    conn.execute(
+        "INSERT INTO agent_sessions (group_folder, chat_jid, session_id, started_at, last_used_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(group_folder, chat_jid) DO UPDATE SET
+            started_at = CASE
+                WHEN agent_sessions.session_id IS NULL OR agent_sessions.session_id = excluded.session_id
+                THEN COALESCE(agent_sessions.started_at, excluded.started_at)
+                ELSE excluded.started_at
+            END,
+            session_id = excluded.session_id,
+            last_used_at = excluded.last_used_at",
+        rusqlite::params![group_folder, chat_jid, session_id, started_at, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to save session: {}", e),
+    })?;
+    Ok(())
+}
+
+/// Remember the session reported by a successful run, logging failures
+pub fn record_session(
+    db: &Database,
+    group_folder: &str,
+    chat_jid: &str,
+    started_at: &str,
+    output: &ContainerOutput,
+) {
+    if output.status != "success" {
+        return;
+    }
+    let Some(session_id) = output.new_session_id.as_deref().filter(|id| !id.is_empty()) else {
+        return;
+    };
+    if let Err(e) = save_session(db, group_folder, chat_jid, session_id, started_at) {
+        tracing::warn!("Failed to save session for {}: {}", chat_jid, e);
+    }
+}
+
+/// Start a new session for a chat
+///
+/// The agent session is forgotten and history before `started_at` (the reset
+/// message's timestamp) is no longer sent to the agent.
+pub fn reset_session(db: &Database, group_folder: &str, chat_jid: &str, started_at: &str) -> Result<()> {
+    let conn = db.get_connection()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_sessions (group_folder, chat_jid, session_id, started_at, last_used_at)
+         VALUES (?, ?, NULL, ?, ?)",
+        rusqlite::params![group_folder, chat_jid, started_at, Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to reset session: {}", e),
+    })?;
+    tracing::info!("Session for {} in group {} reset", chat_jid, group_folder);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{remove_test_db, test_db};
+
+    fn output(session_id: Option<&str>) -> ContainerOutput {
+        ContainerOutput {
+            status: "success".to_string(),
+            result: Some("ok".to_string()),
+            new_session_id: session_id.map(str::to_string),
+            error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_is_reset_command() {
+        assert!(is_reset_command("/reset"));
+        assert!(is_reset_command(" /NEW "));
+        assert!(!is_reset_command("/resetting"));
+        assert!(!is_reset_command("please reset"));
+    }
+
+    #[test]
+    fn test_session_expiry() {
+        let now = Utc::now();
+        let session = AgentSession {
+            group_folder: "main".to_string(),
+            chat_jid: "telegram:1".to_string(),
+            session_id: Some("s".to_string()),
+            started_at: None,
+            last_used_at: (now - chrono::Duration::hours(2)).to_rfc3339(),
+        };
+        assert!(session.is_expired(Some(Duration::from_secs(3600)), now));
+        assert!(!session.is_expired(Some(Duration::from_secs(3 * 3600)), now));
+        assert!(!session.is_expired(None, now));
+    }
+
+    #[test]
+    fn test_record_and_resume_session() {
+        let (db, path) = test_db("test_session_resume.db");
+        assert!(load_session(&db, "main", "telegram:1").unwrap().is_none());
+
+        record_session(&db, "main", "telegram:1", "100", &output(Some("sess-a")));
+        record_session(&db, "main", "telegram:1", "200", &output(Some("sess-a")));
+        let session = load_session(&db, "main", "telegram:1").unwrap().unwrap();
+        assert_eq!(session.session_id.as_deref(), Some("sess-a"));
+        assert_eq!(session.started_at.as_deref(), Some("100"));
+
+        // A different session id starts a new session
+        record_session(&db, "main", "telegram:1", "300", &output(Some("sess-b")));
+        let session = load_session(&db, "main", "telegram:1").unwrap().unwrap();
+        assert_eq!(session.session_id.as_deref(), Some("sess-b"));
+        assert_eq!(session.started_at.as_deref(), Some("300"));
+
+        // Failed runs and runs without a session leave it untouched
+        record_session(&db, "main", "telegram:1", "400", &output(None));
+        let mut failed = output(Some("sess-c"));
+        failed.status = "error".to_string();
+        record_session(&db, "main", "telegram:1", "400", &failed);
+        let session = load_session(&db, "main", "telegram:1").unwrap().unwrap();
+        assert_eq!(session.session_id.as_deref(), Some("sess-b"));
+
+        // Sessions are per group and chat
+        assert!(load_session(&db, "main", "telegram:2").unwrap().is_none());
+        assert!(load_session(&db, "other", "telegram:1").unwrap().is_none());
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_reset_session() {
+        let (db, path) = test_db("test_session_reset.db");
+        record_session(&db, "main", "telegram:1", "100", &output(Some("sess-a")));
+        reset_session(&db, "main", "telegram:1", "500").unwrap();
+
+        let session = load_session(&db, "main", "telegram:1").unwrap().unwrap();
+        assert_eq!(session.session_id, None);
+        assert_eq!(session.started_at.as_deref(), Some("500"));
+
+        // The next session keeps the reset as its start
+        record_session(&db, "main", "telegram:1", "600", &output(Some("sess-new")));
+        let session = load_session(&db, "main", "telegram:1").unwrap().unwrap();
+        assert_eq!(session.session_id.as_deref(), Some("sess-new"));
+        assert_eq!(session.started_at.as_deref(), Some("500"));
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_expired_session_is_dropped() {
+        let (db, path) = test_db("test_session_expired.db");
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO agent_sessions (group_folder, chat_jid, session_id, started_at, last_used_at)
+             VALUES ('main', 'telegram:1', 'old', '1', '2000-01-01T00:00:00+00:00')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        assert!(load_session(&db, "main", "telegram:1").unwrap().is_none());
+        let remaining: i64 = db
+            .get_connection()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM agent_sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+
+        remove_test_db(&path);
+    }
+}
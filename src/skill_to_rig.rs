@@ -2,11 +2,14 @@
 //!
 //! Converts Skill to Rig-compatible Tool
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use async_trait::async_trait;
+use rig::tool::ToolDyn;
+use rig::wasm_compat::WasmBoxedFuture;
 
 use crate::skills::{Skill, SkillType};
 use crate::tool_registry::{Tool, ToolDefinition, ToolError, ToolResult};
+use crate::wasm_executor::WasmExecutor;
 
 /// Adapter to convert a Skill to a Rig-compatible Tool
 pub struct SkillAsTool {
@@ -39,10 +42,7 @@ impl SkillExecutor for DefaultSkillExecutor {
                     skill.name, args
                 )))
             }
-            SkillType::Wasm => {
-                // WASM skills would need Extism runtime
-                Ok(ToolResult::error("WASM execution not implemented yet"))
-            }
+            SkillType::Wasm => WasmExecutor::new().execute_skill(skill, args).await,
         }
     }
 }
@@ -120,6 +120,79 @@ pub fn all_skills_to_tools(skills: Vec<Arc<Skill>>) -> Vec<Arc<dyn Tool>> {
         .collect()
 }
 
+/// Names of tools invoked during a run, shared between rig adapters
+pub type ToolCallLog = Arc<Mutex<Vec<String>>>;
+
+/// Adapter exposing a registry Tool through rig's dynamic tool interface
+pub struct RigTool {
+    tool: Arc<dyn Tool>,
+    call_log: ToolCallLog,
+}
+
+impl RigTool {
+    /// Wrap a tool, recording each invocation in `call_log`
+    pub fn new(tool: Arc<dyn Tool>, call_log: ToolCallLog) -> Self {
+        Self { tool, call_log }
+    }
+}
+
+impl ToolDyn for RigTool {
+    fn name(&self) -> String {
+        self.tool.definition().name
+    }
+
+    fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, rig::completion::ToolDefinition> {
+        Box::pin(async move {
+            let def = self.tool.definition();
+            rig::completion::ToolDefinition {
+                parameters: def.input_schema(),
+                name: def.name,
+                description: def.description,
+            }
+        })
+    }
+
+    fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, rig::tool::ToolError>> {
+        Box::pin(async move {
+            let name = self.tool.definition().name;
+            if let Ok(mut log) = self.call_log.lock() {
+                log.push(name.clone());
+            }
+            tracing::info!("Executing tool call: {}", name);
+
+            let args: serde_json::Value = if args.trim().is_empty() {
+                serde_json::Value::Object(Default::default())
+            } else {
+                serde_json::from_str(&args).map_err(rig::tool::ToolError::JsonError)?
+            };
+
+            let result = self
+                .tool
+                .execute(args)
+                .await
+                .map_err(|e| rig::tool::ToolError::ToolCallError(Box::new(e)))?;
+
+            if result.success {
+                Ok(result.result.unwrap_or_default())
+            } else {
+                Err(rig::tool::ToolError::ToolCallError(Box::new(
+                    ToolError::ExecutionFailed(
+                        result.error.unwrap_or_else(|| "Tool execution failed".to_string()),
+                    ),
+                )))
+            }
+        })
+    }
+}
+
+/// Convert registry tools to boxed rig tools sharing one call log
+pub fn tools_to_rig(tools: Vec<Arc<dyn Tool>>, call_log: &ToolCallLog) -> Vec<Box<dyn ToolDyn>> {
+    tools
+        .into_iter()
+        .map(|t| Box::new(RigTool::new(t, call_log.clone())) as Box<dyn ToolDyn>)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,4 +281,40 @@ mod tests {
         let result = result.unwrap();
         assert!(result.success);
     }
+
+    #[tokio::test]
+    async fn test_rig_tool_definition() {
+        let skill = Arc::new(Skill::new("test-skill", "A test skill", "Content"));
+        let log = ToolCallLog::default();
+        let tool = RigTool::new(Arc::new(SkillAsTool::new(skill)), log);
+
+        assert_eq!(ToolDyn::name(&tool), "test-skill");
+        let def = ToolDyn::definition(&tool, String::new()).await;
+        assert_eq!(def.name, "test-skill");
+        assert_eq!(def.description, "A test skill");
+        assert_eq!(def.parameters["type"], "object");
+    }
+
+    #[tokio::test]
+    async fn test_rig_tool_call_records_log() {
+        let skill = Arc::new(Skill::new("test-skill", "A test skill", "Expected content"));
+        let log = ToolCallLog::default();
+        let tools = tools_to_rig(vec![Arc::new(SkillAsTool::new(skill)) as Arc<dyn Tool>], &log);
+
+        let output = tools[0].call("{}".to_string()).await.unwrap();
+        assert_eq!(output, "Expected content");
+        assert_eq!(*log.lock().unwrap(), vec!["test-skill".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rig_tool_call_error() {
+        let mut skill = Skill::new("wasm-skill", "A wasm skill", "");
+        skill.skill_type = SkillType::Wasm;
+        let log = ToolCallLog::default();
+        let tool = RigTool::new(Arc::new(SkillAsTool::new(Arc::new(skill))), log.clone());
+
+        let result = ToolDyn::call(&tool, "{}".to_string()).await;
+        assert!(result.is_err());
+        assert_eq!(log.lock().unwrap().len(), 1);
+    }
 }
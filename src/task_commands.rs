@@ -0,0 +1,536 @@
+//! Task commands - Manage scheduled tasks from a chat
+//!
+//! - `/tasks` lists the tasks the chat's group can manage
+//! - `/tasks pause|resume|cancel <id>` changes one of them
+//! - `/tasks runs <id>` shows its recent runs, including skipped ones
+//! - `/schedule <cron|interval|once> <value> <prompt>` schedules a prompt
+//!   whose result is posted to the chat
+//!
+//! Cron expressions contain spaces, so they are quoted:
+//! `/schedule cron "0 0 9 * * *" Summarize yesterday`. Intervals take
+//! milliseconds or a unit (`30s`, `15m`, `2h`, `1d`), one-off times an
+//! RFC 3339 timestamp or a local time. Schedules use the group's timezone
+//! unless a `tz=<IANA name>` follows the value; `misfire=<policy>` there sets
+//! how runs missed while NuClaw was down are handled, `retries=<n>` /
+//! `backoff=<interval>` how failed runs are retried, and
+//! `deliver=always|on_change|on_error` which results reach the chat. Task ids
+//! may be shortened to any unique prefix.
+//!
+//! Commands run within a `TaskScope`, so a group only sees its own tasks.
+
+use crate::db::Database;
+use crate::error::{NuClawError, Result};
+use crate::task_scheduler::{
+    format_duration, format_local_time, task_timezone, NewTask, TaskManager, TaskScope,
+};
+use crate::types::{ScheduledTask, TaskRunLog};
+use chrono_tz::Tz;
+
+const TASKS_USAGE: &str = "Usage: /tasks [pause|resume|cancel|runs <id>]";
+const SCHEDULE_USAGE: &str =
+    "Usage: /schedule <cron|interval|once> <value> [tz=<zone>] [misfire=<policy>] [retries=<n>] [backoff=<interval>] [deliver=<when>] <prompt>";
+
+/// Runs shown by `/tasks runs`
+const RUNS_SHOWN: usize = 10;
+
+/// Longest prompt excerpt shown in task listings
+const PROMPT_PREVIEW_CHARS: usize = 60;
+
+/// A parsed task command
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskCommand {
+    List,
+    Pause(String),
+    Resume(String),
+    Cancel(String),
+    Runs(String),
+    Schedule(ScheduleSpec),
+}
+
+/// Schedule and prompt of a task to create
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleSpec {
+    pub schedule_type: String,
+    /// Value as stored: intervals are normalized to milliseconds
+    pub schedule_value: String,
+    pub prompt: String,
+    /// IANA timezone given with `tz=`
+    pub timezone: Option<String>,
+    /// Misfire policy given with `misfire=`
+    pub misfire_policy: Option<String>,
+    /// Retry limit given with `retries=`
+    pub max_retries: Option<u32>,
+    /// First retry delay in milliseconds given with `backoff=`
+    pub retry_backoff_ms: Option<i64>,
+    /// Delivery policy given with `deliver=`
+    pub delivery: Option<String>,
+}
+
+/// Check whether a message is a task command
+pub fn is_task_command(content: &str) -> bool {
+    matches!(command_word(content).as_deref(), Some("/tasks" | "/schedule"))
+}
+
+/// First word of a message, lowercased and without a Telegram `@bot` suffix
+fn command_word(content: &str) -> Option<String> {
+    let word = content.split_whitespace().next()?;
+    let word = word.split('@').next().unwrap_or(word);
+    Some(word.to_lowercase())
+}
+
+/// Parse a message; `Ok(None)` when it is not a task command
+pub fn parse_task_command(content: &str) -> Result<Option<TaskCommand>> {
+    let content = content.trim();
+    let word = match command_word(content) {
+        Some(word) => word,
+        None => return Ok(None),
+    };
+    let rest = content
+        .split_once(char::is_whitespace)
+        .map(|(_, rest)| rest.trim())
+        .unwrap_or("");
+
+    match word.as_str() {
+        "/tasks" => {
+            let args: Vec<&str> = rest.split_whitespace().collect();
+            let command = match args.as_slice() {
+                [] | ["list"] => TaskCommand::List,
+                [action, id] => match action.to_lowercase().as_str() {
+                    "pause" => TaskCommand::Pause(id.to_string()),
+                    "resume" => TaskCommand::Resume(id.to_string()),
+                    "cancel" => TaskCommand::Cancel(id.to_string()),
+                    "runs" => TaskCommand::Runs(id.to_string()),
+                    _ => return Err(usage(TASKS_USAGE)),
+                },
+                _ => return Err(usage(TASKS_USAGE)),
+            };
+            Ok(Some(command))
+        }
+        "/schedule" => parse_schedule_spec(rest).map(|spec| Some(TaskCommand::Schedule(spec))),
+        _ => Ok(None),
+    }
+}
+
+fn usage(text: &str) -> NuClawError {
+    NuClawError::Validation {
+        message: text.to_string(),
+    }
+}
+
+/// Parse `<cron|interval|once> <value> [options] <prompt>`, where the value
+/// may be quoted and options are `tz=`, `misfire=`, `retries=`, `backoff=` and
+/// `deliver=`
+pub fn parse_schedule_spec(spec: &str) -> Result<ScheduleSpec> {
+    let spec = spec.trim();
+    let (schedule_type, rest) = spec
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| usage(SCHEDULE_USAGE))?;
+    let rest = rest.trim_start();
+
+    let (value, prompt) = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let end = rest[1..]
+                .find(quote)
+                .ok_or_else(|| usage(SCHEDULE_USAGE))?;
+            (&rest[1..end + 1], &rest[end + 2..])
+        }
+        _ => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+    };
+    let mut prompt = prompt.trim();
+    let mut timezone = None;
+    let mut misfire_policy = None;
+    let mut max_retries = None;
+    let mut retry_backoff_ms = None;
+    let mut delivery = None;
+    loop {
+        let (option, rest) = prompt.split_once(char::is_whitespace).unwrap_or((prompt, ""));
+        if let Some(zone) = option.strip_prefix("tz=") {
+            timezone = Some(zone.to_string());
+        } else if let Some(policy) = option.strip_prefix("misfire=") {
+            misfire_policy = Some(policy.to_string());
+        } else if let Some(retries) = option.strip_prefix("retries=") {
+            max_retries = Some(retries.parse().map_err(|_| NuClawError::Validation {
+                message: format!("Invalid retries '{}': use a whole number", retries),
+            })?);
+        } else if let Some(backoff) = option.strip_prefix("backoff=") {
+            retry_backoff_ms = Some(parse_interval_ms(backoff).ok_or_else(|| {
+                NuClawError::Validation {
+                    message: format!("Invalid backoff '{}': use e.g. 30s, 5m or 1h", backoff),
+                }
+            })?);
+        } else if let Some(when) = option.strip_prefix("deliver=") {
+            delivery = Some(when.to_string());
+        } else {
+            break;
+        }
+        prompt = rest.trim();
+    }
+    if value.trim().is_empty() || prompt.is_empty() {
+        return Err(usage(SCHEDULE_USAGE));
+    }
+
+    let schedule_type = schedule_type.to_lowercase();
+    let schedule_value = if schedule_type == "interval" {
+        parse_interval_ms(value)
+            .ok_or_else(|| NuClawError::Validation {
+                message: format!("Invalid interval '{}': use e.g. 90000, 30s, 15m, 2h or 1d", value),
+            })?
+            .to_string()
+    } else {
+        value.trim().to_string()
+    };
+
+    Ok(ScheduleSpec {
+        schedule_type,
+        schedule_value,
+        prompt: prompt.to_string(),
+        timezone,
+        misfire_policy,
+        max_retries,
+        retry_backoff_ms,
+        delivery,
+    })
+}
+
+/// Interval in milliseconds from a plain number or one with an s/m/h/d unit
+pub fn parse_interval_ms(value: &str) -> Option<i64> {
+    let value = value.trim().to_lowercase();
+    let (number, unit_ms) = match value.char_indices().last()? {
+        (i, 's') => (&value[..i], 1_000),
+        (i, 'm') => (&value[..i], 60_000),
+        (i, 'h') => (&value[..i], 3_600_000),
+        (i, 'd') => (&value[..i], 86_400_000),
+        _ => (value.as_str(), 1),
+    };
+    number
+        .parse::<i64>()
+        .ok()
+        .filter(|n| *n > 0)
+        .and_then(|n| n.checked_mul(unit_ms))
+}
+
+/// Run a task command from a chat and return the reply, or `None` if the
+/// message is not a task command
+pub fn handle_task_command(
+    db: &Database,
+    scope: &TaskScope,
+    chat_jid: &str,
+    content: &str,
+) -> Option<String> {
+    let reply = match parse_task_command(content) {
+        Ok(None) => return None,
+        Ok(Some(command)) => {
+            let manager = TaskManager::new(db, scope.clone());
+            run_task_command(&manager, scope, chat_jid, command)
+        }
+        Err(e) => Err(e),
+    };
+    Some(reply.unwrap_or_else(|e| format!("⚠️ {}", e)))
+}
+
+fn run_task_command(
+    manager: &TaskManager,
+    scope: &TaskScope,
+    chat_jid: &str,
+    command: TaskCommand,
+) -> Result<String> {
+    match command {
+        TaskCommand::List => {
+            let tasks = manager.list(false)?;
+            if tasks.is_empty() {
+                return Ok("No scheduled tasks.".to_string());
+            }
+            let lines: Vec<String> = tasks
+                .iter()
+                .map(|task| format_task(task, scope.is_main))
+                .collect();
+            Ok(format!("Scheduled tasks:\n{}", lines.join("\n")))
+        }
+        TaskCommand::Pause(id) => {
+            let task = manager.pause(&resolve_task_id(manager, &id)?)?;
+            Ok(format!("Paused {}.", task.id))
+        }
+        TaskCommand::Resume(id) => {
+            let task = manager.resume(&resolve_task_id(manager, &id)?)?;
+            Ok(format!("Resumed {}; next run {}.", task.id, next_run_display(&task)))
+        }
+        TaskCommand::Cancel(id) => {
+            let task = manager.cancel(&resolve_task_id(manager, &id)?)?;
+            Ok(format!("Cancelled {}.", task.id))
+        }
+        TaskCommand::Runs(id) => {
+            let task = manager.get(&resolve_task_id(manager, &id)?)?;
+            let runs = manager.runs(&task.id, RUNS_SHOWN)?;
+            if runs.is_empty() {
+                return Ok(format!("Task {} has not run yet.", task.id));
+            }
+            let tz = task_timezone(&task);
+            let lines: Vec<String> = runs.iter().map(|run| format_run(run, tz)).collect();
+            Ok(format!("Recent runs of {}:\n{}", task.id, lines.join("\n")))
+        }
+        TaskCommand::Schedule(spec) => {
+            let task = manager.create(NewTask {
+                prompt: spec.prompt,
+                schedule_type: spec.schedule_type,
+                schedule_value: spec.schedule_value,
+                group_folder: None,
+                chat_jid: chat_jid.to_string(),
+                context_mode: None,
+                timezone: spec.timezone,
+                misfire_policy: spec.misfire_policy,
+                max_retries: spec.max_retries,
+                retry_backoff_ms: spec.retry_backoff_ms,
+                delivery: spec.delivery,
+            })?;
+            Ok(format!("Scheduled {}; next run {}.", task.id, next_run_display(&task)))
+        }
+    }
+}
+
+/// Full id of the one task in scope whose id starts with `id`
+///
+/// The `task_` prefix may be left out.
+pub fn resolve_task_id(manager: &TaskManager, id: &str) -> Result<String> {
+    if let Ok(task) = manager.get(id) {
+        return Ok(task.id);
+    }
+    let prefixed = format!("task_{}", id);
+    let matches: Vec<String> = manager
+        .list(true)?
+        .into_iter()
+        .map(|task| task.id)
+        .filter(|task_id| task_id.starts_with(id) || task_id.starts_with(&prefixed))
+        .collect();
+    match matches.as_slice() {
+        [task_id] => Ok(task_id.clone()),
+        [] => Err(NuClawError::Validation {
+            message: format!("Task {} not found", id),
+        }),
+        _ => Err(NuClawError::Validation {
+            message: format!("Task id {} is ambiguous ({} matches)", id, matches.len()),
+        }),
+    }
+}
+
+/// Next run of a task in its timezone, or `-` when it has none
+pub fn next_run_display(task: &ScheduledTask) -> String {
+    match task.next_run.as_deref() {
+        Some(next_run) => format_local_time(next_run, task_timezone(task)),
+        None => "-".to_string(),
+    }
+}
+
+/// One run log entry: time, status, duration and outcome
+pub fn format_run(run: &TaskRunLog, tz: Tz) -> String {
+    let outcome = run
+        .error
+        .as_deref()
+        .filter(|e| !e.is_empty())
+        .or(run.result.as_deref())
+        .unwrap_or("");
+    let mut outcome: String = outcome.lines().next().unwrap_or("").chars().take(PROMPT_PREVIEW_CHARS).collect();
+    if !outcome.is_empty() {
+        outcome.insert_str(0, " · ");
+    }
+    format!(
+        "• {} [{}] {}{}",
+        format_local_time(&run.run_at, tz),
+        run.status,
+        format_duration(run.duration_ms),
+        outcome
+    )
+}
+
+/// One listing entry: id, status, schedule, next run and prompt excerpt
+pub fn format_task(task: &ScheduledTask, show_group: bool) -> String {
+    let group = if show_group {
+        format!(" ({})", task.group_folder)
+    } else {
+        String::new()
+    };
+    let mut prompt: String = task.prompt.chars().take(PROMPT_PREVIEW_CHARS).collect();
+    if task.prompt.chars().count() > PROMPT_PREVIEW_CHARS {
+        prompt.push('…');
+    }
+    let failures = if task.failure_count > 0 {
+        format!(" · {} failed", task.failure_count)
+    } else {
+        String::new()
+    };
+    format!(
+        "• {}{} [{}] {} {} · next {}{}\n  {}",
+        task.id,
+        group,
+        task.status,
+        task.schedule_type,
+        task.schedule_value,
+        next_run_display(task),
+        failures,
+        prompt
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{remove_test_db, test_db};
+
+    #[test]
+    fn test_is_task_command() {
+        assert!(is_task_command("/tasks"));
+        assert!(is_task_command(" /TASKS pause abc"));
+        assert!(is_task_command("/schedule@NuClawBot interval 1h hi"));
+        assert!(!is_task_command("/tasksfoo"));
+        assert!(!is_task_command("show my tasks"));
+        assert!(!is_task_command(""));
+    }
+
+    #[test]
+    fn test_parse_task_command() {
+        assert_eq!(parse_task_command("/tasks").unwrap(), Some(TaskCommand::List));
+        assert_eq!(
+            parse_task_command("/tasks cancel task_1").unwrap(),
+            Some(TaskCommand::Cancel("task_1".to_string()))
+        );
+        assert_eq!(
+            parse_task_command("/tasks runs task_1").unwrap(),
+            Some(TaskCommand::Runs("task_1".to_string()))
+        );
+        assert!(parse_task_command("/tasks delete task_1").is_err());
+        assert!(parse_task_command("/tasks pause").is_err());
+        assert_eq!(parse_task_command("hello").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_schedule_spec() {
+        let spec = parse_schedule_spec(r#"cron "0 0 9 * * *" Summarize yesterday"#).unwrap();
+        assert_eq!(spec.schedule_type, "cron");
+        assert_eq!(spec.schedule_value, "0 0 9 * * *");
+        assert_eq!(spec.prompt, "Summarize yesterday");
+
+        let spec = parse_schedule_spec("interval 15m Check the build").unwrap();
+        assert_eq!(spec.schedule_value, "900000");
+
+        let spec = parse_schedule_spec("once 2030-01-01T09:00:00Z Say hi").unwrap();
+        assert_eq!(spec.schedule_value, "2030-01-01T09:00:00Z");
+        assert_eq!(spec.timezone, None);
+
+        let spec = parse_schedule_spec(r#"cron "0 0 9 * * *" tz=Europe/Berlin Good morning"#).unwrap();
+        assert_eq!(spec.timezone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(spec.prompt, "Good morning");
+
+        let spec = parse_schedule_spec("interval 1h misfire=skip tz=Asia/Tokyo Ping").unwrap();
+        assert_eq!(spec.misfire_policy.as_deref(), Some("skip"));
+        assert_eq!(spec.timezone.as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(spec.prompt, "Ping");
+
+        let spec = parse_schedule_spec("interval 1h retries=5 backoff=2m Ping").unwrap();
+        assert_eq!(spec.max_retries, Some(5));
+        assert_eq!(spec.retry_backoff_ms, Some(120_000));
+        assert!(parse_schedule_spec("interval 1h retries=many Ping").is_err());
+        assert!(parse_schedule_spec("interval 1h backoff=later Ping").is_err());
+
+        let spec = parse_schedule_spec("interval 1h deliver=on_change Ping").unwrap();
+        assert_eq!(spec.delivery.as_deref(), Some("on_change"));
+        assert_eq!(spec.prompt, "Ping");
+
+        assert!(parse_schedule_spec("interval 15m").is_err());
+        assert!(parse_schedule_spec(r#"cron "0 0 9 * * * Unclosed"#).is_err());
+        assert!(parse_schedule_spec("interval soon Do it").is_err());
+    }
+
+    #[test]
+    fn test_parse_interval_ms() {
+        assert_eq!(parse_interval_ms("90000"), Some(90_000));
+        assert_eq!(parse_interval_ms("30s"), Some(30_000));
+        assert_eq!(parse_interval_ms("2H"), Some(7_200_000));
+        assert_eq!(parse_interval_ms("1d"), Some(86_400_000));
+        assert_eq!(parse_interval_ms("0m"), None);
+        assert_eq!(parse_interval_ms("m"), None);
+        assert_eq!(parse_interval_ms("1w"), None);
+    }
+
+    #[test]
+    fn test_handle_task_command_lifecycle() {
+        let (db, path) = test_db("test_task_commands.db");
+        let scope = TaskScope::new("family", false);
+        let chat = "telegram:group:-100";
+
+        assert_eq!(handle_task_command(&db, &scope, chat, "hello"), None);
+        assert_eq!(
+            handle_task_command(&db, &scope, chat, "/tasks").as_deref(),
+            Some("No scheduled tasks.")
+        );
+
+        let reply = handle_task_command(&db, &scope, chat, "/schedule interval 1h Ping").unwrap();
+        assert!(reply.starts_with("Scheduled task_"), "{}", reply);
+        let manager = TaskManager::new(&db, scope.clone());
+        let task = manager.list(false).unwrap().remove(0);
+        assert_eq!(task.chat_jid, chat);
+        assert_eq!(task.schedule_value, "3600000");
+
+        let short = &task.id["task_".len().."task_".len() + 8];
+        let reply = handle_task_command(&db, &scope, chat, &format!("/tasks pause {}", short)).unwrap();
+        assert_eq!(reply, format!("Paused {}.", task.id));
+
+        let other = TaskScope::new("work", false);
+        let reply = handle_task_command(&db, &other, chat, &format!("/tasks cancel {}", task.id)).unwrap();
+        assert!(reply.contains("not found"), "{}", reply);
+
+        let reply = handle_task_command(&db, &scope, chat, &format!("/tasks runs {}", short)).unwrap();
+        assert_eq!(reply, format!("Task {} has not run yet.", task.id));
+
+        let listing = handle_task_command(&db, &scope, chat, "/tasks").unwrap();
+        assert!(listing.contains("[paused]"), "{}", listing);
+        assert!(listing.contains("Ping"));
+
+        let reply = handle_task_command(&db, &scope, chat, "/schedule cron \"bad\" Nope").unwrap();
+        assert!(reply.starts_with("⚠️"), "{}", reply);
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_format_task() {
+        let task = ScheduledTask {
+            id: "task_1".to_string(),
+            group_folder: "family".to_string(),
+            chat_jid: "telegram:1".to_string(),
+            prompt: "x".repeat(70),
+            schedule_type: "cron".to_string(),
+            schedule_value: "0 0 9 * * *".to_string(),
+            context_mode: "isolated".to_string(),
+            next_run: None,
+            last_run: None,
+            last_result: None,
+            status: "active".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
+        };
+        let line = format_task(&task, true);
+        assert!(line.starts_with("• task_1 (family) [active] cron 0 0 9 * * * · next -"));
+        assert!(line.ends_with(&format!("{}…", "x".repeat(60))));
+        assert!(!format_task(&task, false).contains("(family)"));
+    }
+
+    #[test]
+    fn test_format_run() {
+        let run = TaskRunLog {
+            task_id: "task_1".to_string(),
+            run_at: "2026-01-01T07:30:00+00:00".to_string(),
+            duration_ms: 0,
+            status: "skipped".to_string(),
+            result: None,
+            error: Some("Missed scheduled run (skip)".to_string()),
+        };
+        assert_eq!(
+            format_run(&run, Tz::UTC),
+            "• 2026-01-01 07:30 UTC [skipped] 0ms · Missed scheduled run (skip)"
+        );
+    }
+}
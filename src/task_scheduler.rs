@@ -5,6 +5,10 @@
 //! - `interval`: Fixed interval in milliseconds (e.g., "3600000" for 1 hour)
 //! - `once`: Single execution at specific timestamp
 //!
+//! Schedules are evaluated in wall-clock time of the task's timezone: its own
+//! `timezone`, else its group's `timezone` in WORKFLOW.md, else `TZ`. Times are
+//! stored in UTC.
+//!
 //! Features:
 //! - Persistent task storage in SQLite
 //! - Task run logging
@@ -12,13 +16,18 @@
 //! - Graceful shutdown
 
 use crate::config::timezone;
-use crate::container_runner::{log_container_output, run_container};
+use crate::container_runner::log_container_output;
+use crate::runtime::{default_runtime, Runtime};
+use crate::session::{active_session, record_session};
 use crate::db::Database;
 use crate::error::{NuClawError, Result};
-use crate::types::{ContainerInput, ContainerOutput, ScheduledTask};
-use chrono::{DateTime, Utc};
+use crate::types::{ContainerInput, ContainerOutput, ScheduledTask, TaskRunLog};
+use crate::workflow::load_workflow_config;
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 use std::str::FromStr;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::time::{interval, Duration, MissedTickBehavior};
 
@@ -28,6 +37,18 @@ const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
 const MAX_CONCURRENT_TASKS: usize = 4;
 /// Default task timeout: 10 minutes
 const DEFAULT_TASK_TIMEOUT_SECS: u64 = 600;
+/// Default lateness before a scheduled run counts as missed: 5 minutes
+const DEFAULT_MISFIRE_GRACE_SECS: u64 = 300;
+/// Default number of missed runs `run_all` catches up on
+const DEFAULT_MISFIRE_MAX_RUNS: usize = 10;
+/// Most missed runs looked at when a task is picked up late
+const MISFIRE_SCAN_LIMIT: usize = 1000;
+/// Default retries after a failed run before a task is paused
+const DEFAULT_TASK_MAX_RETRIES: u32 = 3;
+/// Default delay before the first retry: 1 minute
+const DEFAULT_TASK_RETRY_BACKOFF_MS: i64 = 60_000;
+/// Longest delay between retries: 6 hours
+const MAX_TASK_RETRY_BACKOFF_MS: i64 = 6 * 60 * 60 * 1000;
 
 /// Get poll interval from environment or default
 pub fn poll_interval() -> Duration {
@@ -47,12 +68,48 @@ pub fn task_timeout() -> Duration {
     Duration::from_secs(timeout_secs)
 }
 
+/// Get how late a run may start before it counts as missed, from environment or default
+pub fn misfire_grace() -> Duration {
+    let secs = std::env::var("TASK_MISFIRE_GRACE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MISFIRE_GRACE_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Get how many missed runs `run_all` catches up on, from environment or default
+pub fn misfire_max_runs() -> usize {
+    std::env::var("TASK_MISFIRE_MAX_RUNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MISFIRE_MAX_RUNS)
+}
+
+/// Get the retries after a failed run before a task is paused, from environment or default
+pub fn task_max_retries() -> u32 {
+    std::env::var("TASK_MAX_RETRIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TASK_MAX_RETRIES)
+}
+
+/// Get the delay before a task's first retry, from environment or default
+pub fn task_retry_backoff_ms() -> i64 {
+    std::env::var("TASK_RETRY_BACKOFF_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|ms| *ms > 0)
+        .unwrap_or(DEFAULT_TASK_RETRY_BACKOFF_MS)
+}
+
 /// Task scheduler state
 #[derive(Clone)]
 pub struct TaskScheduler {
     db: Database,
     poll_interval: Duration,
     task_timeout: Duration,
+    runtime: Arc<dyn Runtime>,
 }
 
 impl TaskScheduler {
@@ -62,9 +119,16 @@ impl TaskScheduler {
             db,
             poll_interval: poll_interval(),
             task_timeout: task_timeout(),
+            runtime: default_runtime(),
         }
     }
 
+    /// Run tasks on this runtime instead of the configured one
+    pub fn with_runtime(mut self, runtime: Arc<dyn Runtime>) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
     /// Run the scheduler loop
     pub async fn run(&mut self) -> Result<()> {
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
@@ -99,7 +163,9 @@ impl TaskScheduler {
 
     /// Poll for due tasks and execute them
     async fn poll_and_execute_tasks(&mut self) -> Result<()> {
-        let now = Utc::now().to_rfc3339();
+        let now_at = Utc::now();
+        let now = now_at.to_rfc3339();
+        let grace = chrono::Duration::from_std(misfire_grace()).unwrap_or_else(|_| chrono::Duration::zero());
 
         // Load active tasks that are due
         let tasks = self.load_due_tasks(&now).await?;
@@ -120,9 +186,39 @@ impl TaskScheduler {
                 let _ = tokio::join!(handles.remove(0));
             }
 
-            let mut scheduler = TaskScheduler::new(self.db.clone());
+            // Runs missed while nothing was polling are handled by the task's policy
+            let plan = plan_misfire(&task, now_at, grace);
+            if !plan.skipped.is_empty() {
+                tracing::info!(
+                    "Task {} missed {} run(s), skipping them ({})",
+                    task.id,
+                    plan.skipped.len(),
+                    MisfirePolicy::for_task(&task)
+                );
+                if let Err(e) = self.log_skipped_runs(&task, &plan.skipped).await {
+                    tracing::error!("Failed to log skipped runs of task {}: {}", task.id, e);
+                }
+            }
+            if plan.runs == 0 {
+                if let Err(e) = self.skip_to_next_run(&task).await {
+                    tracing::error!("Failed to reschedule task {}: {}", task.id, e);
+                }
+                continue;
+            }
+
+            let mut scheduler = self.clone();
             let handle = tokio::spawn(async move {
-                let result = scheduler.execute_single_task(&task).await;
+                let mut result = Ok(());
+                for _ in 0..plan.runs {
+                    // A failed run has scheduled its retry; catching up stops there
+                    match scheduler.execute_single_task(&task).await {
+                        Ok(true) => {}
+                        other => {
+                            result = other.map(|_| ());
+                            break;
+                        }
+                    }
+                }
                 (task.id.clone(), result)
             });
             handles.push(handle);
@@ -142,8 +238,8 @@ impl TaskScheduler {
         Ok(())
     }
 
-    /// Execute a single task
-    async fn execute_single_task(&mut self, task: &ScheduledTask) -> Result<()> {
+    /// Execute a single task; returns whether it ran and succeeded
+    async fn execute_single_task(&mut self, task: &ScheduledTask) -> Result<bool> {
         tracing::info!("Executing task: {} (group: {})", task.id, task.group_folder);
 
         let start_time = chrono::Utc::now();
@@ -158,23 +254,30 @@ impl TaskScheduler {
 
         if current_task.status != "active" {
             tracing::info!("Task {} is no longer active, skipping", task.id);
-            return Ok(());
+            return Ok(false);
         }
 
-        // Create container input
+        // Group-context tasks continue the chat's agent session; isolated ones start fresh
         let session_id = format!("scheduled_{}", task.id);
+        let chat_session = if task.context_mode == "group" {
+            active_session(&self.db, &task.group_folder, &task.chat_jid)
+        } else {
+            None
+        };
         let input = ContainerInput {
             prompt: task.prompt.clone(),
-            session_id: Some(session_id.clone()),
+            session_id: chat_session.as_ref().and_then(|s| s.session_id.clone()),
             group_folder: task.group_folder.clone(),
             chat_jid: task.chat_jid.clone(),
             is_main: false,
             is_scheduled_task: true,
             session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
         };
 
         // Execute container with timeout
-        let result = tokio::time::timeout(self.task_timeout, run_container(input)).await;
+        let result = tokio::time::timeout(self.task_timeout, self.runtime.run(input)).await;
 
         let end_time = chrono::Utc::now();
         let duration_ms = (end_time - start_time).num_milliseconds();
@@ -182,14 +285,43 @@ impl TaskScheduler {
         // Process result and log
         match result {
             Ok(Ok(output)) => {
+                crate::usage::record_output_usage(&self.db, &task.group_folder, &task.chat_jid, "scheduler", &output);
+                if let Some(session) = &chat_session {
+                    let started_at = session.started_at.as_deref().unwrap_or_default();
+                    record_session(&self.db, &task.group_folder, &task.chat_jid, started_at, &output);
+                }
+
+                // Log to file
+                let _ = log_container_output(&task.group_folder, &session_id, &output);
+
+                // The agent ran but reported an error, OOM kill or limit hit
+                if output.status != "success" {
+                    self.log_task_run(task, &output, duration_ms, &output.status)
+                        .await?;
+                    // The agent's error is the outcome the delivery policy judges
+                    self.handle_failure(&current_task, &failed_run_error(&output)).await?;
+                    return Ok(false);
+                }
+
                 // Log successful execution
                 self.log_task_run(task, &output, duration_ms, "success")
                     .await?;
 
-                // Log to file
-                let _ = log_container_output(&task.group_folder, &session_id, &output);
+                if current_task.failure_count > 0 {
+                    self.reset_failures(&task.id).await?;
+                }
+
+                if let Some(text) = output.result.as_deref().filter(|t| !t.trim().is_empty()) {
+                    if DeliveryPolicy::for_task(&current_task).should_deliver(
+                        false,
+                        text,
+                        current_task.last_result.as_deref(),
+                    ) {
+                        self.deliver(task, text).await;
+                    }
+                }
 
-                // Calculate next run time
+                // Calculate next run time; a retried task returns to its regular schedule
                 if task.schedule_type == "once" {
                     // Single execution task - mark as completed
                     self.mark_task_completed(&task.id).await?;
@@ -207,10 +339,16 @@ impl TaskScheduler {
                     result: None,
                     new_session_id: None,
                     error: Some(e.to_string()),
+                    tools_used: Vec::new(),
+                    provider: None,
+                    model: None,
+                    usage: None,
+                    artifacts: Vec::new(),
                 };
                 self.log_task_run(task, &output, duration_ms, "error")
                     .await?;
-                self.mark_task_failed(&task.id).await?;
+                self.handle_failure(&current_task, &e.to_string()).await?;
+                return Ok(false);
             }
             Err(_) => {
                 // Timeout
@@ -219,33 +357,39 @@ impl TaskScheduler {
                     result: None,
                     new_session_id: None,
                     error: Some("Task execution timed out".to_string()),
+                    tools_used: Vec::new(),
+                    provider: None,
+                    model: None,
+                    usage: None,
+                    artifacts: Vec::new(),
                 };
                 self.log_task_run(task, &output, duration_ms, "timeout")
                     .await?;
-                self.mark_task_failed(&task.id).await?;
+                self.handle_failure(&current_task, "Task execution timed out")
+                    .await?;
+                return Ok(false);
             }
         }
 
-        Ok(())
+        Ok(true)
     }
 
     /// Calculate next run time for a task
     pub fn calculate_next_run(&self, task: &ScheduledTask) -> Option<String> {
+        let tz = task_timezone(task);
         match task.schedule_type.as_str() {
-            "cron" => self.calculate_next_cron_run(task.schedule_value.clone()),
-            "interval" => self.calculate_next_interval_run(task.schedule_value.clone()),
+            "cron" => self.calculate_next_cron_run(task.schedule_value.clone(), tz),
+            "interval" => self.calculate_next_interval_run(task.schedule_value.clone(), tz),
             "once" => None,
             _ => None,
         }
     }
 
     /// Calculate next run time from cron expression
-    fn calculate_next_cron_run(&self, cron_expr: String) -> Option<String> {
-        let _tz = timezone();
+    fn calculate_next_cron_run(&self, cron_expr: String, tz: Tz) -> Option<String> {
         match Schedule::from_str(&cron_expr) {
             Ok(schedule) => {
-                // Get next run in the specified timezone
-                let next = schedule.after(&chrono::Utc::now()).next()?;
+                let next = next_cron_run(&schedule, tz, chrono::Utc::now())?;
                 Some(next.to_rfc3339())
             }
             Err(e) => {
@@ -256,9 +400,9 @@ impl TaskScheduler {
     }
 
     /// Calculate next run time from interval
-    fn calculate_next_interval_run(&self, interval_str: String) -> Option<String> {
+    fn calculate_next_interval_run(&self, interval_str: String, tz: Tz) -> Option<String> {
         let millis: i64 = interval_str.parse().ok()?;
-        let next_run = chrono::Utc::now() + chrono::Duration::milliseconds(millis);
+        let next_run = next_interval_run(millis, tz, chrono::Utc::now());
         Some(next_run.to_rfc3339())
     }
 
@@ -274,7 +418,8 @@ impl TaskScheduler {
         let mut stmt = conn
             .prepare(
                 "SELECT id, group_folder, chat_jid, prompt, schedule_type, schedule_value,
-                    next_run, last_run, last_result, status, created_at, context_mode
+                    next_run, last_run, last_result, status, created_at, context_mode, timezone,
+                    misfire_policy, max_retries, retry_backoff_ms, failure_count, delivery
              FROM scheduled_tasks
              WHERE status = 'active'
                AND (next_run IS NULL OR next_run <= ?)
@@ -285,22 +430,7 @@ impl TaskScheduler {
             })?;
 
         let tasks: rusqlite::Result<Vec<ScheduledTask>> = stmt
-            .query_map([now], |row| {
-                Ok(ScheduledTask {
-                    id: row.get(0)?,
-                    group_folder: row.get(1)?,
-                    chat_jid: row.get(2)?,
-                    prompt: row.get(3)?,
-                    schedule_type: row.get(4)?,
-                    schedule_value: row.get(5)?,
-                    next_run: row.get(6)?,
-                    last_run: row.get(7)?,
-                    last_result: row.get(8)?,
-                    status: row.get(9)?,
-                    created_at: row.get(10)?,
-                    context_mode: row.get(11)?,
-                })
-            })?
+            .query_map([now], task_from_row)?
             .collect();
 
         tasks.map_err(|e| NuClawError::Database {
@@ -310,49 +440,7 @@ impl TaskScheduler {
 
     /// Load a single task by ID
     async fn load_task(&self, task_id: &str) -> Result<Option<ScheduledTask>> {
-        let conn = self
-            .db
-            .get_connection()
-            .map_err(|e| NuClawError::Database {
-                message: e.to_string(),
-            })?;
-
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, group_folder, chat_jid, prompt, schedule_type, schedule_value,
-                    next_run, last_run, last_result, status, created_at, context_mode
-             FROM scheduled_tasks WHERE id = ?",
-            )
-            .map_err(|e| NuClawError::Database {
-                message: format!("Failed to prepare statement: {}", e),
-            })?;
-
-        stmt.query_row([task_id], |row| {
-            Ok(ScheduledTask {
-                id: row.get(0)?,
-                group_folder: row.get(1)?,
-                chat_jid: row.get(2)?,
-                prompt: row.get(3)?,
-                schedule_type: row.get(4)?,
-                schedule_value: row.get(5)?,
-                next_run: row.get(6)?,
-                last_run: row.get(7)?,
-                last_result: row.get(8)?,
-                status: row.get(9)?,
-                created_at: row.get(10)?,
-                context_mode: row.get(11)?,
-            })
-        })
-        .map(Some)
-        .or_else(|e| {
-            if e == rusqlite::Error::QueryReturnedNoRows {
-                Ok(None)
-            } else {
-                Err(NuClawError::Database {
-                    message: format!("Failed to load task: {}", e),
-                })
-            }
-        })
+        get_task(&self.db, task_id)
     }
 
     /// Log a task run
@@ -406,6 +494,41 @@ impl TaskScheduler {
         Ok(())
     }
 
+    /// Record runs that were dropped under the task's misfire policy
+    async fn log_skipped_runs(&self, task: &ScheduledTask, slots: &[DateTime<Utc>]) -> Result<()> {
+        let conn = self.db.get_connection()?;
+        let reason = format!("Missed scheduled run ({})", MisfirePolicy::for_task(task));
+        for slot in slots {
+            conn.execute(
+                "INSERT INTO task_run_logs (task_id, run_at, duration_ms, status, result, error)
+                 VALUES (?, ?, 0, ?, NULL, ?)",
+                rusqlite::params![task.id, slot.to_rfc3339(), RUN_STATUS_SKIPPED, reason],
+            )
+            .map_err(|e| NuClawError::Database {
+                message: format!("Failed to log skipped run: {}", e),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Move a task past its missed runs without running it
+    async fn skip_to_next_run(&self, task: &ScheduledTask) -> Result<()> {
+        match self.calculate_next_run(task) {
+            Some(next_run) => self.update_next_run(&task.id, &next_run).await,
+            None => {
+                let conn = self.db.get_connection()?;
+                conn.execute(
+                    "UPDATE scheduled_tasks SET status = ?, next_run = NULL WHERE id = ?",
+                    rusqlite::params![RUN_STATUS_SKIPPED, task.id],
+                )
+                .map_err(|e| NuClawError::Database {
+                    message: format!("Failed to skip task: {}", e),
+                })?;
+                Ok(())
+            }
+        }
+    }
+
     /// Update next run time for a task
     async fn update_next_run(&self, task_id: &str, next_run: &str) -> Result<()> {
         let conn = self
@@ -446,23 +569,111 @@ impl TaskScheduler {
         Ok(())
     }
 
-    /// Mark a task as failed
-    async fn mark_task_failed(&self, task_id: &str) -> Result<()> {
-        let conn = self
-            .db
-            .get_connection()
-            .map_err(|e| NuClawError::Database {
-                message: e.to_string(),
-            })?;
+    /// Post a message to the task's chat through the channel that owns it
+    async fn deliver(&self, task: &ScheduledTask, text: &str) {
+        if let Err(e) = crate::channels::send_to_jid(&self.db, &task.chat_jid, text).await {
+            tracing::warn!(
+                "Failed to deliver task {} to {}: {}",
+                task.id,
+                task.chat_jid,
+                e
+            );
+        }
+    }
+
+    /// Schedule a retry after a failed run, or pause the task once it failed
+    /// more often in a row than it may retry
+    ///
+    /// A pause is always reported to the chat; a retry only when the task's
+    /// delivery policy covers the failure.
+    async fn handle_failure(&self, task: &ScheduledTask, error: &str) -> Result<()> {
+        let (action, notice) = self.record_failure(task, error, Utc::now())?;
+        let notify = match action {
+            FailureAction::Pause => true,
+            FailureAction::Retry { .. } => DeliveryPolicy::for_task(task).should_deliver(
+                true,
+                error,
+                task.last_result.as_deref(),
+            ),
+        };
+        if notify {
+            self.deliver(task, &notice).await;
+        }
+        Ok(())
+    }
+
+    /// Count a failed run and apply the resulting action; returns it with the
+    /// notice for the chat
+    fn record_failure(
+        &self,
+        task: &ScheduledTask,
+        error: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(FailureAction, String)> {
+        let failures = task.failure_count + 1;
+        let regular_next = self
+            .calculate_next_run(task)
+            .and_then(|next| DateTime::parse_from_rfc3339(&next).ok())
+            .map(|next| next.with_timezone(&Utc));
+        let conn = self.db.get_connection()?;
+
+        let action = plan_failure(task, failures, now, regular_next);
+        let notice = match &action {
+            FailureAction::Retry { at } => {
+                tracing::warn!(
+                    "Task {} failed ({} in a row), retrying at {}: {}",
+                    task.id,
+                    failures,
+                    at.to_rfc3339(),
+                    error
+                );
+                conn.execute(
+                    "UPDATE scheduled_tasks SET failure_count = ?, next_run = ? WHERE id = ?",
+                    rusqlite::params![failures, at.to_rfc3339(), task.id],
+                )
+                .map_err(|e| NuClawError::Database {
+                    message: format!("Failed to schedule retry: {}", e),
+                })?;
+                format!(
+                    "⚠️ Scheduled task {} failed: {}\nRetrying at {}.",
+                    task.id,
+                    error,
+                    format_local_time(&at.to_rfc3339(), task_timezone(task))
+                )
+            }
+            FailureAction::Pause => {
+                tracing::warn!(
+                    "Task {} failed {} times in a row, pausing it: {}",
+                    task.id,
+                    failures,
+                    error
+                );
+                conn.execute(
+                    "UPDATE scheduled_tasks SET failure_count = ?, status = ? WHERE id = ?",
+                    rusqlite::params![failures, STATUS_PAUSED, task.id],
+                )
+                .map_err(|e| NuClawError::Database {
+                    message: format!("Failed to pause task: {}", e),
+                })?;
+                format!(
+                    "⚠️ Scheduled task {} failed {} times in a row and was paused.\nLast error: {}\nSend /tasks resume {} to start it again.",
+                    task.id, failures, error, task.id
+                )
+            }
+        };
+        Ok((action, notice))
+    }
 
+    /// Clear a task's failure count after a successful run
+    async fn reset_failures(&self, task_id: &str) -> Result<()> {
+        let conn = self.db.get_connection()?;
         conn.execute(
-            "UPDATE scheduled_tasks SET status = 'failed' WHERE id = ?",
+            "UPDATE scheduled_tasks SET failure_count = 0 WHERE id = ?",
             [task_id],
         )
         .map_err(|e| NuClawError::Database {
-            message: format!("Failed to mark task failed: {}", e),
+            message: format!("Failed to reset task failures: {}", e),
         })?;
-
         Ok(())
     }
 }
@@ -509,6 +720,721 @@ pub fn is_valid_schedule_type(schedule_type: &str) -> bool {
     matches!(schedule_type, "cron" | "interval" | "once")
 }
 
+/// First run time for a new task, validating its schedule
+///
+/// `once` takes an RFC 3339 timestamp, or a local date and time
+/// (`2026-03-01T09:00`) read in `tz`.
+pub fn initial_next_run(schedule_type: &str, schedule_value: &str, tz: Tz) -> Result<Option<String>> {
+    let now = Utc::now();
+    match schedule_type {
+        "cron" => next_cron_run(&parse_cron_expression(schedule_value)?, tz, now)
+            .map(|at| Some(at.to_rfc3339()))
+            .ok_or_else(|| NuClawError::Scheduler {
+                message: format!("Cron expression '{}' never fires again", schedule_value),
+            }),
+        "interval" => match schedule_value.parse::<i64>() {
+            Ok(millis) if millis > 0 => Ok(Some(next_interval_run(millis, tz, now).to_rfc3339())),
+            _ => Err(NuClawError::Scheduler {
+                message: format!("Invalid interval '{}': expected milliseconds", schedule_value),
+            }),
+        },
+        "once" => parse_once_time(schedule_value, tz).map(|at| Some(at.to_rfc3339())),
+        other => Err(NuClawError::Scheduler {
+            message: format!("Invalid schedule type '{}'", other),
+        }),
+    }
+}
+
+/// Time of a `once` task: an RFC 3339 timestamp or a local time in `tz`
+fn parse_once_time(value: &str, tz: Tz) -> Result<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|wall| resolve_wall_time(tz, wall, DateTime::<Utc>::MIN_UTC))
+        .ok_or_else(|| NuClawError::Scheduler {
+            message: format!("Invalid timestamp '{}': expected e.g. 2026-03-01T09:00", value),
+        })
+}
+
+/// Parse an IANA timezone name such as `Europe/Berlin`
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.trim().parse::<Tz>().map_err(|_| NuClawError::Validation {
+        message: format!("Unknown timezone '{}': expected an IANA name such as Europe/Berlin", name),
+    })
+}
+
+/// Timezone from `TZ`, or UTC if it is not an IANA name
+pub fn default_timezone() -> Tz {
+    let name = timezone();
+    parse_timezone(name.trim_start_matches(':')).unwrap_or_else(|_| {
+        tracing::warn!("Unknown timezone TZ={}, scheduling in UTC", name);
+        Tz::UTC
+    })
+}
+
+/// Timezone of a group's tasks: its WORKFLOW.md `timezone`, then `TZ`
+pub fn group_timezone(group_folder: &str) -> Tz {
+    match load_workflow_config().timezone_for_group(group_folder) {
+        Some(name) => parse_timezone(&name).unwrap_or_else(|e| {
+            tracing::warn!("Group {}: {}", group_folder, e);
+            default_timezone()
+        }),
+        None => default_timezone(),
+    }
+}
+
+/// Timezone a task's schedule is evaluated in: its own, then its group's
+pub fn task_timezone(task: &ScheduledTask) -> Tz {
+    task.timezone
+        .as_deref()
+        .and_then(|name| parse_timezone(name).ok())
+        .unwrap_or_else(|| group_timezone(&task.group_folder))
+}
+
+/// Next time after `after` that a cron schedule fires, reading its fields as
+/// wall-clock time in `tz`
+///
+/// When clocks go back, a repeated time fires once, at its first occurrence.
+/// When they go forward, a skipped time fires shifted by the gap, so a 02:30
+/// job runs at 03:30 that day.
+pub fn next_cron_run(schedule: &Schedule, tz: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    // Iterate in naive wall-clock time, where every time of day exists exactly once
+    let wall = Utc.from_utc_datetime(&after.with_timezone(&tz).naive_local());
+    schedule
+        .after(&wall)
+        .find_map(|candidate| resolve_wall_time(tz, candidate.naive_utc(), after))
+}
+
+/// Next run of an interval schedule; whole days keep the wall-clock time in `tz`
+pub fn next_interval_run(millis: i64, tz: Tz, from: DateTime<Utc>) -> DateTime<Utc> {
+    const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+    if millis > 0 && millis % DAY_MS == 0 {
+        let wall = from.with_timezone(&tz).naive_local() + chrono::Duration::days(millis / DAY_MS);
+        if let Some(at) = resolve_wall_time(tz, wall, from) {
+            return at;
+        }
+    }
+    from + chrono::Duration::milliseconds(millis)
+}
+
+/// First instant after `after` at which `tz` shows the wall-clock time `wall`
+fn resolve_wall_time(tz: Tz, wall: NaiveDateTime, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let instants = match tz.from_local_datetime(&wall) {
+        LocalResult::Single(at) => vec![at],
+        LocalResult::Ambiguous(first, second) => vec![first, second],
+        LocalResult::None => {
+            // Skipped by a forward transition: apply the offset in effect before it
+            let offset = tz
+                .offset_from_utc_datetime(&(wall - chrono::Duration::days(1)))
+                .fix();
+            let at = wall - chrono::Duration::seconds(offset.local_minus_utc() as i64);
+            return Some(Utc.from_utc_datetime(&at)).filter(|at| *at > after);
+        }
+    };
+    instants
+        .into_iter()
+        .map(|at| at.with_timezone(&Utc))
+        .find(|at| *at > after)
+}
+
+/// Status of a run dropped by a misfire policy (and of a one-off task never run)
+pub const RUN_STATUS_SKIPPED: &str = "skipped";
+
+/// What to do about scheduled runs that passed while the scheduler was down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MisfirePolicy {
+    /// Drop missed runs and wait for the next scheduled time
+    Skip,
+    /// Run once for all missed runs (the default)
+    RunOnce,
+    /// Run once per missed run, at most this many times
+    RunAll { max_runs: usize },
+}
+
+impl MisfirePolicy {
+    /// Parse `skip`, `run_once`, `run_all` or `run_all:<max runs>`
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim().to_lowercase().replace('-', "_");
+        match value.as_str() {
+            "skip" | "skip_to_next" => Ok(Self::Skip),
+            "run_once" => Ok(Self::RunOnce),
+            "run_all" => Ok(Self::RunAll {
+                max_runs: misfire_max_runs(),
+            }),
+            other => other
+                .strip_prefix("run_all:")
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .map(|max_runs| Self::RunAll { max_runs })
+                .ok_or_else(|| NuClawError::Validation {
+                    message: format!(
+                        "Invalid misfire policy '{}': expected skip, run_once, run_all or run_all:<n>",
+                        value
+                    ),
+                }),
+        }
+    }
+
+    /// Policy of a task; unset or unreadable values mean `run_once`
+    pub fn for_task(task: &ScheduledTask) -> Self {
+        task.misfire_policy
+            .as_deref()
+            .and_then(|value| Self::parse(value).ok())
+            .unwrap_or(Self::RunOnce)
+    }
+}
+
+impl std::fmt::Display for MisfirePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Skip => write!(f, "skip"),
+            Self::RunOnce => write!(f, "run_once"),
+            Self::RunAll { max_runs } => write!(f, "run_all:{}", max_runs),
+        }
+    }
+}
+
+/// How a due task is handled: runs dropped and runs to make now
+#[derive(Debug, Clone, PartialEq)]
+pub struct MisfirePlan {
+    /// Scheduled times that will not run, oldest first
+    pub skipped: Vec<DateTime<Utc>>,
+    /// Number of times to run the task now
+    pub runs: usize,
+}
+
+/// Scheduled times of a task from its `next_run` up to `now`, oldest first
+fn due_slots(task: &ScheduledTask, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let first = match task.next_run.as_deref().map(DateTime::parse_from_rfc3339) {
+        Some(Ok(at)) => at.with_timezone(&Utc),
+        _ => return Vec::new(),
+    };
+    if first > now {
+        return Vec::new();
+    }
+
+    let tz = task_timezone(task);
+    let schedule = match task.schedule_type.as_str() {
+        "cron" => Schedule::from_str(&task.schedule_value).ok(),
+        _ => None,
+    };
+    let interval = match task.schedule_type.as_str() {
+        "interval" => task.schedule_value.parse::<i64>().ok().filter(|millis| *millis > 0),
+        _ => None,
+    };
+    let next = |after| match (&schedule, interval) {
+        (Some(schedule), _) => next_cron_run(schedule, tz, after),
+        (None, Some(millis)) => Some(next_interval_run(millis, tz, after)),
+        (None, None) => None,
+    };
+
+    let mut slots = vec![first];
+    while slots.len() < MISFIRE_SCAN_LIMIT {
+        match next(slots[slots.len() - 1]) {
+            Some(at) if at <= now => slots.push(at),
+            _ => break,
+        }
+    }
+    slots
+}
+
+/// Decide how to handle a due task under its misfire policy
+///
+/// Scheduled times more than `grace` before `now` count as missed. A run that
+/// is merely late always happens; missed ones are dropped (`skip`), folded into
+/// a single run (`run_once`), or each run up to the cap, keeping the most
+/// recent (`run_all`).
+pub fn plan_misfire(task: &ScheduledTask, now: DateTime<Utc>, grace: chrono::Duration) -> MisfirePlan {
+    let slots = due_slots(task, now);
+    if slots.is_empty() {
+        // Due without a valid next_run: run it as before
+        return MisfirePlan { skipped: Vec::new(), runs: 1 };
+    }
+
+    let missed = slots.iter().filter(|at| **at < now - grace).count();
+    let on_time = missed < slots.len();
+    // Missed runs plus, if any run is merely late, one run for it
+    let candidates = missed + usize::from(on_time);
+    let runs = match MisfirePolicy::for_task(task) {
+        MisfirePolicy::Skip => usize::from(on_time),
+        MisfirePolicy::RunOnce => 1,
+        MisfirePolicy::RunAll { max_runs } => candidates.min(max_runs),
+    };
+    MisfirePlan {
+        skipped: slots[..candidates - runs].to_vec(),
+        runs,
+    }
+}
+
+/// What happens to a task after a failed run
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureAction {
+    /// Run again at this time
+    Retry { at: DateTime<Utc> },
+    /// Failed too often in a row: pause until resumed
+    Pause,
+}
+
+/// Delay before retry number `attempt` (starting at 1): doubles each time, capped
+pub fn retry_backoff(base_ms: i64, attempt: u32) -> chrono::Duration {
+    let factor = 1i64
+        .checked_shl(attempt.saturating_sub(1).min(30))
+        .unwrap_or(i64::MAX);
+    chrono::Duration::milliseconds(
+        base_ms
+            .saturating_mul(factor)
+            .min(MAX_TASK_RETRY_BACKOFF_MS),
+    )
+}
+
+/// Decide what follows a task's `failures`-th failed run in a row
+///
+/// A retry never waits past the task's next regular run.
+pub fn plan_failure(
+    task: &ScheduledTask,
+    failures: u32,
+    now: DateTime<Utc>,
+    regular_next: Option<DateTime<Utc>>,
+) -> FailureAction {
+    if failures > task.max_retries.unwrap_or_else(task_max_retries) {
+        return FailureAction::Pause;
+    }
+    let base_ms = task.retry_backoff_ms.unwrap_or_else(task_retry_backoff_ms);
+    let retry_at = now + retry_backoff(base_ms, failures);
+    FailureAction::Retry {
+        at: regular_next.map_or(retry_at, |next| next.min(retry_at)),
+    }
+}
+
+/// Which outcomes of a task's runs are posted to its chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryPolicy {
+    /// Every result and failure (the default)
+    Always,
+    /// Only outcomes that differ from the previous run's
+    OnChange,
+    /// Only failures
+    OnError,
+}
+
+impl DeliveryPolicy {
+    /// Parse `always`, `on_change` or `on_error`
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "always" => Ok(Self::Always),
+            "on_change" | "change" => Ok(Self::OnChange),
+            "on_error" | "error" => Ok(Self::OnError),
+            _ => Err(NuClawError::Validation {
+                message: format!(
+                    "Invalid delivery '{}': expected always, on_change or on_error",
+                    value
+                ),
+            }),
+        }
+    }
+
+    /// Policy of a task; unset or unreadable values mean `always`
+    pub fn for_task(task: &ScheduledTask) -> Self {
+        task.delivery
+            .as_deref()
+            .and_then(|value| Self::parse(value).ok())
+            .unwrap_or(Self::Always)
+    }
+
+    /// Whether a run's outcome goes to the chat, given the previous run's
+    ///
+    /// `outcome` is the result of a successful run or the error of a failed one.
+    pub fn should_deliver(&self, failed: bool, outcome: &str, previous: Option<&str>) -> bool {
+        match self {
+            Self::Always => true,
+            Self::OnChange => previous.map(str::trim) != Some(outcome.trim()),
+            Self::OnError => failed,
+        }
+    }
+}
+
+impl std::fmt::Display for DeliveryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Always => write!(f, "always"),
+            Self::OnChange => write!(f, "on_change"),
+            Self::OnError => write!(f, "on_error"),
+        }
+    }
+}
+
+/// Error of a run whose output is not `success`, as stored in `last_result`
+fn failed_run_error(output: &ContainerOutput) -> String {
+    output
+        .error
+        .clone()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| format!("Agent run ended with status {}", output.status))
+}
+
+/// Show an RFC 3339 time as wall-clock time in `tz`, e.g. `2026-03-01 09:00 CET`
+pub fn format_local_time(rfc3339: &str, tz: Tz) -> String {
+    match DateTime::parse_from_rfc3339(rfc3339) {
+        Ok(at) => at.with_timezone(&tz).format("%Y-%m-%d %H:%M %Z").to_string(),
+        Err(_) => rfc3339.to_string(),
+    }
+}
+
+fn task_from_row(row: &rusqlite::Row) -> rusqlite::Result<ScheduledTask> {
+    Ok(ScheduledTask {
+        id: row.get(0)?,
+        group_folder: row.get(1)?,
+        chat_jid: row.get(2)?,
+        prompt: row.get(3)?,
+        schedule_type: row.get(4)?,
+        schedule_value: row.get(5)?,
+        next_run: row.get(6)?,
+        last_run: row.get(7)?,
+        last_result: row.get(8)?,
+        status: row.get(9)?,
+        created_at: row.get(10)?,
+        context_mode: row.get(11)?,
+        timezone: row.get(12)?,
+        misfire_policy: row.get(13)?,
+        max_retries: row.get(14)?,
+        retry_backoff_ms: row.get(15)?,
+        failure_count: row.get(16)?,
+        delivery: row.get(17)?,
+    })
+}
+
+/// Insert a new scheduled task
+pub fn create_task(db: &Database, task: &ScheduledTask) -> Result<()> {
+    let conn = db.get_connection()?;
+    conn.execute(
+        "INSERT INTO scheduled_tasks (id, group_folder, chat_jid, prompt, schedule_type,
+            schedule_value, next_run, last_run, last_result, status, created_at, context_mode, timezone,
+            misfire_policy, max_retries, retry_backoff_ms, failure_count, delivery)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            task.id,
+            task.group_folder,
+            task.chat_jid,
+            task.prompt,
+            task.schedule_type,
+            task.schedule_value,
+            task.next_run,
+            task.last_run,
+            task.last_result,
+            task.status,
+            task.created_at,
+            task.context_mode,
+            task.timezone,
+            task.misfire_policy,
+            task.max_retries,
+            task.retry_backoff_ms,
+            task.failure_count,
+            task.delivery,
+        ],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to create task: {}", e),
+    })?;
+    Ok(())
+}
+
+/// Load a task by ID
+pub fn get_task(db: &Database, task_id: &str) -> Result<Option<ScheduledTask>> {
+    let conn = db.get_connection()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, group_folder, chat_jid, prompt, schedule_type, schedule_value,
+                next_run, last_run, last_result, status, created_at, context_mode, timezone,
+                misfire_policy, max_retries, retry_backoff_ms, failure_count, delivery
+             FROM scheduled_tasks WHERE id = ?",
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to prepare statement: {}", e),
+        })?;
+
+    stmt.query_row([task_id], task_from_row)
+        .map(Some)
+        .or_else(|e| {
+            if e == rusqlite::Error::QueryReturnedNoRows {
+                Ok(None)
+            } else {
+                Err(NuClawError::Database {
+                    message: format!("Failed to load task: {}", e),
+                })
+            }
+        })
+}
+
+/// Cancel a task so it never runs again; returns false if it does not exist
+pub fn cancel_task(db: &Database, task_id: &str) -> Result<bool> {
+    let conn = db.get_connection()?;
+    let updated = conn
+        .execute(
+            "UPDATE scheduled_tasks SET status = 'cancelled', next_run = NULL WHERE id = ?",
+            [task_id],
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to cancel task: {}", e),
+        })?;
+    Ok(updated > 0)
+}
+
+/// Status of a task the scheduler skips until it is resumed
+pub const STATUS_PAUSED: &str = "paused";
+
+/// Which tasks a caller may manage
+///
+/// The main group manages every task; other groups only their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskScope {
+    pub group_folder: String,
+    pub is_main: bool,
+}
+
+impl TaskScope {
+    pub fn new(group_folder: impl Into<String>, is_main: bool) -> Self {
+        Self {
+            group_folder: group_folder.into(),
+            is_main,
+        }
+    }
+
+    /// Scope of the host operator (CLI), which may manage every task
+    pub fn admin() -> Self {
+        Self::new("main", true)
+    }
+
+    pub fn allows(&self, group_folder: &str) -> bool {
+        self.is_main || self.group_folder == group_folder
+    }
+}
+
+/// What is needed to create a task
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewTask {
+    pub prompt: String,
+    pub schedule_type: String,
+    pub schedule_value: String,
+    /// Defaults to the scope's group
+    pub group_folder: Option<String>,
+    pub chat_jid: String,
+    /// `isolated` (default) or `group`
+    pub context_mode: Option<String>,
+    /// IANA timezone; defaults to the group's
+    pub timezone: Option<String>,
+    /// `skip`, `run_once` (default), `run_all` or `run_all:<n>`
+    pub misfire_policy: Option<String>,
+    /// Retries after a failed run before pausing; defaults to `TASK_MAX_RETRIES`
+    pub max_retries: Option<u32>,
+    /// Delay before the first retry; defaults to `TASK_RETRY_BACKOFF_MS`
+    pub retry_backoff_ms: Option<i64>,
+    /// `always` (default), `on_change` or `on_error`
+    pub delivery: Option<String>,
+}
+
+/// Create, list, pause, resume and cancel tasks within a scope
+pub struct TaskManager<'a> {
+    db: &'a Database,
+    scope: TaskScope,
+}
+
+impl<'a> TaskManager<'a> {
+    pub fn new(db: &'a Database, scope: TaskScope) -> Self {
+        Self { db, scope }
+    }
+
+    /// Validate and store a new task
+    pub fn create(&self, new_task: NewTask) -> Result<ScheduledTask> {
+        if new_task.prompt.trim().is_empty() {
+            return Err(NuClawError::Validation {
+                message: "Task prompt is empty".to_string(),
+            });
+        }
+        if !is_valid_schedule_type(&new_task.schedule_type) {
+            return Err(NuClawError::Validation {
+                message: format!(
+                    "Invalid schedule type '{}': expected cron, interval or once",
+                    new_task.schedule_type
+                ),
+            });
+        }
+        if new_task.schedule_type == "cron" {
+            parse_cron_expression(&new_task.schedule_value)?;
+        }
+        let context_mode = new_task.context_mode.unwrap_or_else(|| "isolated".to_string());
+        if !matches!(context_mode.as_str(), "isolated" | "group") {
+            return Err(NuClawError::Validation {
+                message: format!("Invalid context_mode '{}'", context_mode),
+            });
+        }
+        let group_folder = new_task
+            .group_folder
+            .unwrap_or_else(|| self.scope.group_folder.clone());
+        if !self.scope.allows(&group_folder) {
+            return Err(NuClawError::Security {
+                message: format!("Group {} may not schedule tasks for {}", self.scope.group_folder, group_folder),
+            });
+        }
+        let timezone = new_task.timezone.as_deref().map(parse_timezone).transpose()?;
+        let tz = timezone.unwrap_or_else(|| group_timezone(&group_folder));
+        let misfire_policy = new_task
+            .misfire_policy
+            .as_deref()
+            .map(MisfirePolicy::parse)
+            .transpose()?;
+        let delivery = new_task
+            .delivery
+            .as_deref()
+            .map(DeliveryPolicy::parse)
+            .transpose()?;
+        if new_task.retry_backoff_ms.is_some_and(|ms| ms <= 0) {
+            return Err(NuClawError::Validation {
+                message: "Retry backoff must be positive".to_string(),
+            });
+        }
+
+        let task = ScheduledTask {
+            id: format!("task_{}", uuid::Uuid::new_v4()),
+            next_run: initial_next_run(&new_task.schedule_type, &new_task.schedule_value, tz)?,
+            group_folder,
+            chat_jid: new_task.chat_jid,
+            prompt: new_task.prompt,
+            schedule_type: new_task.schedule_type,
+            schedule_value: new_task.schedule_value,
+            context_mode,
+            last_run: None,
+            last_result: None,
+            status: "active".to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            timezone: timezone.map(|tz| tz.name().to_string()),
+            misfire_policy: misfire_policy.map(|policy| policy.to_string()),
+            max_retries: new_task.max_retries,
+            retry_backoff_ms: new_task.retry_backoff_ms,
+            failure_count: 0,
+            delivery: delivery.map(|policy| policy.to_string()),
+        };
+        create_task(self.db, &task)?;
+        Ok(task)
+    }
+
+    /// Tasks in scope, next to run first; finished ones only with `include_finished`
+    pub fn list(&self, include_finished: bool) -> Result<Vec<ScheduledTask>> {
+        let conn = self.db.get_connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, group_folder, chat_jid, prompt, schedule_type, schedule_value,
+                    next_run, last_run, last_result, status, created_at, context_mode, timezone,
+                    misfire_policy, max_retries, retry_backoff_ms, failure_count, delivery
+                 FROM scheduled_tasks
+                 WHERE (?1 OR group_folder = ?2)
+                   AND (?3 OR status IN ('active', 'paused'))
+                 ORDER BY next_run IS NULL, next_run ASC, created_at ASC",
+            )
+            .map_err(|e| NuClawError::Database {
+                message: format!("Failed to prepare statement: {}", e),
+            })?;
+        let tasks: rusqlite::Result<Vec<ScheduledTask>> = stmt
+            .query_map(
+                rusqlite::params![self.scope.is_main, self.scope.group_folder, include_finished],
+                task_from_row,
+            )?
+            .collect();
+        tasks.map_err(|e| NuClawError::Database {
+            message: format!("Failed to list tasks: {}", e),
+        })
+    }
+
+    /// A task in scope; tasks of other groups are reported as missing
+    pub fn get(&self, task_id: &str) -> Result<ScheduledTask> {
+        get_task(self.db, task_id)?
+            .filter(|task| self.scope.allows(&task.group_folder))
+            .ok_or_else(|| NuClawError::Validation {
+                message: format!("Task {} not found", task_id),
+            })
+    }
+
+    /// Stop an active task from running until it is resumed
+    pub fn pause(&self, task_id: &str) -> Result<ScheduledTask> {
+        let task = self.get(task_id)?;
+        if task.status != "active" {
+            return Err(NuClawError::Validation {
+                message: format!("Task {} is {}, not active", task.id, task.status),
+            });
+        }
+        self.set_status(&task.id, STATUS_PAUSED, task.next_run.as_deref())?;
+        self.get(&task.id)
+    }
+
+    /// Let a paused task run again, from the next scheduled time after now
+    pub fn resume(&self, task_id: &str) -> Result<ScheduledTask> {
+        let task = self.get(task_id)?;
+        if task.status != STATUS_PAUSED {
+            return Err(NuClawError::Validation {
+                message: format!("Task {} is {}, not paused", task.id, task.status),
+            });
+        }
+        let next_run = match task.schedule_type.as_str() {
+            // A missed one-off time runs right away
+            "once" => task.next_run.clone(),
+            _ => initial_next_run(&task.schedule_type, &task.schedule_value, task_timezone(&task))?,
+        };
+        self.set_status(&task.id, "active", next_run.as_deref())?;
+        self.get(&task.id)
+    }
+
+    /// Cancel a task so it never runs again
+    pub fn cancel(&self, task_id: &str) -> Result<ScheduledTask> {
+        let task = self.get(task_id)?;
+        cancel_task(self.db, &task.id)?;
+        self.get(&task.id)
+    }
+
+    /// Most recent runs of a task in scope, newest first, including skipped ones
+    pub fn runs(&self, task_id: &str, limit: usize) -> Result<Vec<TaskRunLog>> {
+        let task = self.get(task_id)?;
+        let conn = self.db.get_connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT task_id, run_at, duration_ms, status, result, error
+                 FROM task_run_logs WHERE task_id = ? ORDER BY run_at DESC, id DESC LIMIT ?",
+            )
+            .map_err(|e| NuClawError::Database {
+                message: format!("Failed to prepare statement: {}", e),
+            })?;
+        let runs: rusqlite::Result<Vec<TaskRunLog>> = stmt
+            .query_map(rusqlite::params![task.id, limit as i64], |row| {
+                Ok(TaskRunLog {
+                    task_id: row.get(0)?,
+                    run_at: row.get(1)?,
+                    duration_ms: row.get(2)?,
+                    status: row.get(3)?,
+                    result: row.get(4)?,
+                    error: row.get(5)?,
+                })
+            })?
+            .collect();
+        runs.map_err(|e| NuClawError::Database {
+            message: format!("Failed to load task runs: {}", e),
+        })
+    }
+
+    /// Set status and next run; failures counted so far are forgotten
+    fn set_status(&self, task_id: &str, status: &str, next_run: Option<&str>) -> Result<()> {
+        let conn = self.db.get_connection()?;
+        conn.execute(
+            "UPDATE scheduled_tasks SET status = ?, next_run = ?, failure_count = 0 WHERE id = ?",
+            rusqlite::params![status, next_run, task_id],
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to update task: {}", e),
+        })?;
+        Ok(())
+    }
+}
+
 /// Format duration for logging
 pub fn format_duration(duration_ms: i64) -> String {
     if duration_ms < 1000 {
@@ -523,6 +1449,7 @@ pub fn format_duration(duration_ms: i64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::{remove_test_db, test_db};
 
     fn setup_test_dirs() {
         use crate::config::store_dir;
@@ -571,7 +1498,7 @@ mod tests {
     fn test_calculate_interval_next_run() {
         setup_test_dirs();
         let scheduler = TaskScheduler::new(Database::new().unwrap());
-        let next = scheduler.calculate_next_interval_run("3600000".to_string());
+        let next = scheduler.calculate_next_interval_run("3600000".to_string(), Tz::UTC);
         assert!(next.is_some());
         // Should be approximately 1 hour from now
         let next_time: DateTime<Utc> = DateTime::from_str(&next.unwrap()).unwrap();
@@ -585,7 +1512,7 @@ mod tests {
     fn test_calculate_interval_next_run_invalid() {
         setup_test_dirs();
         let scheduler = TaskScheduler::new(Database::new().unwrap());
-        let next = scheduler.calculate_next_interval_run("not_a_number".to_string());
+        let next = scheduler.calculate_next_interval_run("not_a_number".to_string(), Tz::UTC);
         assert!(next.is_none());
     }
 
@@ -593,7 +1520,7 @@ mod tests {
     fn test_calculate_interval_next_run_zero() {
         setup_test_dirs();
         let scheduler = TaskScheduler::new(Database::new().unwrap());
-        let next = scheduler.calculate_next_interval_run("0".to_string());
+        let next = scheduler.calculate_next_interval_run("0".to_string(), Tz::UTC);
         assert!(next.is_some());
         // Should be essentially now
         let next_time: DateTime<Utc> = DateTime::from_str(&next.unwrap()).unwrap();
@@ -619,6 +1546,12 @@ mod tests {
             status: "active".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         let next = scheduler.calculate_next_run(&task);
         assert!(next.is_some());
@@ -641,6 +1574,12 @@ mod tests {
             status: "active".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         let next = scheduler.calculate_next_run(&task);
         assert!(next.is_none());
@@ -663,6 +1602,12 @@ mod tests {
             status: "active".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         let next = scheduler.calculate_next_run(&task);
         assert!(next.is_none());
@@ -762,6 +1707,12 @@ mod tests {
             status: "active".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         let now = chrono::Utc::now().to_rfc3339();
         assert!(is_task_due(&task, &now));
@@ -784,6 +1735,12 @@ mod tests {
             status: "active".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         let now_str = now.to_rfc3339();
         assert!(is_task_due(&task, &now_str));
@@ -806,6 +1763,12 @@ mod tests {
             status: "active".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         let now_str = now.to_rfc3339();
         assert!(!is_task_due(&task, &now_str));
@@ -827,6 +1790,12 @@ mod tests {
             status: "paused".to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             context_mode: "isolated".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         assert!(!is_task_due(&task, &now));
     }
@@ -864,4 +1833,516 @@ mod tests {
         assert_eq!(format_duration(60000), "1m");
         assert_eq!(format_duration(120000), "2m");
     }
+
+    #[test]
+    fn test_initial_next_run() {
+        assert!(initial_next_run("cron", "0 0 9 * * *", Tz::UTC).unwrap().is_some());
+        assert!(initial_next_run("interval", "60000", Tz::UTC).unwrap().is_some());
+        assert_eq!(
+            initial_next_run("once", "2030-01-01T09:00:00+08:00", Tz::UTC).unwrap(),
+            Some("2030-01-01T01:00:00+00:00".to_string())
+        );
+        assert!(initial_next_run("interval", "0", Tz::UTC).is_err());
+        assert!(initial_next_run("cron", "not a cron", Tz::UTC).is_err());
+        assert!(initial_next_run("weekly", "1", Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_create_get_and_cancel_task() {
+        let (db, path) = test_db("test_scheduler_tasks.db");
+
+        let task = ScheduledTask {
+            id: "task-crud".to_string(),
+            group_folder: "main".to_string(),
+            chat_jid: "telegram:1".to_string(),
+            prompt: "Remind me".to_string(),
+            schedule_type: "interval".to_string(),
+            schedule_value: "60000".to_string(),
+            context_mode: "isolated".to_string(),
+            next_run: initial_next_run("interval", "60000", Tz::UTC).unwrap(),
+            last_run: None,
+            last_result: None,
+            status: "active".to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
+        };
+        create_task(&db, &task).unwrap();
+        assert_eq!(get_task(&db, "task-crud").unwrap().unwrap().prompt, "Remind me");
+
+        assert!(cancel_task(&db, "task-crud").unwrap());
+        let cancelled = get_task(&db, "task-crud").unwrap().unwrap();
+        assert_eq!(cancelled.status, "cancelled");
+        assert!(cancelled.next_run.is_none());
+        assert!(!cancel_task(&db, "missing").unwrap());
+        assert!(get_task(&db, "missing").unwrap().is_none());
+
+        remove_test_db(&path);
+    }
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_next_cron_run_in_timezone() {
+        let schedule = parse_cron_expression("0 0 9 * * *").unwrap();
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        assert_eq!(
+            next_cron_run(&schedule, berlin, utc("2026-01-10T00:00:00Z")),
+            Some(utc("2026-01-10T08:00:00Z"))
+        );
+        assert_eq!(
+            next_cron_run(&schedule, berlin, utc("2026-07-10T00:00:00Z")),
+            Some(utc("2026-07-10T07:00:00Z"))
+        );
+        assert_eq!(
+            next_cron_run(&schedule, Tz::UTC, utc("2026-07-10T00:00:00Z")),
+            Some(utc("2026-07-10T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_cron_run_across_dst() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        // 02:30 does not exist on 2026-03-08; the run moves to 03:30 EDT
+        let skipped = parse_cron_expression("0 30 2 * * *").unwrap();
+        let first = next_cron_run(&skipped, new_york, utc("2026-03-08T05:00:00Z")).unwrap();
+        assert_eq!(first, utc("2026-03-08T07:30:00Z"));
+        assert_eq!(
+            next_cron_run(&skipped, new_york, first),
+            Some(utc("2026-03-09T06:30:00Z"))
+        );
+
+        // 01:30 happens twice on 2026-11-01; the run fires only the first time
+        let repeated = parse_cron_expression("0 30 1 * * *").unwrap();
+        let first = next_cron_run(&repeated, new_york, utc("2026-11-01T04:00:00Z")).unwrap();
+        assert_eq!(first, utc("2026-11-01T05:30:00Z"));
+        assert_eq!(
+            next_cron_run(&repeated, new_york, first),
+            Some(utc("2026-11-02T06:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_interval_run_keeps_wall_clock_for_days() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        let from = utc("2026-03-07T14:00:00Z");
+        assert_eq!(next_interval_run(86_400_000, new_york, from), utc("2026-03-08T13:00:00Z"));
+        assert_eq!(next_interval_run(3_600_000, new_york, from), utc("2026-03-07T15:00:00Z"));
+        assert_eq!(next_interval_run(86_400_000, Tz::UTC, from), utc("2026-03-08T14:00:00Z"));
+    }
+
+    #[test]
+    fn test_timezones() {
+        assert!(parse_timezone("Asia/Shanghai").is_ok());
+        assert!(parse_timezone("Mars/Olympus").is_err());
+
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        assert_eq!(
+            initial_next_run("once", "2030-03-01T09:00", berlin).unwrap().as_deref(),
+            Some("2030-03-01T08:00:00+00:00")
+        );
+        assert!(initial_next_run("once", "tomorrow", berlin).is_err());
+        assert_eq!(
+            format_local_time("2026-01-10T08:00:00+00:00", berlin),
+            "2026-01-10 09:00 CET"
+        );
+        assert_eq!(format_local_time("not a time", berlin), "not a time");
+    }
+
+    fn interval_task(next_run: &str, misfire_policy: Option<&str>) -> ScheduledTask {
+        ScheduledTask {
+            id: "task_misfire".to_string(),
+            group_folder: "main".to_string(),
+            chat_jid: "telegram:1".to_string(),
+            prompt: "Hourly check".to_string(),
+            schedule_type: "interval".to_string(),
+            schedule_value: "3600000".to_string(),
+            context_mode: "isolated".to_string(),
+            next_run: Some(next_run.to_string()),
+            last_run: None,
+            last_result: None,
+            status: "active".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            timezone: Some("UTC".to_string()),
+            misfire_policy: misfire_policy.map(str::to_string),
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
+        }
+    }
+
+    #[test]
+    fn test_misfire_policy_parse() {
+        assert_eq!(MisfirePolicy::parse("skip").unwrap(), MisfirePolicy::Skip);
+        assert_eq!(MisfirePolicy::parse("skip-to-next").unwrap(), MisfirePolicy::Skip);
+        assert_eq!(MisfirePolicy::parse("RUN_ONCE").unwrap(), MisfirePolicy::RunOnce);
+        assert_eq!(
+            MisfirePolicy::parse("run_all:3").unwrap(),
+            MisfirePolicy::RunAll { max_runs: 3 }
+        );
+        assert!(matches!(MisfirePolicy::parse("run_all").unwrap(), MisfirePolicy::RunAll { .. }));
+        assert!(MisfirePolicy::parse("run_all:0").is_err());
+        assert!(MisfirePolicy::parse("later").is_err());
+        assert_eq!(MisfirePolicy::RunAll { max_runs: 3 }.to_string(), "run_all:3");
+
+        let task = interval_task("2026-01-01T00:00:00Z", None);
+        assert_eq!(MisfirePolicy::for_task(&task), MisfirePolicy::RunOnce);
+    }
+
+    #[test]
+    fn test_plan_misfire() {
+        let now = utc("2026-01-01T12:00:00Z");
+        let grace = chrono::Duration::minutes(5);
+
+        // Due at 07:30, 08:30, ..., 11:30: five runs missed
+        let run_once = plan_misfire(&interval_task("2026-01-01T07:30:00Z", None), now, grace);
+        assert_eq!(run_once.runs, 1);
+        assert_eq!(run_once.skipped.len(), 4);
+        assert_eq!(run_once.skipped[0], utc("2026-01-01T07:30:00Z"));
+
+        let skip = plan_misfire(&interval_task("2026-01-01T07:30:00Z", Some("skip")), now, grace);
+        assert_eq!(skip.runs, 0);
+        assert_eq!(skip.skipped.len(), 5);
+
+        let run_all = plan_misfire(&interval_task("2026-01-01T07:30:00Z", Some("run_all:3")), now, grace);
+        assert_eq!(run_all.runs, 3);
+        assert_eq!(
+            run_all.skipped,
+            vec![utc("2026-01-01T07:30:00Z"), utc("2026-01-01T08:30:00Z")]
+        );
+
+        // Only slightly late: runs normally whatever the policy
+        let late = plan_misfire(&interval_task("2026-01-01T11:58:00Z", Some("skip")), now, grace);
+        assert_eq!(late, MisfirePlan { skipped: Vec::new(), runs: 1 });
+
+        let mut once = interval_task("2025-12-31T12:00:00Z", Some("skip"));
+        once.schedule_type = "once".to_string();
+        let once = plan_misfire(&once, now, grace);
+        assert_eq!(once.runs, 0);
+        assert_eq!(once.skipped, vec![utc("2025-12-31T12:00:00Z")]);
+    }
+
+    #[tokio::test]
+    async fn test_skipped_runs_are_logged() {
+        let (db, path) = test_db("test_task_misfire.db");
+        let manager = TaskManager::new(&db, TaskScope::admin());
+        let mut spec = new_task("interval", "3600000");
+        spec.misfire_policy = Some("skip".to_string());
+        let task = manager.create(spec).unwrap();
+        assert_eq!(task.misfire_policy.as_deref(), Some("skip"));
+
+        let scheduler = TaskScheduler::new(db.clone());
+        let slots = [utc("2026-01-01T07:30:00Z"), utc("2026-01-01T08:30:00Z")];
+        scheduler.log_skipped_runs(&task, &slots).await.unwrap();
+        scheduler.skip_to_next_run(&task).await.unwrap();
+
+        let runs = manager.runs(&task.id, 10).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].status, RUN_STATUS_SKIPPED);
+        assert_eq!(runs[0].run_at, "2026-01-01T08:30:00+00:00");
+        assert_eq!(runs[0].error.as_deref(), Some("Missed scheduled run (skip)"));
+        assert_eq!(manager.get(&task.id).unwrap().status, "active");
+
+        let mut once = new_task("once", "2030-01-01T00:00:00Z");
+        once.misfire_policy = Some("bogus".to_string());
+        assert!(manager.create(once).is_err());
+        let once = manager.create(new_task("once", "2030-01-01T00:00:00Z")).unwrap();
+        scheduler.skip_to_next_run(&once).await.unwrap();
+        let once = manager.get(&once.id).unwrap();
+        assert_eq!(once.status, RUN_STATUS_SKIPPED);
+        assert!(once.next_run.is_none());
+
+        remove_test_db(&path);
+    }
+
+    fn new_task(schedule_type: &str, schedule_value: &str) -> NewTask {
+        NewTask {
+            prompt: "Daily summary".to_string(),
+            schedule_type: schedule_type.to_string(),
+            schedule_value: schedule_value.to_string(),
+            group_folder: None,
+            chat_jid: "telegram:-100".to_string(),
+            context_mode: None,
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            delivery: None,
+        }
+    }
+
+    #[test]
+    fn test_task_manager_create_validates() {
+        let (db, path) = test_db("test_task_manager_create.db");
+        let manager = TaskManager::new(&db, TaskScope::new("family", false));
+
+        let task = manager.create(new_task("cron", "0 0 9 * * *")).unwrap();
+        assert!(task.id.starts_with("task_"));
+        assert_eq!(task.group_folder, "family");
+        assert_eq!(task.context_mode, "isolated");
+        assert!(task.next_run.is_some());
+
+        assert!(manager.create(new_task("cron", "not a cron")).is_err());
+        assert!(manager.create(new_task("weekly", "1")).is_err());
+        assert!(manager.create(new_task("interval", "-5")).is_err());
+        let mut empty = new_task("interval", "60000");
+        empty.prompt = "  ".to_string();
+        assert!(manager.create(empty).is_err());
+        let mut berlin = new_task("cron", "0 0 9 * * *");
+        berlin.timezone = Some("Europe/Berlin".to_string());
+        let task = manager.create(berlin).unwrap();
+        assert_eq!(task.timezone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(task_timezone(&get_task(&db, &task.id).unwrap().unwrap()).name(), "Europe/Berlin");
+        let mut unknown = new_task("cron", "0 0 9 * * *");
+        unknown.timezone = Some("Nowhere/Special".to_string());
+        assert!(manager.create(unknown).is_err());
+        let mut quiet = new_task("interval", "60000");
+        quiet.delivery = Some("on_change".to_string());
+        assert_eq!(manager.create(quiet).unwrap().delivery.as_deref(), Some("on_change"));
+        let mut noisy = new_task("interval", "60000");
+        noisy.delivery = Some("sometimes".to_string());
+        assert!(manager.create(noisy).is_err());
+        let mut other_group = new_task("interval", "60000");
+        other_group.group_folder = Some("work".to_string());
+        assert!(manager.create(other_group).is_err());
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_task_manager_scope() {
+        let (db, path) = test_db("test_task_manager_scope.db");
+        let family = TaskManager::new(&db, TaskScope::new("family", false));
+        let work = TaskManager::new(&db, TaskScope::new("work", false));
+        let admin = TaskManager::new(&db, TaskScope::admin());
+
+        let task = family.create(new_task("interval", "60000")).unwrap();
+        work.create(new_task("interval", "60000")).unwrap();
+
+        assert_eq!(family.list(false).unwrap().len(), 1);
+        assert_eq!(admin.list(false).unwrap().len(), 2);
+        assert!(work.get(&task.id).is_err());
+        assert!(work.cancel(&task.id).is_err());
+        assert_eq!(admin.get(&task.id).unwrap().group_folder, "family");
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_task_manager_pause_resume_cancel() {
+        let (db, path) = test_db("test_task_manager_lifecycle.db");
+        let manager = TaskManager::new(&db, TaskScope::new("family", false));
+        let task = manager.create(new_task("interval", "60000")).unwrap();
+
+        let paused = manager.pause(&task.id).unwrap();
+        assert_eq!(paused.status, STATUS_PAUSED);
+        assert!(manager.pause(&task.id).is_err());
+
+        let resumed = manager.resume(&task.id).unwrap();
+        assert_eq!(resumed.status, "active");
+        assert!(resumed.next_run.is_some());
+        assert!(manager.resume(&task.id).is_err());
+
+        let cancelled = manager.cancel(&task.id).unwrap();
+        assert_eq!(cancelled.status, "cancelled");
+        assert!(manager.list(false).unwrap().is_empty());
+        assert_eq!(manager.list(true).unwrap().len(), 1);
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_retry_backoff_doubles_and_caps() {
+        assert_eq!(retry_backoff(60_000, 1), chrono::Duration::minutes(1));
+        assert_eq!(retry_backoff(60_000, 2), chrono::Duration::minutes(2));
+        assert_eq!(retry_backoff(60_000, 4), chrono::Duration::minutes(8));
+        assert_eq!(
+            retry_backoff(60_000, 40),
+            chrono::Duration::milliseconds(MAX_TASK_RETRY_BACKOFF_MS)
+        );
+    }
+
+    #[test]
+    fn test_plan_failure() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let mut task = interval_task("2026-03-01T12:00:00Z", None);
+        task.max_retries = Some(2);
+        task.retry_backoff_ms = Some(60_000);
+
+        assert_eq!(
+            plan_failure(&task, 1, now, None),
+            FailureAction::Retry {
+                at: now + chrono::Duration::minutes(1)
+            }
+        );
+        assert_eq!(
+            plan_failure(&task, 2, now, None),
+            FailureAction::Retry {
+                at: now + chrono::Duration::minutes(2)
+            }
+        );
+        assert_eq!(plan_failure(&task, 3, now, None), FailureAction::Pause);
+
+        // A retry never waits past the next regular run
+        let regular = now + chrono::Duration::seconds(30);
+        assert_eq!(
+            plan_failure(&task, 1, now, Some(regular)),
+            FailureAction::Retry { at: regular }
+        );
+
+        task.max_retries = Some(0);
+        assert_eq!(plan_failure(&task, 1, now, None), FailureAction::Pause);
+    }
+
+    #[test]
+    fn test_record_failure_retries_then_pauses() {
+        let (db, path) = test_db("test_record_failure.db");
+        let manager = TaskManager::new(&db, TaskScope::new("family", false));
+        let mut new = new_task("once", "2030-01-01T00:00:00Z");
+        new.max_retries = Some(1);
+        new.retry_backoff_ms = Some(60_000);
+        let task = manager.create(new).unwrap();
+        let scheduler = TaskScheduler::new(db.clone());
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+
+        let (action, notice) = scheduler.record_failure(&task, "boom", now).unwrap();
+        assert_eq!(
+            action,
+            FailureAction::Retry {
+                at: now + chrono::Duration::minutes(1)
+            }
+        );
+        assert!(notice.contains("boom"));
+        let retrying = get_task(&db, &task.id).unwrap().unwrap();
+        assert_eq!(retrying.failure_count, 1);
+        assert_eq!(retrying.status, "active");
+        assert_eq!(
+            retrying.next_run.as_deref(),
+            Some((now + chrono::Duration::minutes(1)).to_rfc3339().as_str())
+        );
+
+        let (action, notice) = scheduler
+            .record_failure(&retrying, "boom again", now)
+            .unwrap();
+        assert_eq!(action, FailureAction::Pause);
+        assert!(notice.contains("boom again"));
+        assert!(notice.contains(&format!("/tasks resume {}", task.id)));
+        let paused = get_task(&db, &task.id).unwrap().unwrap();
+        assert_eq!(paused.status, STATUS_PAUSED);
+        assert_eq!(paused.failure_count, 2);
+
+        let resumed = manager.resume(&task.id).unwrap();
+        assert_eq!(resumed.failure_count, 0);
+
+        remove_test_db(&path);
+    }
+
+    /// Fails every run and counts them
+    struct FailingRuntime(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl Runtime for FailingRuntime {
+        async fn run(&self, _input: ContainerInput) -> Result<ContainerOutput> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(NuClawError::Container {
+                message: "boom".to_string(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_all_stops_after_failed_run() {
+        let (db, path) = test_db("test_run_all_failure.db");
+        let manager = TaskManager::new(&db, TaskScope::new("family", false));
+        let mut new = new_task("interval", "3600000");
+        new.misfire_policy = Some("run_all:3".to_string());
+        new.max_retries = Some(3);
+        new.retry_backoff_ms = Some(60_000);
+        let task = manager.create(new).unwrap();
+        // Three runs missed, and no channel to report the failure to
+        let missed = (Utc::now() - chrono::Duration::minutes(150)).to_rfc3339();
+        db.get_connection()
+            .unwrap()
+            .execute(
+                "UPDATE scheduled_tasks SET next_run = ?, chat_jid = 'test:scheduler' WHERE id = ?",
+                rusqlite::params![missed, task.id],
+            )
+            .unwrap();
+
+        let runtime = Arc::new(FailingRuntime(Default::default()));
+        let mut scheduler = TaskScheduler::new(db.clone()).with_runtime(runtime.clone());
+        let before = Utc::now();
+        scheduler.poll_and_execute_tasks().await.unwrap();
+
+        assert_eq!(runtime.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let retrying = get_task(&db, &task.id).unwrap().unwrap();
+        assert_eq!(retrying.status, "active");
+        assert_eq!(retrying.failure_count, 1);
+        let next_run = DateTime::parse_from_rfc3339(retrying.next_run.as_deref().unwrap()).unwrap();
+        assert!(next_run >= before + chrono::Duration::minutes(1));
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_delivery_policy() {
+        assert_eq!(DeliveryPolicy::parse("always").unwrap(), DeliveryPolicy::Always);
+        assert_eq!(DeliveryPolicy::parse("on-change").unwrap(), DeliveryPolicy::OnChange);
+        assert_eq!(DeliveryPolicy::parse("ON_ERROR").unwrap(), DeliveryPolicy::OnError);
+        assert!(DeliveryPolicy::parse("sometimes").is_err());
+        assert_eq!(DeliveryPolicy::OnChange.to_string(), "on_change");
+
+        let mut task = interval_task("2026-03-01T12:00:00Z", None);
+        assert_eq!(DeliveryPolicy::for_task(&task), DeliveryPolicy::Always);
+        task.delivery = Some("on_error".to_string());
+        assert_eq!(DeliveryPolicy::for_task(&task), DeliveryPolicy::OnError);
+        task.delivery = Some("bogus".to_string());
+        assert_eq!(DeliveryPolicy::for_task(&task), DeliveryPolicy::Always);
+    }
+
+    #[test]
+    fn test_delivery_policy_should_deliver() {
+        let always = DeliveryPolicy::Always;
+        assert!(always.should_deliver(false, "42", Some("42")));
+        assert!(always.should_deliver(true, "boom", None));
+
+        let on_change = DeliveryPolicy::OnChange;
+        assert!(on_change.should_deliver(false, "42", None));
+        assert!(on_change.should_deliver(false, "43", Some("42")));
+        assert!(!on_change.should_deliver(false, "42\n", Some("42")));
+        assert!(on_change.should_deliver(true, "boom", Some("42")));
+        assert!(!on_change.should_deliver(true, "boom", Some("boom")));
+
+        let on_error = DeliveryPolicy::OnError;
+        assert!(!on_error.should_deliver(false, "43", Some("42")));
+        assert!(on_error.should_deliver(true, "boom", Some("boom")));
+    }
+
+    #[test]
+    fn test_failed_run_error() {
+        let mut output = ContainerOutput {
+            status: crate::container_runner::STATUS_OOM_KILLED.to_string(),
+            result: Some("partial".to_string()),
+            new_session_id: None,
+            error: Some("out of memory".to_string()),
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
+        };
+        assert_eq!(failed_run_error(&output), "out of memory");
+
+        output.status = "error".to_string();
+        output.error = None;
+        assert_eq!(failed_run_error(&output), "Agent run ended with status error");
+    }
 }
@@ -1,19 +1,26 @@
 //! Telegram client implementation
 
-use crate::agent_runner::create_runner;
+use crate::agent_runner::{agent_runner_mode, create_runner, AgentRunnerMode, ApiRunner};
+use crate::artifacts::{Artifact, ArtifactKind};
 use crate::config::{assistant_name, data_dir};
 use crate::db::Database;
 use crate::error::{NuClawError, Result};
 use crate::skill_installer::{parse_install_request, GitInstaller};
 use crate::telegram::pairing::PairingManager;
-use crate::telegram::policy::{DMPolicy, GroupPolicy};
+use crate::telegram::policy::{ChunkMode, DMPolicy, GroupPolicy, StreamMode};
 use crate::telegram::types::TelegramMessage;
-use crate::telegram::utils::{extract_chat_id_pure, is_duplicate_message_pure, DEFAULT_TEXT_CHUNK_LIMIT};
-use crate::types::{NewMessage, RegisteredGroup, RouterState};
+use crate::telegram::utils::{
+    chunk_text_advanced, extract_chat_id_pure, is_duplicate_message_pure, stream_preview,
+    DEFAULT_STREAM_THROTTLE_MS, DEFAULT_TEXT_CHUNK_LIMIT,
+};
+use crate::types::{ContainerInput, ContainerProgress, NewMessage, RegisteredGroup, RouterState};
 use crate::utils::json::{load_json, save_json};
 
 const PAIRING_CODE_LENGTH: usize = 6;
 
+/// Text of the message that is edited while a reply streams in
+const STREAM_PLACEHOLDER: &str = "…";
+
 use axum::body::Body;
 use axum::extract::State;
 use axum::routing::{get, post};
@@ -35,6 +42,9 @@ pub struct TelegramClient {
     dm_policy: DMPolicy,
     group_policy: GroupPolicy,
     text_chunk_limit: usize,
+    chunk_mode: ChunkMode,
+    stream_mode: StreamMode,
+    stream_throttle: Duration,
     allowed_groups: Vec<String>,
     registered_groups: HashMap<String, RegisteredGroup>,
     router_state: RouterState,
@@ -156,6 +166,110 @@ async fn telegram_send_with_retry(
     })
 }
 
+/// Send a plain message and return its message id
+async fn telegram_send_returning_id(api_url: &str, chat_id: i64, text: &str) -> Result<i64> {
+    let payload = serde_json::json!({
+        "chat_id": chat_id,
+        "text": text,
+    });
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/sendMessage", api_url))
+        .json(&payload)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| NuClawError::Telegram {
+            message: format!("Request failed: {}", e),
+        })?;
+
+    let body: serde_json::Value = response.json().await.map_err(|e| NuClawError::Telegram {
+        message: format!("Failed to parse sendMessage response: {}", e),
+    })?;
+
+    body["result"]["message_id"]
+        .as_i64()
+        .ok_or_else(|| NuClawError::Telegram {
+            message: format!("Failed to send message: {}", body),
+        })
+}
+
+/// Replace the text of a sent message
+///
+/// Edits that would not change the text are treated as success.
+async fn telegram_edit_message(
+    api_url: &str,
+    chat_id: i64,
+    message_id: i64,
+    text: &str,
+    html: bool,
+) -> Result<()> {
+    let mut payload = serde_json::json!({
+        "chat_id": chat_id,
+        "message_id": message_id,
+        "text": text,
+    });
+    if html {
+        payload["parse_mode"] = serde_json::Value::String("HTML".to_string());
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/editMessageText", api_url))
+        .json(&payload)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| NuClawError::Telegram {
+            message: format!("Request failed: {}", e),
+        })?;
+
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let error = response.text().await.unwrap_or_default();
+    if error.contains("message is not modified") {
+        return Ok(());
+    }
+    Err(NuClawError::Telegram {
+        message: format!("Failed to edit message: {}", error),
+    })
+}
+
+/// Largest image Telegram accepts through `sendPhoto`; bigger ones go as documents
+const TELEGRAM_PHOTO_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Upload an artifact as a photo or document
+async fn telegram_send_file(api_url: &str, chat_id: i64, artifact: &Artifact) -> Result<()> {
+    let (method, field) = match artifact.kind {
+        ArtifactKind::Image if artifact.size <= TELEGRAM_PHOTO_MAX_BYTES => ("sendPhoto", "photo"),
+        _ => ("sendDocument", "document"),
+    };
+    let bytes = tokio::fs::read(&artifact.path).await.map_err(|e| NuClawError::FileSystem {
+        message: format!("Failed to read {}: {}", artifact.path.display(), e),
+    })?;
+    let form = reqwest::multipart::Form::new()
+        .text("chat_id", chat_id.to_string())
+        .part(field, reqwest::multipart::Part::bytes(bytes).file_name(artifact.name.clone()));
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/{}", api_url, method))
+        .multipart(form)
+        .timeout(Duration::from_secs(120))
+        .send()
+        .await
+        .map_err(|e| NuClawError::Telegram {
+            message: format!("Request failed: {}", e),
+        })?;
+
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let error = response.text().await.unwrap_or_default();
+    Err(NuClawError::Telegram {
+        message: format!("Failed to upload {}: {}", artifact.name, error),
+    })
+}
+
 impl TelegramClient {
     pub fn new(db: Database) -> Result<Self> {
         let bot_token = std::env::var("TELEGRAM_BOT_TOKEN").map_err(|_| NuClawError::Config {
@@ -178,6 +292,18 @@ impl TelegramClient {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(DEFAULT_TEXT_CHUNK_LIMIT),
+            chunk_mode: ChunkMode::parse(
+                &std::env::var("TELEGRAM_CHUNK_MODE").unwrap_or_else(|_| "length".to_string()),
+            ),
+            stream_mode: StreamMode::parse(
+                &std::env::var("TELEGRAM_STREAM_MODE").unwrap_or_else(|_| "partial".to_string()),
+            ),
+            stream_throttle: Duration::from_millis(
+                std::env::var("TELEGRAM_STREAM_THROTTLE_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_STREAM_THROTTLE_MS),
+            ),
             allowed_groups: std::env::var("TELEGRAM_WHITELIST_GROUPS")
                 .ok()
                 .map(|s| s.split(',').map(|v| v.trim().to_string()).collect())
@@ -261,6 +387,7 @@ impl TelegramClient {
         let app = Router::new()
             .route(&format!("/{}", webhook_path), post(handle_telegram_webhook))
             .route("/health", get(health_check))
+            .route("/pool", get(pool_stats))
             .layer(axum::middleware::from_fn(verify_secret_middleware))
             .with_state(client);
 
@@ -422,16 +549,54 @@ impl TelegramClient {
             }
         };
 
+        if crate::session::is_reset_command(&content) {
+            crate::session::reset_session(&self.db, &group_folder, &msg.chat_jid, &msg.timestamp)?;
+            let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+            self.send_message(&chat_id, crate::session::SESSION_RESET_REPLY).await?;
+            return Ok(Some(crate::session::SESSION_RESET_REPLY.to_string()));
+        }
+
+        let scope = crate::task_scheduler::TaskScope::new(&group_folder, !is_group);
+        if let Some(reply) =
+            crate::task_commands::handle_task_command(&self.db, &scope, &msg.chat_jid, &content)
+        {
+            let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+            self.send_message(&chat_id, &reply).await?;
+            return Ok(Some(reply));
+        }
+
+        let session = crate::session::active_session(&self.db, &group_folder, &msg.chat_jid);
         let input = crate::types::ContainerInput {
             prompt: content,
-            session_id: Some(format!("telegram_{}", msg.id)),
-            group_folder,
+            session_id: session.as_ref().and_then(|s| s.session_id.clone()),
+            group_folder: group_folder.clone(),
             chat_jid: msg.chat_jid.clone(),
             is_main: !is_group,
             is_scheduled_task: false,
             session_workspace_id: None,
+            history: self.conversation_history(msg, session.as_ref()),
+            run_id: None,
         };
 
+        let budget = crate::budget::check_budget_for(&self.db, &group_folder, &msg.sender);
+        if let crate::budget::BudgetStatus::Exhausted(notice) = budget {
+            let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+            self.send_message(&chat_id.to_string(), &notice).await?;
+            return Ok(Some(notice));
+        }
+
+        if self.streams_replies() {
+            let reply = self.stream_reply(msg, input, &group_folder).await?;
+            if reply.is_some() {
+                if let crate::budget::BudgetStatus::Warning(warning) = budget {
+                    let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+                    self.send_message(&chat_id, &warning.message).await?;
+                    warning.mark_sent();
+                }
+            }
+            return Ok(reply);
+        }
+
         let runner = create_runner()?;
         let result = tokio::time::timeout(crate::container_runner::container_timeout(), runner.run(input)).await;
 
@@ -439,19 +604,28 @@ impl TelegramClient {
 
         match result {
             Ok(Ok(output)) => {
+                crate::usage::record_output_usage(&self.db, &group_folder, &msg.chat_jid, &msg.sender, &output);
+                crate::session::record_session(&self.db, &group_folder, &msg.chat_jid, &msg.timestamp, &output);
+                let chat_id = self.extract_chat_id(&msg.chat_jid)?;
                 if let Some(response) = output.result {
                     if response.trim().is_empty() {
                         tracing::warn!("Agent returned empty response, skipping");
+                        self.send_artifacts(&chat_id, &output.artifacts).await;
                         return Ok(None);
                     }
-                    let chat_id = self.extract_chat_id(&msg.chat_jid)?;
                     self.send_message(&chat_id.to_string(), &response).await?;
+                    self.send_artifacts(&chat_id, &output.artifacts).await;
+                    self.store_reply_background(msg, &response);
+                    if let crate::budget::BudgetStatus::Warning(warning) = budget {
+                        self.send_message(&chat_id.to_string(), &warning.message).await?;
+                        warning.mark_sent();
+                    }
                     return Ok(Some(response));
                 }
                 error!("Agent returned no result: status={}", output.status);
-                let chat_id = self.extract_chat_id(&msg.chat_jid)?;
-                self.send_message(&chat_id.to_string(), "Sorry, I couldn't process your request.")
-                    .await?;
+                let reply = output.limit_error().unwrap_or("Sorry, I couldn't process your request.");
+                self.send_message(&chat_id.to_string(), reply).await?;
+                self.send_artifacts(&chat_id, &output.artifacts).await;
             }
             Ok(Err(e)) => {
                 error!("Agent error: {}", e);
@@ -470,6 +644,180 @@ impl TelegramClient {
         Ok(None)
     }
 
+    /// Whether replies are streamed (the API runner yields deltas, containers
+    /// report progress lines)
+    fn streams_replies(&self) -> bool {
+        self.stream_mode != StreamMode::Off
+            && matches!(agent_runner_mode(), AgentRunnerMode::Api | AgentRunnerMode::Container)
+    }
+
+    /// Run the agent with streaming, editing a placeholder message as progress arrives
+    ///
+    /// Partial text replaces the placeholder as it grows; until there is any,
+    /// the tool or status the agent reports is shown instead. Edits are
+    /// throttled to `stream_throttle`; the final text is chunked with
+    /// `chunk_text_advanced`, the first chunk replacing the placeholder.
+    async fn stream_reply(
+        &self,
+        msg: &NewMessage,
+        input: ContainerInput,
+        group_folder: &str,
+    ) -> Result<Option<String>> {
+        let chat_id = self.extract_chat_id(&msg.chat_jid)?;
+        let cid: i64 = chat_id.parse().map_err(|_| NuClawError::Telegram {
+            message: format!("Invalid chat_id: {}", chat_id),
+        })?;
+
+        let message_id = telegram_send_returning_id(&self.api_url, cid, STREAM_PLACEHOLDER).await?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<ContainerProgress>();
+        let api_url = self.api_url.clone();
+        let (chunk_limit, stream_mode, throttle) = (self.text_chunk_limit, self.stream_mode, self.stream_throttle);
+        let editor = tokio::spawn(async move {
+            let mut text = String::new();
+            let mut status = None;
+            let mut shown = String::new();
+            let mut last_edit = tokio::time::Instant::now();
+            while let Some(update) = rx.recv().await {
+                if let Some(delta) = &update.text {
+                    text.push_str(delta);
+                }
+                if let Some(line) = update.status_line() {
+                    status = Some(line);
+                }
+                if last_edit.elapsed() < throttle {
+                    continue;
+                }
+                let preview = stream_preview(&text, chunk_limit, stream_mode).or_else(|| status.clone());
+                if let Some(preview) = preview {
+                    if preview != shown {
+                        if let Err(e) = telegram_edit_message(&api_url, cid, message_id, &preview, false).await {
+                            debug!("Streaming edit failed: {}", e);
+                        }
+                        shown = preview;
+                        last_edit = tokio::time::Instant::now();
+                    }
+                }
+            }
+        });
+
+        let timeout = crate::container_runner::container_timeout();
+        let result = if agent_runner_mode() == AgentRunnerMode::Api {
+            let runner = ApiRunner::new()?;
+            tokio::time::timeout(
+                timeout,
+                runner.run_streaming(&input, move |delta| {
+                    let _ = tx.send(ContainerProgress {
+                        text: Some(delta),
+                        ..Default::default()
+                    });
+                }),
+            )
+            .await
+        } else {
+            let runner = create_runner()?;
+            tokio::time::timeout(timeout, runner.run_with_progress(input, tx)).await
+        };
+        let _ = editor.await;
+
+        let mut artifacts = Vec::new();
+        let failure = match result {
+            Ok(Ok(output)) => {
+                crate::usage::record_output_usage(&self.db, group_folder, &msg.chat_jid, &msg.sender, &output);
+                crate::session::record_session(&self.db, group_folder, &msg.chat_jid, &msg.timestamp, &output);
+                artifacts = output.artifacts.clone();
+                match output.result {
+                    Some(response) if !response.trim().is_empty() => {
+                        self.finish_streamed_reply(cid, message_id, &response).await?;
+                        self.send_artifacts(&chat_id, &artifacts).await;
+                        self.store_reply_background(msg, &response);
+                        return Ok(Some(response));
+                    }
+                    _ => {
+                        error!("Agent returned no result: status={}", output.status);
+                        output
+                            .limit_error()
+                            .unwrap_or("Sorry, I couldn't process your request.")
+                            .to_string()
+                    }
+                }
+            }
+            Ok(Err(e)) => {
+                error!("Agent error: {}", e);
+                format!("Error: {}", e)
+            }
+            Err(_) => {
+                error!("Agent timeout");
+                "Sorry, the request timed out.".to_string()
+            }
+        };
+
+        telegram_edit_message(&self.api_url, cid, message_id, &failure, false).await?;
+        self.send_artifacts(&chat_id, &artifacts).await;
+        Ok(None)
+    }
+
+    /// Upload the files a run produced, logging failures
+    async fn send_artifacts(&self, chat_id: &str, artifacts: &[Artifact]) {
+        let Ok(cid) = chat_id.parse::<i64>() else {
+            error!("Invalid chat_id for artifacts: {}", chat_id);
+            return;
+        };
+        for artifact in artifacts {
+            if let Err(e) = telegram_send_file(&self.api_url, cid, artifact).await {
+                error!("Failed to upload {}: {}", artifact.name, e);
+            }
+        }
+    }
+
+    /// Replace the placeholder with the first chunk and send the rest in order
+    async fn finish_streamed_reply(&self, chat_id: i64, message_id: i64, response: &str) -> Result<()> {
+        let chunks = chunk_text_advanced(response, self.text_chunk_limit, self.chunk_mode);
+        let mut chunks = chunks.iter().filter(|c| !c.trim().is_empty());
+
+        if let Some(first) = chunks.next() {
+            let clean = strip_unsupported_html_tags(first);
+            if let Err(e) = telegram_edit_message(&self.api_url, chat_id, message_id, &clean, true).await {
+                // Markup the HTML parser rejects is still worth showing as plain text
+                debug!("HTML edit failed, retrying as plain text: {}", e);
+                telegram_edit_message(&self.api_url, chat_id, message_id, first, false).await?;
+            }
+        }
+        for chunk in chunks {
+            telegram_send_single_message(&self.api_url, chat_id, chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Load previous turns of this chat's session, excluding the message being answered
+    fn conversation_history(
+        &self,
+        msg: &NewMessage,
+        session: Option<&crate::session::AgentSession>,
+    ) -> Vec<crate::providers::ChatMessage> {
+        let mut query = crate::history::HistoryQuery::new(&msg.chat_jid).excluding(&msg.id);
+        if let Some(started_at) = session.and_then(|s| s.started_at.as_deref()) {
+            query = query.since(started_at);
+        }
+        crate::history::load_history(&self.db, &query).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load history for {}: {}", msg.chat_jid, e);
+            Vec::new()
+        })
+    }
+
+    /// Persist a reply in the background so it shows up in later history
+    fn store_reply_background(&self, msg: &NewMessage, response: &str) {
+        let db = self.db.clone();
+        let msg = msg.clone();
+        let response = response.to_string();
+        let sender_name = self.assistant_name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::history::store_reply(&db, &msg, &sender_name, &response) {
+                error!("Failed to store reply: {}", e);
+            }
+        });
+    }
+
     pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<()> {
         if text.trim().is_empty() {
             tracing::warn!("Skipping empty message");
@@ -505,7 +853,7 @@ impl TelegramClient {
     }
 
     fn chunk_text(&self, text: &str) -> Vec<String> {
-        crate::telegram::utils::chunk_text_pure(text, self.text_chunk_limit)
+        chunk_text_advanced(text, self.text_chunk_limit, self.chunk_mode)
     }
 
     async fn check_dm_policy(&self, user_id: &str) -> Result<bool> {
@@ -729,6 +1077,11 @@ async fn health_check() -> (StatusCode, &'static str) {
     (StatusCode::OK, "OK")
 }
 
+/// Container pool statistics for monitoring
+async fn pool_stats() -> Json<crate::container_runner::PoolStats> {
+    Json(crate::container_runner::global_pool().stats().await)
+}
+
 // Helper functions
 pub fn load_router_state() -> RouterState {
     let state_path = data_dir().join("router_state.json");
@@ -1,11 +1,40 @@
 //! Telegram utility functions
 
-use crate::telegram::policy::{ChunkMode, GroupPolicy};
+use crate::telegram::policy::{ChunkMode, GroupPolicy, StreamMode};
 use crate::types::NewMessage;
 
 /// Default text chunk limit: 4000 characters
 pub const DEFAULT_TEXT_CHUNK_LIMIT: usize = 4000;
 
+/// Default minimum interval between streaming message edits
+pub const DEFAULT_STREAM_THROTTLE_MS: u64 = 1000;
+
+/// Largest char boundary in `s` at or below `limit` bytes (at least one char)
+fn split_point(s: &str, limit: usize) -> usize {
+    if s.len() <= limit {
+        return s.len();
+    }
+    let mut point = limit;
+    while point > 0 && !s.is_char_boundary(point) {
+        point -= 1;
+    }
+    if point == 0 {
+        s.chars().next().map(char::len_utf8).unwrap_or(0)
+    } else {
+        point
+    }
+}
+
+/// Hard-split text that has no usable separators
+fn push_hard_split(chunks: &mut Vec<String>, text: &str, chunk_limit: usize) {
+    let mut remaining = text;
+    while !remaining.is_empty() {
+        let point = split_point(remaining, chunk_limit);
+        chunks.push(remaining[..point].to_string());
+        remaining = &remaining[point..];
+    }
+}
+
 /// Chunk text into smaller pieces (pure function)
 pub fn chunk_text_pure(text: &str, chunk_limit: usize) -> Vec<String> {
     if text.len() <= chunk_limit {
@@ -24,12 +53,7 @@ pub fn chunk_text_pure(text: &str, chunk_limit: usize) -> Vec<String> {
                 current = String::new();
             }
 
-            let mut remaining = para;
-            while !remaining.is_empty() {
-                let split_point = remaining.len().min(chunk_limit);
-                chunks.push(remaining[..split_point].to_string());
-                remaining = &remaining[split_point..];
-            }
+            push_hard_split(&mut chunks, para, chunk_limit);
         } else if current.len() + para.len() + 2 > chunk_limit {
             if !current.is_empty() {
                 chunks.push(current);
@@ -50,10 +74,69 @@ pub fn chunk_text_pure(text: &str, chunk_limit: usize) -> Vec<String> {
     chunks
 }
 
+/// Chunk text on line boundaries, packing whole lines up to the limit
+fn chunk_text_lines(text: &str, chunk_limit: usize) -> Vec<String> {
+    if text.len() <= chunk_limit {
+        return vec![text.to_string()];
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.split('\n') {
+        if line.len() > chunk_limit {
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            push_hard_split(&mut chunks, line, chunk_limit);
+        } else if !current.is_empty() && current.len() + line.len() + 1 > chunk_limit {
+            chunks.push(std::mem::replace(&mut current, line.to_string()));
+        } else {
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(line);
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
 /// Advanced chunk text with configurable mode
 pub fn chunk_text_advanced(text: &str, chunk_limit: usize, mode: ChunkMode) -> Vec<String> {
     match mode {
-        ChunkMode::Length | ChunkMode::Newline => chunk_text_pure(text, chunk_limit),
+        ChunkMode::Length => chunk_text_pure(text, chunk_limit),
+        ChunkMode::Newline => chunk_text_lines(text, chunk_limit),
+    }
+}
+
+/// Text to show while a reply is still streaming (pure function)
+///
+/// `Partial` shows everything received so far, `Block` only completed
+/// paragraphs. Text beyond `chunk_limit` is cut off with an ellipsis; the
+/// full reply is chunked once streaming finishes. Returns `None` when there
+/// is nothing to show yet.
+pub fn stream_preview(text: &str, chunk_limit: usize, mode: StreamMode) -> Option<String> {
+    let visible = match mode {
+        StreamMode::Off => return None,
+        StreamMode::Partial => text,
+        StreamMode::Block => &text[..text.rfind("\n\n").unwrap_or(0)],
+    };
+    let visible = visible.trim_end();
+    if visible.is_empty() {
+        return None;
+    }
+
+    if visible.len() <= chunk_limit {
+        Some(visible.to_string())
+    } else {
+        let ellipsis = "…";
+        let point = split_point(visible, chunk_limit.saturating_sub(ellipsis.len()));
+        Some(format!("{}{}", &visible[..point], ellipsis))
     }
 }
 
@@ -200,6 +283,35 @@ mod tests {
         assert!(!chunks_newline.is_empty());
     }
 
+    #[test]
+    fn test_chunk_text_multibyte_does_not_panic() {
+        let text = "你好".repeat(10);
+        let chunks = chunk_text_pure(&text, 7);
+        assert!(chunks.iter().all(|c| c.len() <= 7));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn test_chunk_text_newline_mode() {
+        let text = "line one\nline two\nline three";
+        let chunks = chunk_text_advanced(text, 18, ChunkMode::Newline);
+        assert_eq!(chunks, vec!["line one\nline two", "line three"]);
+    }
+
+    #[test]
+    fn test_stream_preview() {
+        assert_eq!(stream_preview("hello", 100, StreamMode::Off), None);
+        assert_eq!(stream_preview("  ", 100, StreamMode::Partial), None);
+        assert_eq!(stream_preview("hello wor", 100, StreamMode::Partial), Some("hello wor".to_string()));
+
+        assert_eq!(stream_preview("first para\n\nsecond pa", 100, StreamMode::Block), Some("first para".to_string()));
+        assert_eq!(stream_preview("no break yet", 100, StreamMode::Block), None);
+
+        let preview = stream_preview(&"x".repeat(50), 10, StreamMode::Partial).unwrap();
+        assert!(preview.len() <= 10);
+        assert!(preview.ends_with('…'));
+    }
+
     #[test]
     fn test_is_duplicate_message_pure() {
         let msg = NewMessage {
@@ -26,6 +26,38 @@ pub struct ToolDefinition {
     pub params: Vec<ToolParam>,
 }
 
+impl ToolDefinition {
+    /// JSON Schema object describing the tool parameters (for LLM tool APIs)
+    pub fn input_schema(&self) -> serde_json::Value {
+        let mut properties = serde_json::Map::new();
+        let mut required = Vec::new();
+
+        for param in &self.params {
+            let param_type = if param.param_type.is_empty() {
+                "string"
+            } else {
+                param.param_type.as_str()
+            };
+            properties.insert(
+                param.name.clone(),
+                serde_json::json!({
+                    "type": param_type,
+                    "description": param.description,
+                }),
+            );
+            if param.required {
+                required.push(serde_json::Value::String(param.name.clone()));
+            }
+        }
+
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
 /// Tool execution result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolResult {
@@ -266,6 +298,34 @@ mod tests {
         assert_eq!(defs[0].name, "test");
     }
 
+    #[test]
+    fn test_tool_definition_input_schema() {
+        let def = ToolDefinition {
+            name: "search".to_string(),
+            description: "Search things".to_string(),
+            params: vec![
+                ToolParam {
+                    name: "query".to_string(),
+                    description: "Search query".to_string(),
+                    required: true,
+                    param_type: "string".to_string(),
+                },
+                ToolParam {
+                    name: "limit".to_string(),
+                    description: "Max results".to_string(),
+                    required: false,
+                    param_type: String::new(),
+                },
+            ],
+        };
+
+        let schema = def.input_schema();
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["query"]["type"], "string");
+        assert_eq!(schema["properties"]["limit"]["type"], "string");
+        assert_eq!(schema["required"], serde_json::json!(["query"]));
+    }
+
     #[test]
     fn test_tool_context() {
         let ctx = ToolContext::new("group1")
@@ -42,6 +42,24 @@ pub struct ScheduledTask {
     pub last_result: Option<String>,
     pub status: String,
     pub created_at: String,
+    /// IANA timezone the schedule is evaluated in; unset uses the group's or `TZ`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// What to do about runs missed while the scheduler was down; unset is `run_once`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub misfire_policy: Option<String>,
+    /// Retries after a failed run before the task is paused; unset uses `TASK_MAX_RETRIES`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Delay before the first retry, doubled for each further one; unset uses `TASK_RETRY_BACKOFF_MS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_ms: Option<i64>,
+    /// Failed runs in a row since the last success
+    #[serde(default)]
+    pub failure_count: u32,
+    /// Which run outcomes are posted to the chat; unset is `always`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +99,12 @@ pub struct ContainerInput {
     pub is_scheduled_task: bool,
     /// Session workspace ID (set when using per-session workspace)
     pub session_workspace_id: Option<String>,
+    /// Previous conversation turns, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<crate::providers::ChatMessage>,
+    /// Identifies this run; its outbox is `outbox/<run_id>` (assigned by the runner when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +113,65 @@ pub struct ContainerOutput {
     pub result: Option<String>,
     pub new_session_id: Option<String>,
     pub error: Option<String>,
+    /// Names of tools invoked while producing this output
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools_used: Vec<String>,
+    /// Provider that produced the result (set by API-based runners)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model that produced the result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Token usage reported by the provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<crate::providers::Usage>,
+    /// Files the run produced for the chat
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<crate::artifacts::Artifact>,
+}
+
+impl ContainerOutput {
+    /// Error to show users when the run was stopped by a container resource limit
+    pub fn limit_error(&self) -> Option<&str> {
+        let status = self.status.as_str();
+        if status == crate::container_runner::STATUS_OOM_KILLED
+            || status == crate::container_runner::STATUS_LIMIT_EXCEEDED
+        {
+            self.error.as_deref()
+        } else {
+            None
+        }
+    }
+}
+
+/// Intermediate progress an agent reports before its final output
+///
+/// Emitted by the container as `--NUCLAW_PROGRESS--{json}` lines on stdout.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerProgress {
+    /// Short phase description, e.g. "thinking"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Partial reply text, appended to what was sent before
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Tool the agent is currently using
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+}
+
+impl ContainerProgress {
+    /// One-line description of what the agent is doing, if reported
+    pub fn status_line(&self) -> Option<String> {
+        if let Some(tool) = self.tool.as_deref().filter(|t| !t.trim().is_empty()) {
+            return Some(format!("🔧 Using {}…", tool.trim()));
+        }
+        self.status
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("{}…", s))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -153,6 +236,12 @@ mod tests {
             last_result: None,
             status: "active".to_string(),
             created_at: "2025-01-01T00:00:00Z".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
         assert_eq!(task.schedule_type, "cron");
         assert_eq!(task.status, "active");
@@ -168,6 +257,8 @@ mod tests {
             is_main: true,
             is_scheduled_task: false,
             session_workspace_id: Some("ws_456".to_string()),
+            history: Vec::new(),
+            run_id: None,
         };
         assert!(input.session_id.is_some());
         assert!(input.is_main);
@@ -181,12 +272,38 @@ mod tests {
             result: Some("result".to_string()),
             new_session_id: Some("new_sess".to_string()),
             error: None,
+            tools_used: Vec::new(),
+            provider: None,
+            model: None,
+            usage: None,
+            artifacts: Vec::new(),
         };
         assert_eq!(output.status, "success");
         assert!(output.result.is_some());
         assert!(output.error.is_none());
     }
 
+    #[test]
+    fn test_container_output_limit_error() {
+        let json = r#"{"status":"oom_killed","result":null,"new_session_id":null,"error":"out of memory"}"#;
+        let output: ContainerOutput = serde_json::from_str(json).unwrap();
+        assert_eq!(output.limit_error(), Some("out of memory"));
+
+        let json = r#"{"status":"error","result":null,"new_session_id":null,"error":"failed"}"#;
+        let output: ContainerOutput = serde_json::from_str(json).unwrap();
+        assert_eq!(output.limit_error(), None);
+    }
+
+    #[test]
+    fn test_container_output_tools_used_default() {
+        let json = r#"{"status":"success","result":"ok","new_session_id":null,"error":null}"#;
+        let output: ContainerOutput = serde_json::from_str(json).unwrap();
+        assert!(output.tools_used.is_empty());
+
+        let serialized = serde_json::to_string(&output).unwrap();
+        assert!(!serialized.contains("tools_used"));
+    }
+
     #[test]
     fn test_router_state() {
         let mut state = RouterState::default();
@@ -231,4 +348,26 @@ mod tests {
         };
         assert_eq!(info.name, "Test Chat");
     }
+
+    #[test]
+    fn test_progress_status_line() {
+        let tool = ContainerProgress {
+            status: Some("thinking".to_string()),
+            tool: Some("Bash".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(tool.status_line().as_deref(), Some("🔧 Using Bash…"));
+
+        let status = ContainerProgress {
+            status: Some("thinking".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(status.status_line().as_deref(), Some("thinking…"));
+
+        let text_only = ContainerProgress {
+            text: Some("partial".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(text_only.status_line(), None);
+    }
 }
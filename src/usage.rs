@@ -0,0 +1,408 @@
+//! Token usage accounting for NuClaw
+//!
+//! Records the tokens each agent request consumed in the `token_usage` table,
+//! prices them with a per-model price table and aggregates them per day or
+//! month for billing.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::db::Database;
+use crate::error::{NuClawError, Result};
+use crate::providers::Usage;
+use crate::types::ContainerOutput;
+use crate::workflow::{workflow_config, ModelPrice, WorkflowConfig};
+
+/// Built-in prices (USD per million tokens), keyed by model name prefix
+const BUILTIN_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+];
+
+/// Per-model price table matched by longest model name prefix
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    /// Table with the built-in prices only
+    pub fn builtin() -> Self {
+        let prices = BUILTIN_PRICES
+            .iter()
+            .map(|&(model, input, output)| (model.to_string(), ModelPrice { input, output }))
+            .collect();
+        Self { prices }
+    }
+
+    /// Built-in prices overridden by the `pricing` section of WORKFLOW.md
+    pub fn from_workflow(workflow: &WorkflowConfig) -> Self {
+        let mut table = Self::builtin();
+        for (model, price) in &workflow.pricing {
+            table = table.with_price(model.clone(), *price);
+        }
+        table
+    }
+
+    pub fn with_price(mut self, model_prefix: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(model_prefix.into(), price);
+        self
+    }
+
+    /// Look up the price of a model
+    ///
+    /// Gateway prefixes such as `anthropic/` (OpenRouter) are ignored.
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        let name = model.rsplit('/').next().unwrap_or(model);
+        if let Some(price) = self.prices.get(model) {
+            return Some(*price);
+        }
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| name.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// Cost of a request in USD (zero for unknown models)
+    pub fn cost(&self, model: &str, usage: &Usage) -> f64 {
+        match self.price(model) {
+            Some(price) => {
+                (usage.input_tokens as f64 * price.input + usage.output_tokens as f64 * price.output)
+                    / 1_000_000.0
+            }
+            None => {
+                tracing::debug!("No price configured for model {}", model);
+                0.0
+            }
+        }
+    }
+}
+
+/// Price table from the shared workflow config
+pub fn price_table() -> PriceTable {
+    PriceTable::from_workflow(&workflow_config())
+}
+
+/// One request's token usage
+#[derive(Debug, Clone)]
+pub struct UsageRecord {
+    pub group_folder: String,
+    pub chat_jid: String,
+    pub user: String,
+    pub provider: Option<String>,
+    pub model: String,
+    pub usage: Usage,
+    pub cost_usd: f64,
+}
+
+/// Store a usage record
+pub fn record_usage(db: &Database, record: &UsageRecord) -> Result<()> {
+    let conn = db.get_connection()?;
+    conn.execute(
+        "INSERT INTO token_usage
+            (recorded_at, group_folder, chat_jid, user, provider, model, input_tokens, output_tokens, cost_usd)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            chrono::Utc::now().to_rfc3339(),
+            record.group_folder,
+            record.chat_jid,
+            record.user,
+            record.provider,
+            record.model,
+            record.usage.input_tokens as i64,
+            record.usage.output_tokens as i64,
+            record.cost_usd,
+        ],
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to record token usage: {}", e),
+    })?;
+    Ok(())
+}
+
+/// Record the usage reported in an agent output, if any
+///
+/// Failures are logged rather than returned so accounting never blocks a reply.
+pub fn record_output_usage(db: &Database, group_folder: &str, chat_jid: &str, user: &str, output: &ContainerOutput) {
+    let Some(usage) = output.usage else {
+        return;
+    };
+    let model = output.model.clone().unwrap_or_else(|| "unknown".to_string());
+    let record = UsageRecord {
+        group_folder: group_folder.to_string(),
+        chat_jid: chat_jid.to_string(),
+        user: user.to_string(),
+        provider: output.provider.clone(),
+        cost_usd: price_table().cost(&model, &usage),
+        model,
+        usage,
+    };
+    if let Err(e) = record_usage(db, &record) {
+        tracing::warn!("Failed to record usage for {}: {}", group_folder, e);
+    }
+}
+
+/// Tokens and spend accumulated over a time range
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Total usage of a group (optionally one user) recorded at or after `since`
+///
+/// `since` is compared against the RFC 3339 `recorded_at` column, so a date
+/// prefix such as `2024-05` or `2024-05-17` selects a whole month or day.
+pub fn usage_since(db: &Database, group_folder: &str, user: Option<&str>, since: &str) -> Result<UsageTotals> {
+    let conn = db.get_connection()?;
+    conn.query_row(
+        "SELECT COALESCE(SUM(input_tokens + output_tokens), 0), COALESCE(SUM(cost_usd), 0.0)
+         FROM token_usage
+         WHERE group_folder = ?1 AND (?2 IS NULL OR user = ?2) AND recorded_at >= ?3",
+        rusqlite::params![group_folder, user, since],
+        |row| {
+            Ok(UsageTotals {
+                tokens: row.get::<_, i64>(0)? as u64,
+                cost_usd: row.get(1)?,
+            })
+        },
+    )
+    .map_err(|e| NuClawError::Database {
+        message: format!("Failed to sum usage: {}", e),
+    })
+}
+
+/// Aggregation period for usage reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsagePeriod {
+    Daily,
+    Monthly,
+}
+
+impl UsagePeriod {
+    /// Length of the `recorded_at` prefix that identifies the period
+    fn key_len(self) -> i64 {
+        match self {
+            UsagePeriod::Daily => 10,
+            UsagePeriod::Monthly => 7,
+        }
+    }
+
+    /// Key of the current period (`YYYY-MM-DD` or `YYYY-MM`, UTC)
+    pub fn current_key(self) -> String {
+        let now = chrono::Utc::now();
+        match self {
+            UsagePeriod::Daily => now.format("%Y-%m-%d").to_string(),
+            UsagePeriod::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+impl FromStr for UsagePeriod {
+    type Err = NuClawError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "daily" | "day" => Ok(UsagePeriod::Daily),
+            "monthly" | "month" => Ok(UsagePeriod::Monthly),
+            _ => Err(NuClawError::Validation {
+                message: format!("Invalid usage period '{}': expected daily or monthly", s),
+            }),
+        }
+    }
+}
+
+/// Aggregated usage for one period, group, user and model
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageSummary {
+    /// `YYYY-MM-DD` or `YYYY-MM` (UTC)
+    pub period: String,
+    pub group_folder: String,
+    pub user: String,
+    pub model: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Aggregate usage per period, newest first, optionally for one group
+pub fn usage_summary(db: &Database, period: UsagePeriod, group_folder: Option<&str>) -> Result<Vec<UsageSummary>> {
+    let conn = db.get_connection()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT substr(recorded_at, 1, ?1) AS period, group_folder, user, model,
+                    COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+             FROM token_usage
+             WHERE ?2 IS NULL OR group_folder = ?2
+             GROUP BY period, group_folder, user, model
+             ORDER BY period DESC, group_folder, user, model",
+        )
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to prepare usage query: {}", e),
+        })?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![period.key_len(), group_folder], |row| {
+            Ok(UsageSummary {
+                period: row.get(0)?,
+                group_folder: row.get(1)?,
+                user: row.get(2)?,
+                model: row.get(3)?,
+                requests: row.get::<_, i64>(4)? as u64,
+                input_tokens: row.get::<_, i64>(5)? as u64,
+                output_tokens: row.get::<_, i64>(6)? as u64,
+                cost_usd: row.get(7)?,
+            })
+        })
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to query usage: {}", e),
+        })?;
+
+    rows.collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| NuClawError::Database {
+            message: format!("Failed to read usage row: {}", e),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{remove_test_db, test_db};
+
+    fn usage(input_tokens: u64, output_tokens: u64) -> Usage {
+        Usage {
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    #[test]
+    fn test_price_longest_prefix() {
+        let table = PriceTable::builtin();
+        assert_eq!(table.price("gpt-4o-mini-2024-07-18").unwrap().input, 0.15);
+        assert_eq!(table.price("gpt-4o-2024-08-06").unwrap().input, 2.5);
+        assert_eq!(table.price("anthropic/claude-sonnet-4-20250514").unwrap().output, 15.0);
+        assert!(table.price("llama3").is_none());
+    }
+
+    #[test]
+    fn test_cost() {
+        let table = PriceTable::builtin().with_price("local", ModelPrice { input: 1.0, output: 2.0 });
+        let cost = table.cost("local-7b", &usage(1_000_000, 500_000));
+        assert!((cost - 2.0).abs() < 1e-9);
+        assert_eq!(table.cost("unknown", &usage(10, 10)), 0.0);
+    }
+
+    #[test]
+    fn test_price_table_from_workflow_overrides() {
+        let mut workflow = WorkflowConfig::default();
+        workflow
+            .pricing
+            .insert("gpt-4o".to_string(), ModelPrice { input: 1.0, output: 1.0 });
+        let table = PriceTable::from_workflow(&workflow);
+        assert_eq!(table.price("gpt-4o").unwrap().input, 1.0);
+        assert_eq!(table.price("gpt-4o-mini").unwrap().input, 0.15);
+    }
+
+    #[test]
+    fn test_usage_period_parse() {
+        assert_eq!("daily".parse::<UsagePeriod>().unwrap(), UsagePeriod::Daily);
+        assert_eq!("Monthly".parse::<UsagePeriod>().unwrap(), UsagePeriod::Monthly);
+        assert!("weekly".parse::<UsagePeriod>().is_err());
+    }
+
+    #[test]
+    fn test_record_and_summarize() {
+        let (db, path) = test_db("test_usage_summary.db");
+        let record = |group: &str, user: &str, tokens: Usage| UsageRecord {
+            group_folder: group.to_string(),
+            chat_jid: "telegram:1".to_string(),
+            user: user.to_string(),
+            provider: Some("anthropic".to_string()),
+            model: "claude-sonnet-4".to_string(),
+            usage: tokens,
+            cost_usd: 0.5,
+        };
+        record_usage(&db, &record("team-a", "alice", usage(100, 10))).unwrap();
+        record_usage(&db, &record("team-a", "alice", usage(50, 5))).unwrap();
+        record_usage(&db, &record("team-b", "bob", usage(1, 1))).unwrap();
+
+        let daily = usage_summary(&db, UsagePeriod::Daily, None).unwrap();
+        assert_eq!(daily.len(), 2);
+        let alice = daily.iter().find(|s| s.user == "alice").unwrap();
+        assert_eq!(alice.requests, 2);
+        assert_eq!(alice.input_tokens, 150);
+        assert_eq!(alice.output_tokens, 15);
+        assert!((alice.cost_usd - 1.0).abs() < 1e-9);
+        assert_eq!(alice.period.len(), 10);
+
+        let monthly = usage_summary(&db, UsagePeriod::Monthly, Some("team-b")).unwrap();
+        assert_eq!(monthly.len(), 1);
+        assert_eq!(monthly[0].period.len(), 7);
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_usage_since() {
+        let (db, path) = test_db("test_usage_since.db");
+        let record = |user: &str, tokens: u64| UsageRecord {
+            group_folder: "main".to_string(),
+            chat_jid: "telegram:1".to_string(),
+            user: user.to_string(),
+            provider: None,
+            model: "gpt-4o".to_string(),
+            usage: usage(tokens, 0),
+            cost_usd: 0.25,
+        };
+        record_usage(&db, &record("alice", 100)).unwrap();
+        record_usage(&db, &record("bob", 50)).unwrap();
+
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let group = usage_since(&db, "main", None, &today).unwrap();
+        assert_eq!(group.tokens, 150);
+        assert!((group.cost_usd - 0.5).abs() < 1e-9);
+        assert_eq!(usage_since(&db, "main", Some("bob"), &today).unwrap().tokens, 50);
+        assert_eq!(usage_since(&db, "main", None, "9999").unwrap(), UsageTotals::default());
+
+        remove_test_db(&path);
+    }
+
+    #[test]
+    fn test_record_output_usage_skips_missing_usage() {
+        let (db, path) = test_db("test_usage_output.db");
+        let mut output = ContainerOutput {
+            status: "success".to_string(),
+            result: Some("hi".to_string()),
+            new_session_id: None,
+            error: None,
+            tools_used: Vec::new(),
+            provider: Some("openai".to_string()),
+            model: Some("gpt-4o".to_string()),
+            usage: None,
+            artifacts: Vec::new(),
+        };
+        record_output_usage(&db, "main", "telegram:1", "alice", &output);
+        assert!(usage_summary(&db, UsagePeriod::Daily, None).unwrap().is_empty());
+
+        output.usage = Some(usage(1_000_000, 0));
+        record_output_usage(&db, "main", "telegram:1", "alice", &output);
+        let summary = usage_summary(&db, UsagePeriod::Daily, None).unwrap();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].model, "gpt-4o");
+
+        remove_test_db(&path);
+    }
+}
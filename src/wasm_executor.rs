@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use extism::{Manifest, Plugin, Wasm};
 
+use crate::secrets::current_tool_secrets;
 use crate::skills::{Skill, SkillType};
 use crate::tool_registry::{ToolError, ToolResult};
 
@@ -50,6 +51,19 @@ impl Default for WasmExecutor {
     }
 }
 
+/// Group secrets the skill lists under `secrets` in its config, as plugin config
+fn declared_secrets(skill: &Skill) -> Vec<(String, String)> {
+    let Some(names) = skill.config.get("secrets").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    let secrets = current_tool_secrets();
+    names
+        .iter()
+        .filter_map(|name| name.as_str())
+        .filter_map(|name| secrets.get(name).map(|value| (name.to_string(), value.to_string())))
+        .collect()
+}
+
 #[async_trait]
 impl SkillExecutor for WasmExecutor {
     async fn execute_skill(&self, skill: &Skill, args: serde_json::Value) -> Result<ToolResult, ToolError> {
@@ -60,7 +74,7 @@ impl SkillExecutor for WasmExecutor {
         let wasm = self.get_wasm(skill)?;
         let function_name = self.get_function_name(skill);
 
-        let manifest = Manifest::new([wasm]);
+        let manifest = Manifest::new([wasm]).with_config(declared_secrets(skill).into_iter());
         let mut plugin = Plugin::new(&manifest, [], true)
             .map_err(|e| ToolError::ExecutionFailed(format!("Failed to load WASM plugin: {}", e)))?;
 
@@ -107,4 +121,18 @@ mod tests {
         let name = executor.get_function_name(&skill);
         assert_eq!(name, "custom_func");
     }
+
+    #[tokio::test]
+    async fn test_declared_secrets() {
+        let mut skill = Skill::new("test", "test", "content");
+        skill.config.insert("secrets".to_string(), serde_json::json!(["GITHUB_TOKEN", "MISSING"]));
+        assert!(declared_secrets(&skill).is_empty());
+
+        let secrets = crate::secrets::GroupSecrets::new(vec![
+            ("GITHUB_TOKEN".to_string(), "ghp_abc".to_string()),
+            ("OTHER".to_string(), "other".to_string()),
+        ]);
+        let declared = crate::secrets::with_tool_secrets(secrets, async { declared_secrets(&skill) }).await;
+        assert_eq!(declared, vec![("GITHUB_TOKEN".to_string(), "ghp_abc".to_string())]);
+    }
 }
@@ -203,23 +203,61 @@ impl WhatsAppClient {
                     message: format!("Group not found: {}", msg.chat_jid),
                 })?;
 
+        if crate::session::is_reset_command(&content) {
+            crate::session::reset_session(&self.db, &group_folder, &msg.chat_jid, &msg.timestamp)?;
+            self.send_message(&msg.chat_jid, crate::session::SESSION_RESET_REPLY).await?;
+            return Ok(Some(crate::session::SESSION_RESET_REPLY.to_string()));
+        }
+
         let is_group = !msg.chat_jid.ends_with("@s.whatsapp.net");
+        let scope = crate::task_scheduler::TaskScope::new(&group_folder, !is_group);
+        if let Some(reply) =
+            crate::task_commands::handle_task_command(&self.db, &scope, &msg.chat_jid, &content)
+        {
+            self.send_message(&msg.chat_jid, &reply).await?;
+            return Ok(Some(reply));
+        }
+
+        let budget = crate::budget::check_budget_for(&self.db, &group_folder, &msg.sender);
+        if let crate::budget::BudgetStatus::Exhausted(notice) = budget {
+            self.send_message(&msg.chat_jid, &notice).await?;
+            return Ok(Some(notice));
+        }
+
+        let session_id = crate::session::active_session(&self.db, &group_folder, &msg.chat_jid)
+            .and_then(|s| s.session_id);
+
         let event = crate::types::AppEvent::ChatMessage {
             platform: "whatsapp".to_string(),
             chat_id: msg.chat_jid.clone(),
             user_id: msg.sender.clone(),
             message_id: msg.id.clone(),
             message_text: content,
-            group_folder,
+            group_folder: group_folder.clone(),
             is_group,
         };
 
-        let result = tokio::time::timeout(container_timeout(), self.router.dispatch(event)).await;
+        let result = tokio::time::timeout(
+            container_timeout(),
+            self.router.dispatch_in_session(event, session_id),
+        )
+        .await;
 
         match result {
             Ok(Ok(output)) => {
+                crate::usage::record_output_usage(&self.db, &group_folder, &msg.chat_jid, &msg.sender, &output);
+                crate::session::record_session(&self.db, &group_folder, &msg.chat_jid, &msg.timestamp, &output);
+                // The MCP bridge only sends text, so produced files are listed instead
+                let files = crate::artifacts::artifact_summary(&output.artifacts);
                 if let Some(response) = output.result {
                     self.send_message(&msg.chat_jid, &response).await?;
+                    if let Some(files) = files {
+                        self.send_message(&msg.chat_jid, &files).await?;
+                    }
+                    if let crate::budget::BudgetStatus::Warning(warning) = budget {
+                        self.send_message(&msg.chat_jid, &warning.message).await?;
+                        warning.mark_sent();
+                    }
                     return Ok(Some(response));
                 }
             }
@@ -240,30 +278,7 @@ impl WhatsAppClient {
 
     /// Send a message
     pub async fn send_message(&self, jid: &str, content: &str) -> Result<()> {
-        let mcp_url = get_mcp_url()?;
-
-        let payload = serde_json::json!({
-            "jid": jid,
-            "message": content,
-        });
-
-        let response = reqwest::Client::new()
-            .post(format!("{}/messages/send", mcp_url))
-            .json(&payload)
-            .timeout(Duration::from_secs(30))
-            .send()
-            .await
-            .map_err(|e| NuClawError::WhatsApp {
-                message: format!("Failed to send message: {}", e),
-            })?;
-
-        if !response.status().is_success() {
-            return Err(NuClawError::WhatsApp {
-                message: format!("Failed to send message: status {}", response.status()),
-            });
-        }
-
-        Ok(())
+        send_whatsapp_message(jid, content).await
     }
 
     async fn is_duplicate_message(&self, msg: &NewMessage) -> bool {
@@ -316,6 +331,34 @@ fn get_mcp_url() -> Result<String> {
     })
 }
 
+/// Send a message through the WhatsApp MCP server
+pub async fn send_whatsapp_message(jid: &str, content: &str) -> Result<()> {
+    let mcp_url = get_mcp_url()?;
+
+    let payload = serde_json::json!({
+        "jid": jid,
+        "message": content,
+    });
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/messages/send", mcp_url))
+        .json(&payload)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| NuClawError::WhatsApp {
+            message: format!("Failed to send message: {}", e),
+        })?;
+
+    if !response.status().is_success() {
+        return Err(NuClawError::WhatsApp {
+            message: format!("Failed to send message: status {}", response.status()),
+        });
+    }
+
+    Ok(())
+}
+
 pub fn load_router_state() -> RouterState {
     let state_path = data_dir().join("router_state.json");
     load_json(&state_path, RouterState::default())
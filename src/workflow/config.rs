@@ -3,6 +3,7 @@
 //! These types define the structure of WORKFLOW.md configuration file.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Main workflow configuration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -23,6 +24,18 @@ pub struct WorkflowConfig {
     #[serde(default)]
     pub hooks: HookSettings,
 
+    /// Per-group overrides, keyed by group folder
+    #[serde(default)]
+    pub groups: HashMap<String, GroupSettings>,
+
+    /// Model prices keyed by model name prefix (overrides built-in prices)
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPrice>,
+
+    /// Token and spend budgets applied to every group
+    #[serde(default)]
+    pub budget: BudgetSettings,
+
     /// Default prompt template (Markdown body after front matter)
     #[serde(default)]
     pub prompt_template: String,
@@ -68,9 +81,42 @@ pub struct AgentSettings {
     #[serde(default = "default_max_retries")]
     pub max_retries: usize,
 
-    /// Retry backoff in milliseconds
+    /// Maximum retry backoff in milliseconds
     #[serde(default = "default_retry_backoff_ms")]
     pub retry_backoff_ms: u64,
+
+    /// LLM provider for provider mode (defaults to the detected provider)
+    #[serde(default)]
+    pub provider: Option<String>,
+
+    /// Model override for provider mode
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Ordered providers to fall back to when the primary fails
+    #[serde(default)]
+    pub failover: Vec<FailoverTarget>,
+}
+
+/// One entry of the provider failover chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailoverTarget {
+    /// Provider name from the `PROVIDERS` table
+    pub provider: String,
+
+    /// Model to request (defaults to the provider's configured model)
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    /// Price per million input tokens
+    pub input: f64,
+
+    /// Price per million output tokens
+    pub output: f64,
 }
 
 /// Container execution settings
@@ -95,6 +141,148 @@ pub struct ContainerSettings {
     /// Maximum pool size
     #[serde(default)]
     pub pool_max_size: Option<usize>,
+
+    /// Resource limits and network policy for every agent container
+    #[serde(flatten)]
+    pub limits: ContainerLimits,
+}
+
+/// Resource limits and network policy for agent containers (unset fields are unlimited)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ContainerLimits {
+    /// CPU cores the container may use (e.g. 1.5)
+    #[serde(default)]
+    pub cpus: Option<f64>,
+
+    /// Memory limit such as "512m" or "2g"; swap is disabled when set
+    #[serde(default)]
+    pub memory: Option<String>,
+
+    /// Maximum number of processes and threads
+    #[serde(default)]
+    pub pids_limit: Option<u64>,
+
+    /// Size of the container's writable layer such as "10g"
+    /// (requires a storage driver with quota support)
+    #[serde(default)]
+    pub disk: Option<String>,
+
+    /// Network mode: "none", "bridge", "host" or a named network
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+impl ContainerLimits {
+    /// Apply `overrides` on top of these limits, field by field
+    pub fn merged_with(&self, overrides: &ContainerLimits) -> ContainerLimits {
+        ContainerLimits {
+            cpus: overrides.cpus.or(self.cpus),
+            memory: overrides.memory.clone().or_else(|| self.memory.clone()),
+            pids_limit: overrides.pids_limit.or(self.pids_limit),
+            disk: overrides.disk.clone().or_else(|| self.disk.clone()),
+            network: overrides.network.clone().or_else(|| self.network.clone()),
+        }
+    }
+}
+
+/// Per-group settings overriding the global configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GroupSettings {
+    /// LLM provider for this group
+    #[serde(default)]
+    pub provider: Option<String>,
+
+    /// Model for this group
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Budget overrides for this group (unset fields use the global budget)
+    #[serde(default)]
+    pub budget: Option<BudgetSettings>,
+
+    /// Container limit overrides for this group (unset fields use `container`)
+    #[serde(default)]
+    pub container: Option<ContainerLimits>,
+
+    /// Extra host directories to mount, checked against the mount allowlist
+    #[serde(default)]
+    pub mounts: Vec<GroupMount>,
+
+    /// IANA timezone the group's scheduled tasks are evaluated in (defaults to `TZ`)
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// An additional host directory mounted into a group's container
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupMount {
+    /// Host path (`~` expands to the home directory)
+    pub host_path: String,
+
+    /// Name under `/workspace/extra` (defaults to the host directory name)
+    #[serde(default)]
+    pub container_path: Option<String>,
+
+    /// Mount read-write; only honored when the allowlist permits it
+    #[serde(default)]
+    pub read_write: bool,
+}
+
+/// Daily/monthly token and spend budgets (unset limits are unlimited)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct BudgetSettings {
+    /// Tokens the whole group may use per UTC day
+    #[serde(default)]
+    pub daily_tokens: Option<u64>,
+
+    /// Tokens the whole group may use per UTC month
+    #[serde(default)]
+    pub monthly_tokens: Option<u64>,
+
+    /// USD the whole group may spend per UTC day
+    #[serde(default)]
+    pub daily_spend_usd: Option<f64>,
+
+    /// USD the whole group may spend per UTC month
+    #[serde(default)]
+    pub monthly_spend_usd: Option<f64>,
+
+    /// Tokens a single user may use per UTC day within the group
+    #[serde(default)]
+    pub user_daily_tokens: Option<u64>,
+
+    /// Tokens a single user may use per UTC month within the group
+    #[serde(default)]
+    pub user_monthly_tokens: Option<u64>,
+
+    /// Fraction of a budget at which users are warned (default 0.8)
+    #[serde(default)]
+    pub warn_ratio: Option<f64>,
+}
+
+impl BudgetSettings {
+    /// Apply `overrides` on top of these settings, field by field
+    pub fn merged_with(&self, overrides: &BudgetSettings) -> BudgetSettings {
+        BudgetSettings {
+            daily_tokens: overrides.daily_tokens.or(self.daily_tokens),
+            monthly_tokens: overrides.monthly_tokens.or(self.monthly_tokens),
+            daily_spend_usd: overrides.daily_spend_usd.or(self.daily_spend_usd),
+            monthly_spend_usd: overrides.monthly_spend_usd.or(self.monthly_spend_usd),
+            user_daily_tokens: overrides.user_daily_tokens.or(self.user_daily_tokens),
+            user_monthly_tokens: overrides.user_monthly_tokens.or(self.user_monthly_tokens),
+            warn_ratio: overrides.warn_ratio.or(self.warn_ratio),
+        }
+    }
+
+    /// Whether any limit is configured
+    pub fn is_limited(&self) -> bool {
+        self.daily_tokens.is_some()
+            || self.monthly_tokens.is_some()
+            || self.daily_spend_usd.is_some()
+            || self.monthly_spend_usd.is_some()
+            || self.user_daily_tokens.is_some()
+            || self.user_monthly_tokens.is_some()
+    }
 }
 
 /// Hook scripts for workspace lifecycle
@@ -138,6 +326,9 @@ impl Default for AgentSettings {
             timeout_ms: default_timeout_ms(),
             max_retries: default_max_retries(),
             retry_backoff_ms: default_retry_backoff_ms(),
+            provider: None,
+            model: None,
+            failover: Vec::new(),
         }
     }
 }
@@ -166,6 +357,63 @@ impl WorkflowConfig {
         Self::default()
     }
 
+    /// Get the overrides for a group folder, if any
+    pub fn group(&self, folder: &str) -> Option<&GroupSettings> {
+        self.groups.get(folder)
+    }
+
+    /// Provider for a group: group override, then the agent-wide setting
+    pub fn provider_for_group(&self, folder: &str) -> Option<String> {
+        self.group(folder)
+            .and_then(|g| g.provider.clone())
+            .or_else(|| self.agent.provider.clone())
+    }
+
+    /// Model for a group: group override, then the agent-wide setting
+    pub fn model_for_group(&self, folder: &str) -> Option<String> {
+        self.group(folder)
+            .and_then(|g| g.model.clone())
+            .or_else(|| self.agent.model.clone())
+    }
+
+    /// Budget for a group: group overrides on top of the global budget
+    pub fn budget_for_group(&self, folder: &str) -> BudgetSettings {
+        match self.group(folder).and_then(|g| g.budget.as_ref()) {
+            Some(overrides) => self.budget.merged_with(overrides),
+            None => self.budget.clone(),
+        }
+    }
+
+    /// Timezone configured for a group, if any
+    pub fn timezone_for_group(&self, folder: &str) -> Option<String> {
+        self.group(folder).and_then(|g| g.timezone.clone())
+    }
+
+    /// Container limits for a group: group overrides on top of `container`
+    pub fn container_limits_for_group(&self, folder: &str) -> ContainerLimits {
+        match self.group(folder).and_then(|g| g.container.as_ref()) {
+            Some(overrides) => self.container.limits.merged_with(overrides),
+            None => self.container.limits.clone(),
+        }
+    }
+
+    /// Failover chain for a group: its primary provider followed by `agent.failover`
+    pub fn failover_chain(&self, folder: &str, primary: Option<String>) -> Vec<FailoverTarget> {
+        let mut chain = Vec::new();
+        if let Some(provider) = primary {
+            chain.push(FailoverTarget {
+                provider,
+                model: self.model_for_group(folder),
+            });
+        }
+        for target in &self.agent.failover {
+            if !chain.contains(target) {
+                chain.push(target.clone());
+            }
+        }
+        chain
+    }
+
     /// Check if any channel is enabled
     pub fn has_enabled_channel(&self) -> bool {
         self.channels
@@ -426,4 +674,119 @@ prompt_template: |
         let config: WorkflowConfig = serde_yaml::from_str("").unwrap();
         assert_eq!(config.agent.max_concurrent, 5); // default
     }
+
+    #[test]
+    fn test_group_provider_overrides() {
+        let yaml = r#"
+agent:
+  provider: openai
+  model: gpt-4o-mini
+
+groups:
+  research:
+    provider: openrouter
+    model: anthropic/claude-sonnet-4
+  ops:
+    model: gpt-4o
+"#;
+        let config: WorkflowConfig = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(config.provider_for_group("research"), Some("openrouter".to_string()));
+        assert_eq!(config.model_for_group("research"), Some("anthropic/claude-sonnet-4".to_string()));
+        assert_eq!(config.provider_for_group("ops"), Some("openai".to_string()));
+        assert_eq!(config.model_for_group("ops"), Some("gpt-4o".to_string()));
+        assert_eq!(config.provider_for_group("other"), Some("openai".to_string()));
+        assert_eq!(config.model_for_group("other"), Some("gpt-4o-mini".to_string()));
+        assert!(WorkflowConfig::default().provider_for_group("other").is_none());
+    }
+
+    #[test]
+    fn test_failover_chain() {
+        let yaml = r#"
+agent:
+  model: gpt-4o
+  failover:
+    - provider: openrouter
+      model: openai/gpt-4o
+    - provider: anthropic
+"#;
+        let config: WorkflowConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.agent.failover.len(), 2);
+        assert!(config.agent.failover[1].model.is_none());
+
+        let chain = config.failover_chain("main", Some("openai".to_string()));
+        let names: Vec<&str> = chain.iter().map(|t| t.provider.as_str()).collect();
+        assert_eq!(names, vec!["openai", "openrouter", "anthropic"]);
+        assert_eq!(chain[0].model, Some("gpt-4o".to_string()));
+
+        assert_eq!(config.failover_chain("main", None).len(), 2);
+    }
+
+    #[test]
+    fn test_budget_for_group() {
+        let yaml = r#"
+budget:
+  daily_tokens: 100000
+  user_daily_tokens: 20000
+groups:
+  research:
+    budget:
+      daily_tokens: 500000
+      monthly_spend_usd: 50.0
+"#;
+        let config: WorkflowConfig = serde_yaml::from_str(yaml).unwrap();
+
+        let research = config.budget_for_group("research");
+        assert_eq!(research.daily_tokens, Some(500000));
+        assert_eq!(research.user_daily_tokens, Some(20000));
+        assert_eq!(research.monthly_spend_usd, Some(50.0));
+
+        let main = config.budget_for_group("main");
+        assert_eq!(main.daily_tokens, Some(100000));
+        assert!(main.monthly_spend_usd.is_none());
+        assert!(main.is_limited());
+        assert!(!WorkflowConfig::default().budget_for_group("main").is_limited());
+    }
+
+    #[test]
+    fn test_container_limits_for_group() {
+        let yaml = r#"
+container:
+  image: "anthropic/claude-code:latest"
+  cpus: 1.5
+  memory: "1g"
+  pids_limit: 256
+  network: "bridge"
+groups:
+  sandboxed:
+    container:
+      memory: "512m"
+      network: "none"
+"#;
+        let config: WorkflowConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.container.image.as_deref(), Some("anthropic/claude-code:latest"));
+
+        let sandboxed = config.container_limits_for_group("sandboxed");
+        assert_eq!(sandboxed.cpus, Some(1.5));
+        assert_eq!(sandboxed.memory.as_deref(), Some("512m"));
+        assert_eq!(sandboxed.pids_limit, Some(256));
+        assert_eq!(sandboxed.network.as_deref(), Some("none"));
+
+        let main = config.container_limits_for_group("main");
+        assert_eq!(main.memory.as_deref(), Some("1g"));
+        assert!(main.disk.is_none());
+        assert_eq!(WorkflowConfig::default().container_limits_for_group("main"), ContainerLimits::default());
+    }
+
+    #[test]
+    fn test_timezone_for_group() {
+        let yaml = r#"
+groups:
+  berlin:
+    timezone: Europe/Berlin
+"#;
+        let config: WorkflowConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.timezone_for_group("berlin").as_deref(), Some("Europe/Berlin"));
+        assert!(config.timezone_for_group("main").is_none());
+    }
 }
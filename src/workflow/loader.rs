@@ -130,6 +130,26 @@ impl WorkflowLoader {
         Self::validate_config(&config)?;
         Ok((config, template))
     }
+
+    /// Load the config at `path`, falling back to defaults if missing or invalid
+    pub fn load_or_default(path: &Path) -> WorkflowConfig {
+        if !path.exists() {
+            return WorkflowConfig::default();
+        }
+
+        match Self::load_and_validate(path) {
+            Ok((mut config, template)) => {
+                if config.prompt_template.is_empty() {
+                    config.prompt_template = template;
+                }
+                config
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring invalid workflow config {}: {}", path.display(), e);
+                WorkflowConfig::default()
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -369,4 +389,22 @@ Prompt here.
         let (config, _) = result.unwrap();
         assert!(config.has_enabled_channel());
     }
+
+    #[test]
+    fn test_load_or_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let workflow_path = temp_dir.path().join("WORKFLOW.md");
+
+        let config = WorkflowLoader::load_or_default(&workflow_path);
+        assert_eq!(config.agent.max_concurrent, 5);
+
+        fs::write(&workflow_path, "---\nagent:\n  max_retries: 99\n---\n").unwrap();
+        let config = WorkflowLoader::load_or_default(&workflow_path);
+        assert_eq!(config.agent.max_retries, 3);
+
+        fs::write(&workflow_path, "---\nagent:\n  provider: openai\n---\nBe brief.\n").unwrap();
+        let config = WorkflowLoader::load_or_default(&workflow_path);
+        assert_eq!(config.agent.provider, Some("openai".to_string()));
+        assert_eq!(config.prompt_template, "Be brief.");
+    }
 }
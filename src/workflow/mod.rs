@@ -13,8 +13,69 @@ pub mod watcher;
 
 // Re-exports
 pub use config::{
-    AgentSettings, ChannelConfig, ChannelSettings, ContainerSettings, HookSettings, WorkflowConfig,
+    AgentSettings, BudgetSettings, ChannelConfig, ChannelSettings, ContainerLimits, ContainerSettings, FailoverTarget,
+    GroupMount, GroupSettings, HookSettings, ModelPrice, WorkflowConfig,
 };
 pub use hooks::{HookRunner, HookType};
 pub use loader::{WorkflowLoader, WorkflowLoaderError};
 pub use watcher::WorkflowWatcher;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Load the workflow config from the configured WORKFLOW.md path
+pub fn load_workflow_config() -> WorkflowConfig {
+    WorkflowLoader::load_or_default(&crate::config::workflow_path())
+}
+
+/// Shared workflow config, reloaded when WORKFLOW.md changes
+///
+/// Only the file's modification time is checked per call, so this suits
+/// per-message paths where `load_workflow_config` would re-parse the file.
+pub fn workflow_config() -> Arc<WorkflowConfig> {
+    cached_workflow_config(&crate::config::workflow_path())
+}
+
+type CachedConfig = (PathBuf, Option<SystemTime>, Arc<WorkflowConfig>);
+
+fn cached_workflow_config(path: &Path) -> Arc<WorkflowConfig> {
+    static CACHE: OnceLock<Mutex<Option<CachedConfig>>> = OnceLock::new();
+
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut cache = CACHE
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_path, cached_modified, config)) = cache.as_ref() {
+        if cached_path == path && *cached_modified == modified {
+            return config.clone();
+        }
+    }
+    let config = Arc::new(WorkflowLoader::load_or_default(path));
+    *cache = Some((path.to_path_buf(), modified, config.clone()));
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cached_workflow_config_reloads_on_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("WORKFLOW.md");
+        std::fs::write(&path, "---\nagent:\n  max_concurrent: 3\n---\n").unwrap();
+
+        let first = cached_workflow_config(&path);
+        assert_eq!(first.agent.max_concurrent, 3);
+        assert!(Arc::ptr_eq(&first, &cached_workflow_config(&path)));
+
+        std::fs::write(&path, "---\nagent:\n  max_concurrent: 5\n---\n").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(cached_workflow_config(&path).agent.max_concurrent, 5);
+    }
+}
@@ -374,6 +374,12 @@ mod e2e_tests {
             last_result: None,
             status: "active".to_string(),
             created_at: "2025-01-01T00:00:00Z".to_string(),
+            timezone: None,
+            misfire_policy: None,
+            max_retries: None,
+            retry_backoff_ms: None,
+            failure_count: 0,
+            delivery: None,
         };
 
         assert_eq!(task.id, "test_task_1");
@@ -457,6 +463,8 @@ mod e2e_tests {
             is_main: true,
             is_scheduled_task: false,
             session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
         };
 
         assert_eq!(input.prompt, "Test prompt");
@@ -602,6 +610,8 @@ mod e2e_tests {
             is_main: true,
             is_scheduled_task: false,
             session_workspace_id: None,
+            history: Vec::new(),
+            run_id: None,
         };
 
         let json = serde_json::to_string(&input).expect("Failed to serialize");